@@ -0,0 +1,263 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use libc::*;
+use crate::raw::{CURLALTSVC_H1, CURLALTSVC_H2, CURLALTSVC_H3};
+
+// How long an alternative is used if the header doesn't say
+const DEFAULT_MAX_AGE: i64 = 24 * 60 * 60;
+const DATE_FORMAT: &str = "%Y%m%d %H:%M:%S";
+const FILE_HEADER: &str = "# Your alt-svc cache. https://curl.se/docs/alt-svc.html\n\
+                           # This file was generated by libcurl! Edit at your own risk.\n";
+
+/// The protocol an origin or an alternative service is spoken with.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Alpn {
+    H1,
+    H2,
+    H3,
+}
+
+impl Alpn {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "h1" | "http/1.1" => Some(Alpn::H1),
+            "h2" => Some(Alpn::H2),
+            "h3" => Some(Alpn::H3),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Alpn::H1 => "h1",
+            Alpn::H2 => "h2",
+            Alpn::H3 => "h3",
+        }
+    }
+
+    // The CURLOPT_ALTSVC_CTRL bit allowing it
+    fn bit(self) -> c_long {
+        match self {
+            Alpn::H1 => CURLALTSVC_H1 as c_long,
+            Alpn::H2 => CURLALTSVC_H2 as c_long,
+            Alpn::H3 => CURLALTSVC_H3 as c_long,
+        }
+    }
+}
+
+/// Where to connect to instead of an origin.
+#[derive(Clone)]
+pub struct Alternative {
+    pub alpn: Alpn,
+    pub host: String,
+    pub port: u16,
+}
+
+struct Entry {
+    src_alpn: Alpn,
+    src_host: String,
+    src_port: u16,
+    dst: Alternative,
+    expires: DateTime<Utc>,
+    persist: bool,
+}
+
+impl Entry {
+    fn is_from(&self, alpn: Alpn, host: &str, port: u16) -> bool {
+        self.src_alpn == alpn && self.src_port == port && self.src_host.eq_ignore_ascii_case(host)
+    }
+}
+
+/// The per-handle Alt-Svc cache, see RFC 7838.
+#[derive(Default)]
+pub struct Cache {
+    entries: Vec<Entry>,
+    // The CURLOPT_ALTSVC file the entries were loaded from
+    file: Option<String>,
+}
+
+impl Cache {
+    /// Reads a cache file in libcurl's format, unless it was already loaded.
+    ///
+    /// A missing file is an empty cache, it is created when saving.
+    pub fn load(&mut self, path: &str) {
+        if self.file.as_deref() == Some(path) {
+            return;
+        }
+
+        self.file = Some(path.to_owned());
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+
+        let now = Utc::now();
+
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            if let Some(entry) = parse_line(&line).filter(|entry| entry.expires > now) {
+                self.entries.push(entry);
+            }
+        }
+    }
+
+    /// Writes the unexpired entries in libcurl's format.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let now = Utc::now();
+        let mut contents = String::from(FILE_HEADER);
+
+        for entry in self.entries.iter().filter(|entry| entry.expires > now) {
+            contents.push_str(&format!(
+                "{} {} {} {} {} {} \"{}\" {} 0\n",
+                entry.src_alpn.as_str(),
+                bracket(&entry.src_host),
+                entry.src_port,
+                entry.dst.alpn.as_str(),
+                bracket(&entry.dst.host),
+                entry.dst.port,
+                entry.expires.format(DATE_FORMAT),
+                entry.persist as u8,
+            ));
+        }
+
+        fs::write(path, contents)
+    }
+
+    /// Applies an Alt-Svc header received from `host` and `port` over `alpn`.
+    ///
+    /// The alternatives replace the ones known for the origin, "clear" just removes them.
+    pub fn update(&mut self, alpn: Alpn, host: &str, port: u16, value: &str) {
+        let value = value.trim();
+
+        if value.eq_ignore_ascii_case("clear") {
+            self.entries.retain(|entry| !entry.is_from(alpn, host, port));
+            return;
+        }
+
+        let now = Utc::now();
+        let mut flushed = false;
+
+        for alternative in value.split(',') {
+            let mut params = alternative.split(';');
+
+            let (protocol, authority) = match params.next().and_then(split_param) {
+                Some(pair) => pair,
+                None => continue,
+            };
+
+            // Unknown protocols are ignored, like any malformed alternative
+            let dst_alpn = match Alpn::parse(protocol) {
+                Some(dst_alpn) => dst_alpn,
+                None => continue,
+            };
+
+            let (dst_host, dst_port) = match split_authority(authority.trim_matches('"')) {
+                Some((dst_host, dst_port)) if dst_host.is_empty() => (host.to_owned(), dst_port),
+                Some(authority) => authority,
+                None => continue,
+            };
+
+            let mut max_age = DEFAULT_MAX_AGE;
+            let mut persist = false;
+
+            for (name, value) in params.filter_map(split_param) {
+                let value = value.trim_matches('"');
+
+                if name.eq_ignore_ascii_case("ma") {
+                    max_age = value.parse().unwrap_or(max_age);
+                } else if name.eq_ignore_ascii_case("persist") {
+                    persist = value == "1";
+                }
+            }
+
+            if !flushed {
+                self.entries.retain(|entry| !entry.is_from(alpn, host, port));
+                flushed = true;
+            }
+
+            self.entries.push(Entry {
+                src_alpn: alpn,
+                src_host: host.to_owned(),
+                src_port: port,
+                dst: Alternative { alpn: dst_alpn, host: dst_host, port: dst_port },
+                expires: now + Duration::seconds(max_age),
+                persist,
+            });
+        }
+    }
+
+    /// The alternative to connect to for `host` and `port`, out of the protocols `ctrl` allows.
+    ///
+    /// There is no QUIC transport, so h3 alternatives are only kept for the file.
+    pub fn lookup(&mut self, host: &str, port: u16, ctrl: c_long) -> Option<Alternative> {
+        let now = Utc::now();
+        self.entries.retain(|entry| entry.expires > now);
+
+        let entries = &self.entries;
+
+        [Alpn::H2, Alpn::H1].iter()
+            .filter(|alpn| ctrl & alpn.bit() != 0)
+            .filter_map(|&alpn| entries.iter().find(|entry| {
+                entry.dst.alpn == alpn && entry.src_port == port && entry.src_host.eq_ignore_ascii_case(host)
+            }))
+            .map(|entry| entry.dst.clone())
+            .next()
+    }
+}
+
+// Parses `h2 example.com 443 h3 example.com 443 "20190125 10:00:00" 0 0`
+fn parse_line(line: &str) -> Option<Entry> {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let fields: Vec<_> = line.split_whitespace().collect();
+
+    if fields.len() != 10 {
+        return None;
+    }
+
+    let date = format!("{} {}", fields[6], fields[7]);
+    let expires = NaiveDateTime::parse_from_str(date.trim_matches('"'), DATE_FORMAT).ok()?;
+
+    Some(Entry {
+        src_alpn: Alpn::parse(fields[0])?,
+        src_host: unbracket(fields[1]).to_owned(),
+        src_port: fields[2].parse().ok()?,
+        dst: Alternative {
+            alpn: Alpn::parse(fields[3])?,
+            host: unbracket(fields[4]).to_owned(),
+            port: fields[5].parse().ok()?,
+        },
+        expires: Utc.from_utc_datetime(&expires),
+        persist: fields[8] == "1",
+    })
+}
+
+// Splits `name=value`
+fn split_param(param: &str) -> Option<(&str, &str)> {
+    let equals = param.find('=')?;
+    Some((param[..equals].trim(), param[equals + 1..].trim()))
+}
+
+// Splits "host:port", "[v6]:port" or just ":port"
+fn split_authority(authority: &str) -> Option<(String, u16)> {
+    let colon = authority.rfind(':')?;
+    let port = authority[colon + 1..].parse().ok()?;
+
+    Some((unbracket(&authority[..colon]).to_owned(), port))
+}
+
+fn unbracket(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+fn bracket(host: &str) -> String {
+    match host.contains(':') {
+        true => format!("[{}]", host),
+        false => host.to_owned(),
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use libc::*;
+use openssl::hash::{hash, MessageDigest};
+use openssl::rand::rand_bytes;
+use hyper::Method;
+use url::Url;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use percent_encoding::percent_decode;
+use crate::{Options, netrc};
+use crate::error::Error;
+use crate::raw::CURLcode::*;
+use crate::raw::CURL_NETRC_OPTION::*;
+use crate::util::hex;
+use crate::rawx::*;
+
+pub struct Credentials {
+    pub user: String,
+    pub password: String,
+}
+
+impl Credentials {
+    // Credentials set through options win over the URL, which wins over .netrc
+    // unless CURL_NETRC_REQUIRED is set.
+    pub fn from_request(options: &Options, url: &Url) -> Result<Option<Self>, Error> {
+        if options.username.is_some() || options.password.is_some() {
+            return Ok(Some(Self {
+                user: options.username.clone().unwrap_or_default(),
+                password: options.password.clone().unwrap_or_default(),
+            }));
+        }
+
+        let decode = |value: &str| percent_decode(value.as_bytes()).decode_utf8_lossy().into_owned();
+        let user = Some(url.username()).filter(|user| !user.is_empty()).map(decode);
+        let password = url.password().map(decode);
+
+        let (user, password) = match options.netrc {
+            CURL_NETRC_IGNORED => (user, password),
+            CURL_NETRC_OPTIONAL if password.is_some() => (user, password),
+            CURL_NETRC_OPTIONAL => {
+                let entry = Self::from_netrc(options, url, user.as_deref())?;
+                (user.or(entry.login), entry.password)
+            },
+            _ => {
+                let entry = Self::from_netrc(options, url, None)?;
+                (entry.login, entry.password)
+            },
+        };
+
+        if user.is_none() && password.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            user: user.unwrap_or_default(),
+            password: password.unwrap_or_default(),
+        }))
+    }
+
+    fn from_netrc(options: &Options, url: &Url, login: Option<&str>) -> Result<netrc::Entry, Error> {
+        let none = netrc::Entry { login: None, password: None };
+
+        let path = match &options.netrc_file {
+            Some(path) => PathBuf::from(path),
+            None => match netrc::default_path() {
+                Some(path) => path,
+                None => return Ok(none),
+            },
+        };
+
+        let host = url.host_str().unwrap_or_default();
+
+        netrc::lookup(&path, host, login)
+            .map(|entry| entry.unwrap_or(none))
+            .map_err(|e| Error::new(CURLE_READ_ERROR, format!(".netrc parser error: {}", e)))
+    }
+}
+
+pub struct Challenge {
+    pub scheme: c_ulong,
+    params: Vec<(String, String)>,
+}
+
+impl Challenge {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parses all challenges of the given `WWW-Authenticate` or `Proxy-Authenticate` headers.
+pub fn challenges(headers: &HeaderMap, name: HeaderName) -> Vec<Challenge> {
+    headers.get_all(name).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(parse_challenges)
+        .collect()
+}
+
+/// Returns the `CURLAUTH_*` bits of all schemes offered by the challenges.
+pub fn available(challenges: &[Challenge]) -> c_ulong {
+    challenges.iter().fold(0, |avail, challenge| avail | challenge.scheme)
+}
+
+/// The header to send before the server asked for authentication.
+///
+/// Like libcurl, only an unambiguous choice of Basic or Bearer is sent right away.
+pub fn initial(options: &Options, credentials: Option<&Credentials>) -> Option<HeaderValue> {
+    match options.http_auth & !CURLAUTH_ONLY {
+        CURLAUTH_BASIC => credentials.and_then(basic),
+        CURLAUTH_BEARER => options.bearer.as_ref().and_then(|token| bearer(token)),
+        _ => None,
+    }
+}
+
+/// Answers the best allowed scheme from the challenges that can be answered.
+///
+/// A Digest challenge this can't answer, like one that requires `auth-int`,
+/// falls through to the next scheme.
+pub fn respond(
+    options: &Options,
+    credentials: Option<&Credentials>,
+    challenges: &[Challenge],
+    method: &Method,
+    url: &Url,
+) -> Option<HeaderValue> {
+    let allowed = options.http_auth & !CURLAUTH_ONLY;
+    let offered = |scheme| allowed & scheme != 0 && challenges.iter().any(|c| c.scheme == scheme);
+
+    let digest = || challenges.iter()
+        .filter(|c| c.scheme == CURLAUTH_DIGEST)
+        .find_map(|challenge| digest(credentials?, challenge, method, url));
+
+    if let Some(header) = offered(CURLAUTH_DIGEST).then(digest).flatten() {
+        return Some(header);
+    }
+
+    if offered(CURLAUTH_BASIC) {
+        return credentials.and_then(basic);
+    }
+
+    if offered(CURLAUTH_BEARER) {
+        return options.bearer.as_ref().and_then(|token| bearer(token));
+    }
+
+    None
+}
+
+fn basic(credentials: &Credentials) -> Option<HeaderValue> {
+    let token = base64::encode(&format!("{}:{}", credentials.user, credentials.password));
+    HeaderValue::from_str(&format!("Basic {}", token)).ok()
+}
+
+fn bearer(token: &str) -> Option<HeaderValue> {
+    HeaderValue::from_str(&format!("Bearer {}", token)).ok()
+}
+
+// Only `qop=auth` is supported, a challenge that requires `auth-int` is left unanswered.
+fn digest(credentials: &Credentials, challenge: &Challenge, method: &Method, url: &Url) -> Option<HeaderValue> {
+    let realm = challenge.param("realm").unwrap_or_default();
+    let nonce = challenge.param("nonce")?;
+    let algorithm = challenge.param("algorithm").unwrap_or("MD5");
+    let qop = match challenge.param("qop") {
+        Some(qop) if qop.split(',').any(|qop| qop.trim() == "auth") => Some("auth"),
+        Some(_) => return None,
+        None => None,
+    };
+
+    let (digest, session) = match algorithm.to_ascii_uppercase().as_str() {
+        "MD5" => (MessageDigest::md5(), false),
+        "MD5-SESS" => (MessageDigest::md5(), true),
+        "SHA-256" => (MessageDigest::sha256(), false),
+        "SHA-256-SESS" => (MessageDigest::sha256(), true),
+        _ => return None,
+    };
+    let h = |data: String| hash(digest, data.as_bytes()).ok().map(|digest| hex::encode(&digest));
+
+    let mut cnonce = [0; 16];
+    rand_bytes(&mut cnonce).ok()?;
+    let cnonce = hex::encode(&cnonce);
+    let nc = "00000001";
+
+    let uri = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_owned(),
+    };
+
+    let mut ha1 = h(format!("{}:{}:{}", credentials.user, realm, credentials.password))?;
+    if session {
+        ha1 = h(format!("{}:{}:{}", ha1, nonce, cnonce))?;
+    }
+    let ha2 = h(format!("{}:{}", method, uri))?;
+
+    let response = match qop {
+        Some(qop) => h(format!("{}:{}:{}:{}:{}:{}", ha1, nonce, nc, cnonce, qop, ha2))?,
+        None => h(format!("{}:{}:{}", ha1, nonce, ha2))?,
+    };
+
+    let mut header = format!(
+        r#"Digest username="{}", realm="{}", nonce="{}", uri="{}", response="{}", algorithm={}"#,
+        quote(&credentials.user), quote(realm), quote(nonce), quote(&uri), response, algorithm,
+    );
+
+    if let Some(qop) = qop {
+        write!(header, r#", cnonce="{}", nc={}, qop={}"#, cnonce, nc, qop).ok();
+    }
+
+    if let Some(opaque) = challenge.param("opaque") {
+        write!(header, r#", opaque="{}""#, quote(opaque)).ok();
+    }
+
+    HeaderValue::from_str(&header).ok()
+}
+
+// Escapes a value for a quoted-string
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn scheme_bit(scheme: &str) -> c_ulong {
+    match scheme.to_ascii_lowercase().as_str() {
+        "basic" => CURLAUTH_BASIC,
+        "digest" => CURLAUTH_DIGEST,
+        "negotiate" => CURLAUTH_NEGOTIATE,
+        "ntlm" => CURLAUTH_NTLM,
+        "bearer" => CURLAUTH_BEARER,
+        _ => CURLAUTH_NONE,
+    }
+}
+
+// Challenges are separated by commas just like their parameters,
+// so a new challenge starts at every token that isn't followed by '='.
+// Right after the scheme, a token68 like `abc==` may take the place of the parameters.
+fn parse_challenges(header: &str) -> Vec<Challenge> {
+    let mut challenges = Vec::<Challenge>::new();
+    let mut rest = header.trim();
+    let mut after_scheme = false;
+
+    while !rest.is_empty() {
+        let skipped = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        let may_be_token68 = after_scheme && !rest[..rest.len() - skipped.len()].contains(',');
+        rest = skipped;
+        after_scheme = false;
+
+        if let Some(after) = skip_token68(rest).filter(|_| may_be_token68) {
+            rest = after;
+            continue;
+        }
+
+        let token_len = rest
+            .find(|c: char| c == '=' || c == ',' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let (token, after) = rest.split_at(token_len);
+        let after_space = after.trim_start();
+
+        if token.is_empty() {
+            break;
+        }
+
+        if !after_space.starts_with('=') {
+            challenges.push(Challenge {
+                scheme: scheme_bit(token),
+                params: Vec::new(),
+            });
+            rest = after;
+            after_scheme = true;
+            continue;
+        }
+
+        let value_start = after_space[1..].trim_start();
+        let (value, after_value) = if let Some(quoted) = value_start.strip_prefix('"') {
+            parse_quoted(quoted)
+        } else {
+            let end = value_start.find(',').unwrap_or(value_start.len());
+            (value_start[..end].trim().to_owned(), &value_start[end..])
+        };
+
+        if let Some(challenge) = challenges.last_mut() {
+            challenge.params.push((token.to_owned(), value));
+        }
+
+        rest = after_value;
+    }
+
+    challenges
+}
+
+// Skips a token68, which has to end the challenge. None of the supported schemes use one.
+fn skip_token68(input: &str) -> Option<&str> {
+    let len = input
+        .find(|c: char| !c.is_ascii_alphanumeric() && !"-._~+/".contains(c))
+        .unwrap_or(input.len());
+    let after = input[len..].trim_start_matches('=');
+    let after_space = after.trim_start();
+
+    if len > 0 && (after_space.is_empty() || after_space.starts_with(',')) {
+        Some(after)
+    } else {
+        None
+    }
+}
+
+fn parse_quoted(input: &str) -> (String, &str) {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => if let Some((_, escaped)) = chars.next() {
+                value.push(escaped);
+            },
+            '"' => return (value, &input[i + 1..]),
+            c => value.push(c),
+        }
+    }
+
+    (value, "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest_header(user: &str, challenge: &str) -> Option<String> {
+        let credentials = Credentials { user: user.to_owned(), password: "secret".to_owned() };
+        let challenge = parse_challenges(challenge).pop().unwrap();
+        let url = Url::parse("http://example.com/dir/index.html").unwrap();
+
+        digest(&credentials, &challenge, &Method::GET, &url).map(|header| header.to_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn token68_belongs_to_its_challenge() {
+        let challenges = parse_challenges(r#"Negotiate abc+/de==, Newauth xyz, Basic realm="a, b""#);
+
+        assert_eq!(challenges.len(), 3);
+        assert_eq!(challenges[0].scheme, CURLAUTH_NEGOTIATE);
+        assert_eq!(challenges[1].scheme, CURLAUTH_NONE);
+        assert_eq!(challenges[2].scheme, CURLAUTH_BASIC);
+        assert_eq!(challenges[2].param("realm"), Some("a, b"));
+    }
+
+    #[test]
+    fn challenges_without_parameters() {
+        let challenges = parse_challenges(r#"Basic, Digest realm="x", nonce=abc"#);
+
+        assert_eq!(challenges.len(), 2);
+        assert_eq!(challenges[0].scheme, CURLAUTH_BASIC);
+        assert_eq!(challenges[1].param("realm"), Some("x"));
+        assert_eq!(challenges[1].param("nonce"), Some("abc"));
+    }
+
+    #[test]
+    fn digest_escapes_quoted_strings() {
+        let header = digest_header(r#"a"b\c"#, r#"Digest realm="r\"1", nonce="n", qop="auth""#).unwrap();
+
+        assert!(header.starts_with(r#"Digest username="a\"b\\c", realm="r\"1", nonce="n", uri="/dir/index.html""#));
+        assert!(header.contains("qop=auth"));
+    }
+
+    #[test]
+    fn falls_back_to_basic_when_digest_cannot_be_answered() {
+        let options = Options { http_auth: CURLAUTH_DIGEST | CURLAUTH_BASIC, ..Default::default() };
+        let credentials = Credentials { user: "user".to_owned(), password: "secret".to_owned() };
+        let url = Url::parse("http://example.com/").unwrap();
+        let respond = |header: &str| {
+            let challenges = parse_challenges(header);
+            respond(&options, Some(&credentials), &challenges, &Method::GET, &url)
+                .map(|header| header.to_str().unwrap().to_owned())
+        };
+
+        let answer = respond(r#"Digest realm="r", nonce="n", qop="auth-int", Basic realm="r""#).unwrap();
+        assert_eq!(answer, "Basic dXNlcjpzZWNyZXQ=");
+
+        let answer = respond(r#"Digest realm="r", nonce="n", algorithm=SHA-512-256, Basic realm="r""#).unwrap();
+        assert_eq!(answer, "Basic dXNlcjpzZWNyZXQ=");
+
+        let answer = respond(r#"Digest realm="r", nonce="n", qop="auth", Basic realm="r""#).unwrap();
+        assert!(answer.starts_with("Digest "));
+
+        assert!(respond(r#"Digest realm="r", nonce="n", qop="auth-int""#).is_none());
+    }
+
+    #[test]
+    fn digest_requires_qop_auth() {
+        assert!(digest_header("user", r#"Digest realm="r", nonce="n", qop="auth-int""#).is_none());
+        assert!(digest_header("user", r#"Digest realm="r", nonce="n", qop="auth,auth-int""#).is_some());
+    }
+}
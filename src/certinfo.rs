@@ -0,0 +1,110 @@
+use std::ffi::CString;
+use std::ptr::null_mut;
+use openssl::x509::{X509, X509NameRef};
+use crate::raw::{curl_certinfo, curl_slist};
+
+// Owns the C representation handed out by CURLINFO_CERTINFO.
+// Invariant: `lists` is never modified after construction,
+// because `raw.certinfo` points into it.
+pub struct CertInfo {
+    raw: curl_certinfo,
+    lists: Vec<*mut curl_slist>,
+}
+
+impl CertInfo {
+    pub fn new() -> Self {
+        Self::from_lists(Vec::new())
+    }
+
+    pub fn from_chain(chain: &[X509]) -> Self {
+        let lists = chain.iter()
+            .map(|cert| new_slist(cert_fields(cert)))
+            .collect();
+
+        Self::from_lists(lists)
+    }
+
+    fn from_lists(mut lists: Vec<*mut curl_slist>) -> Self {
+        Self {
+            raw: curl_certinfo {
+                num_of_certs: lists.len() as _,
+                certinfo: lists.as_mut_ptr(),
+            },
+            lists,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *const curl_certinfo {
+        &self.raw
+    }
+}
+
+impl Default for CertInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for CertInfo {
+    fn drop(&mut self) {
+        for &list in &self.lists {
+            unsafe { free_slist(list) };
+        }
+    }
+}
+
+fn cert_fields(cert: &X509) -> Vec<String> {
+    let mut fields = Vec::new();
+
+    fields.push(format!("Subject:{}", format_name(cert.subject_name())));
+    fields.push(format!("Issuer:{}", format_name(cert.issuer_name())));
+
+    if let Ok(serial) = cert.serial_number().to_bn().and_then(|serial| serial.to_hex_str()) {
+        fields.push(format!("Serial Number:{}", *serial));
+    }
+
+    fields.push(format!("Signature Algorithm:{}", cert.signature_algorithm().object()));
+    fields.push(format!("Start date:{}", cert.not_before()));
+    fields.push(format!("Expire date:{}", cert.not_after()));
+
+    if let Ok(pem) = cert.to_pem() {
+        fields.push(format!("Cert:{}", String::from_utf8_lossy(&pem)));
+    }
+
+    fields
+}
+
+// Same layout as OpenSSL's XN_FLAG_ONELINE, which libcurl uses
+fn format_name(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("UNDEF");
+            // Its replacement `to_string` needs openssl 0.10.81, newer than the one required
+            #[allow(deprecated)]
+            let value = entry.data().as_utf8()
+                .map(|value| value.to_string())
+                .unwrap_or_default();
+
+            format!("{} = {}", key, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn new_slist(fields: Vec<String>) -> *mut curl_slist {
+    fields.into_iter().rev().fold(null_mut(), |next, field| {
+        let data = CString::new(field)
+            .unwrap_or_default()
+            .into_raw();
+
+        Box::into_raw(Box::new(curl_slist { data, next }))
+    })
+}
+
+unsafe fn free_slist(mut list: *mut curl_slist) {
+    while !list.is_null() {
+        let node = Box::from_raw(list);
+        drop(CString::from_raw(node.data));
+        list = node.next;
+    }
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, TimeZone, Utc};
+use hyper::header::{HeaderName, HeaderValue, IF_MODIFIED_SINCE, IF_UNMODIFIED_SINCE, LAST_MODIFIED};
+use crate::Options;
+use crate::raw::curl_TimeCond::*;
+
+/// The request header expressing `CURLOPT_TIMECONDITION`, if any.
+pub fn header(options: &Options) -> Option<(HeaderName, HeaderValue)> {
+    let name = match options.time_condition {
+        CURL_TIMECOND_IFMODSINCE => IF_MODIFIED_SINCE,
+        CURL_TIMECOND_IFUNMODSINCE => IF_UNMODIFIED_SINCE,
+        // Sending the date as Last-Modified is what libcurl does
+        CURL_TIMECOND_LASTMOD => LAST_MODIFIED,
+        _ => return None,
+    };
+
+    let date = Utc.timestamp_opt(options.time_value, 0).single()?
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string();
+
+    HeaderValue::from_str(&date).ok().map(|value| (name, value))
+}
+
+/// Checks the condition against the modification time of a document.
+///
+/// Only HTTP transfers are supported, so this is checked against the Last-Modified
+/// date of a response; file:// and FTP transfers don't exist to apply it to.
+/// Like libcurl, an unknown time always meets the condition.
+pub fn is_met<Tz: TimeZone>(options: &Options, modified: Option<&DateTime<Tz>>) -> bool {
+    let modified = match modified {
+        Some(modified) => modified.timestamp(),
+        None => return true,
+    };
+
+    match options.time_condition {
+        CURL_TIMECOND_IFMODSINCE => modified > options.time_value,
+        CURL_TIMECOND_IFUNMODSINCE => modified <= options.time_value,
+        _ => true,
+    }
+}
@@ -0,0 +1,456 @@
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::{SocketAddr, TcpStream};
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::time::{Duration, Instant};
+use std::vec;
+use libc::*;
+use crate::Options;
+use crate::error::Error;
+use crate::raw::CURLcode::*;
+use crate::resolve::Resolver;
+use crate::socket::{self, Callbacks, Socket, Tuning};
+use crate::timeout::Deadline;
+
+/// Everything needed to open connections for a transfer.
+#[derive(Clone)]
+pub struct Settings {
+    pub resolver: Resolver,
+    pub deadline: Deadline,
+    pub connect_timeout: Duration,
+    pub happy_eyeballs_timeout: Duration,
+    pub unix_socket: Option<UnixSocket>,
+    pub interface: Option<String>,
+    pub tuning: Tuning,
+    pub callbacks: Callbacks,
+}
+
+/// Set by `CURLOPT_UNIX_SOCKET_PATH` or `CURLOPT_ABSTRACT_UNIX_SOCKET`.
+#[derive(Clone)]
+pub enum UnixSocket {
+    Path(String),
+    // Linux only, the name lives in its own namespace instead of the filesystem
+    Abstract(String),
+}
+
+impl Settings {
+    pub fn new(options: &Options, resolver: Resolver, deadline: Deadline) -> Self {
+        Self {
+            resolver,
+            deadline,
+            connect_timeout: options.connect_timeout,
+            happy_eyeballs_timeout: options.happy_eyeballs_timeout,
+            unix_socket: options.unix_socket.clone(),
+            interface: options.interface.clone(),
+            tuning: Tuning {
+                local_v4: None,
+                local_v6: None,
+                local_port: options.local_port,
+                local_port_range: options.local_port_range,
+                nodelay: options.tcp_nodelay,
+                keepalive: match options.tcp_keepalive {
+                    true => Some((options.tcp_keepidle, options.tcp_keepintvl)),
+                    false => None,
+                },
+            },
+            callbacks: Callbacks::new(options),
+        }
+    }
+}
+
+/// A connection to a server, over TCP or a Unix socket.
+#[derive(Debug)]
+pub enum Stream {
+    Tcp(Socket),
+    Unix(UnixStream),
+}
+
+impl Stream {
+    /// A duplicate of the connection for other threads.
+    pub fn duplicate(&self) -> io::Result<Duplicate> {
+        match self {
+            Stream::Tcp(stream) => TcpStream::try_clone(stream).map(Duplicate::Tcp),
+            Stream::Unix(stream) => stream.try_clone().map(Duplicate::Unix),
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            Stream::Unix(stream) => stream.set_nonblocking(nonblocking),
+        }
+    }
+
+    /// The local and remote address of a TCP connection.
+    pub fn addrs(&self) -> Option<(SocketAddr, SocketAddr)> {
+        match self {
+            Stream::Tcp(stream) => Some((stream.local_addr().ok()?, stream.peer_addr().ok()?)),
+            Stream::Unix(_) => None,
+        }
+    }
+}
+
+impl AsRawFd for Stream {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Stream::Tcp(stream) => stream.as_raw_fd(),
+            Stream::Unix(stream) => stream.as_raw_fd(),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            Stream::Unix(stream) => send_unix(stream, buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// A duplicate of a `Stream` for the transport.
+///
+/// It holds its own descriptor, while the `Stream` it came from stays with the transfer,
+/// which closes the connection through `CURLOPT_CLOSESOCKETFUNCTION`.
+#[derive(Debug)]
+pub enum Duplicate {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Duplicate {
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Duplicate::Tcp(stream) => stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)),
+            Duplicate::Unix(stream) => stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)),
+        }
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Duplicate::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            Duplicate::Unix(stream) => stream.set_nonblocking(nonblocking),
+        }
+    }
+}
+
+impl AsRawFd for Duplicate {
+    fn as_raw_fd(&self) -> RawFd {
+        match self {
+            Duplicate::Tcp(stream) => stream.as_raw_fd(),
+            Duplicate::Unix(stream) => stream.as_raw_fd(),
+        }
+    }
+}
+
+impl Read for Duplicate {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Duplicate::Tcp(stream) => stream.read(buf),
+            Duplicate::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Duplicate {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Duplicate::Tcp(stream) => stream.write(buf),
+            Duplicate::Unix(stream) => send_unix(stream, buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Duplicate::Tcp(stream) => stream.flush(),
+            Duplicate::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+// Unlike TcpStream, UnixStream writes can raise SIGPIPE
+fn send_unix(stream: &UnixStream, buf: &[u8]) -> io::Result<usize> {
+    let len = unsafe { send(stream.as_raw_fd(), buf.as_ptr() as *const c_void, buf.len(), MSG_NOSIGNAL) };
+
+    match len {
+        -1 => Err(io::Error::last_os_error()),
+        len => Ok(len as usize),
+    }
+}
+
+/// Opens the connection for a request to `host` and `port`.
+///
+/// With a Unix socket set, every connection goes to that socket
+/// and the host only ends up in the Host header.
+pub fn open(host: &str, port: u16, settings: &Settings) -> Result<Stream, Error> {
+    let socket = match &settings.unix_socket {
+        Some(socket) => socket,
+        None => return tcp(host, port, settings).map(Stream::Tcp),
+    };
+
+    let (stream, path) = match socket {
+        UnixSocket::Path(path) => (UnixStream::connect(path), path),
+        UnixSocket::Abstract(name) => (connect_abstract(name.as_bytes()), name),
+    };
+
+    stream.map(Stream::Unix).map_err(|e| Error::new(
+        CURLE_COULDNT_CONNECT,
+        format!("Failed to connect to {}: {}", path, e),
+    ))
+}
+
+/// Opens a TCP connection the way libcurl does.
+///
+/// Name resolution runs on a helper thread, so it is bounded by the connect timeout
+/// without relying on signals. If the host has both IPv6 and IPv4 addresses,
+/// the second family is tried after `CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS`.
+/// The connection attempts run side by side on the calling thread,
+/// which is the only one the socket callbacks are called from.
+pub fn tcp(host: &str, port: u16, settings: &Settings) -> Result<Socket, Error> {
+    let started = Instant::now();
+    let timeout = settings.deadline.connect_timeout(settings.connect_timeout);
+
+    let (target, port) = settings.resolver.connect_to(host, port);
+    let host = target.as_str();
+    let mut addrs = settings.resolver.resolve(host, port, timeout)?;
+    let mut tuning = settings.tuning.clone();
+
+    // Only the families the interface has an address for can be used
+    if let Some(interface) = &settings.interface {
+        tuning.local_v4 = socket::interface_addr(interface, false);
+        tuning.local_v6 = socket::interface_addr(interface, true);
+
+        addrs.retain(|addr| match addr {
+            SocketAddr::V4(_) => tuning.local_v4.is_some(),
+            SocketAddr::V6(_) => tuning.local_v6.is_some(),
+        });
+
+        if addrs.is_empty() {
+            return Err(Error::new(CURLE_INTERFACE_FAILED, format!("Couldn't bind to '{}'", interface)));
+        }
+    }
+
+    let (first, second): (Vec<_>, Vec<_>) = {
+        let family = addrs[0].is_ipv6();
+        addrs.into_iter().partition(|addr| addr.is_ipv6() == family)
+    };
+
+    let remaining = || timeout.checked_sub(started.elapsed()).unwrap_or_default();
+    let timed_out = || Error::new(
+        CURLE_OPERATION_TIMEDOUT,
+        format!("Connection timed out after {} milliseconds", started.elapsed().as_millis()),
+    );
+    let aborted = |e: io::Error| Error::new(CURLE_ABORTED_BY_CALLBACK, e.to_string());
+
+    let mut families = [Family::new(first, true), Family::new(second, false)];
+    let mut last_error = None;
+
+    loop {
+        // The second family gets its turn after the head start, or as soon as the first one failed
+        if !families[1].started && (started.elapsed() >= settings.happy_eyeballs_timeout || families[0].is_done()) {
+            families[1].started = true;
+        }
+
+        for family in families.iter_mut().filter(|family| family.started) {
+            while family.pending.is_none() {
+                let addr = match family.addrs.next() {
+                    Some(addr) => addr,
+                    None => break,
+                };
+
+                match socket::connect(&addr, &tuning, &settings.callbacks) {
+                    Ok((socket, true)) => return Ok(socket),
+                    Ok((socket, false)) => family.pending = Some(socket),
+                    Err(e) if socket::is_aborted(&e) => return Err(aborted(e)),
+                    Err(e) => last_error = Some(e),
+                }
+            }
+        }
+
+        if families.iter().all(Family::is_done) {
+            break;
+        }
+
+        if families[0].is_done() && !families[1].started {
+            continue;
+        }
+
+        let mut wait = remaining();
+        if wait == Duration::from_secs(0) {
+            return Err(timed_out());
+        }
+
+        if !families[1].started {
+            wait = wait.min(settings.happy_eyeballs_timeout.checked_sub(started.elapsed()).unwrap_or_default());
+        }
+
+        let mut poll_fds: Vec<_> = families.iter()
+            .filter_map(|family| family.pending.as_ref())
+            .map(|socket| pollfd { fd: socket.as_raw_fd(), events: POLLOUT, revents: 0 })
+            .collect();
+        let wait_ms = wait.as_millis().clamp(1, c_int::MAX as u128) as c_int;
+
+        if unsafe { poll(poll_fds.as_mut_ptr(), poll_fds.len() as nfds_t, wait_ms) } < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(Error::new(CURLE_COULDNT_CONNECT, e.to_string()));
+        }
+
+        let ready: Vec<_> = poll_fds.iter().filter(|poll_fd| poll_fd.revents != 0).map(|poll_fd| poll_fd.fd).collect();
+
+        for family in families.iter_mut() {
+            let socket = match family.pending.take() {
+                Some(socket) if ready.contains(&socket.as_raw_fd()) => socket,
+                pending => {
+                    family.pending = pending;
+                    continue;
+                },
+            };
+
+            // A failed socket is closed right away, then the next address gets its turn
+            match socket::finish(&socket) {
+                Ok(()) => return Ok(socket),
+                Err(e) => last_error = Some(e),
+            }
+        }
+    }
+
+    let e = last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address to connect to"));
+
+    match e.kind() {
+        io::ErrorKind::TimedOut => return Err(timed_out()),
+        io::ErrorKind::AddrInUse | io::ErrorKind::AddrNotAvailable => return Err(Error::new(
+            CURLE_INTERFACE_FAILED,
+            format!("bind failed: {}", e),
+        )),
+        _ => {},
+    }
+
+    Err(Error::new(
+        CURLE_COULDNT_CONNECT,
+        format!("Failed to connect to {} port {} after {} ms: {}", host, port, started.elapsed().as_millis(), e),
+    ))
+}
+
+// std can't address the abstract namespace, so the socket is set up by hand
+fn connect_abstract(name: &[u8]) -> io::Result<UnixStream> {
+    unsafe {
+        let mut addr: sockaddr_un = mem::zeroed();
+        addr.sun_family = AF_UNIX as sa_family_t;
+
+        // The leading NUL byte marks the name as abstract
+        if name.len() + 1 > addr.sun_path.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "abstract socket name too long"));
+        }
+
+        for (dst, &src) in addr.sun_path[1..].iter_mut().zip(name) {
+            *dst = src as c_char;
+        }
+
+        let fd = libc::socket(AF_UNIX, SOCK_STREAM, 0);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let stream = UnixStream::from_raw_fd(fd);
+        let len = mem::size_of::<sa_family_t>() + 1 + name.len();
+
+        if connect(fd, &addr as *const sockaddr_un as *const sockaddr, len as socklen_t) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(stream)
+    }
+}
+
+// The addresses of one family, tried one after the other
+struct Family {
+    addrs: vec::IntoIter<SocketAddr>,
+    // The connection attempt in progress
+    pending: Option<Socket>,
+    started: bool,
+}
+
+impl Family {
+    fn new(addrs: Vec<SocketAddr>, started: bool) -> Self {
+        Self {
+            addrs: addrs.into_iter(),
+            pending: None,
+            started,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.pending.is_none() && self.addrs.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, ThreadId};
+    use crate::raw::{curl_socket_t, curlsocktype, CURL_SOCKOPT_OK};
+    use super::*;
+
+    unsafe extern "C" fn sockopt(clientp: *mut c_void, _: curl_socket_t, _: curlsocktype::Type) -> c_int {
+        (*(clientp as *const RefCell<Vec<ThreadId>>)).borrow_mut().push(thread::current().id());
+        CURL_SOCKOPT_OK as c_int
+    }
+
+    unsafe extern "C" fn close_socket(clientp: *mut c_void, item: curl_socket_t) -> c_int {
+        (*(clientp as *const RefCell<Vec<ThreadId>>)).borrow_mut().push(thread::current().id());
+        close(item)
+    }
+
+    #[test]
+    fn falls_back_to_the_second_family_on_the_calling_thread() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sockopt_calls = RefCell::new(Vec::<ThreadId>::new());
+        let close_calls = RefCell::new(Vec::<ThreadId>::new());
+
+        let mut options = Options::new();
+        // Nothing listens on the IPv6 port, so that family fails first
+        options.resolve = vec![format!("example.test:{}:[::1],127.0.0.1", port)];
+        options.happy_eyeballs_timeout = Duration::from_secs(60);
+        options.sockopt_function = Some(sockopt);
+        options.sockopt_data = &sockopt_calls as *const _ as *mut c_void;
+        options.close_socket_function = Some(close_socket);
+        options.close_socket_data = &close_calls as *const _ as *mut c_void;
+
+        let resolver = Resolver::new(&options, Arc::new(Mutex::new(<_>::default()))).unwrap();
+        let settings = Settings::new(&options, resolver, Deadline::new(None));
+
+        let started = Instant::now();
+        let socket = tcp("example.test", port, &settings).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(socket.peer_addr().unwrap().is_ipv4());
+        drop(socket);
+
+        let current = thread::current().id();
+        assert!(sockopt_calls.borrow().iter().all(|&id| id == current));
+        assert!(!close_calls.borrow().is_empty());
+        assert!(close_calls.borrow().iter().all(|&id| id == current));
+    }
+}
@@ -1,25 +1,58 @@
 use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
 use std::ffi::{CString, CStr};
+use std::fmt;
 use std::io::{self, Write};
-use reqwest::RedirectPolicy;
-use reqwest::header::{HeaderValue, CONTENT_TYPE, LAST_MODIFIED};
+use std::time::Duration;
+use hyper::{Body, Method, Request, StatusCode, Uri, Version};
+use hyper::body::Payload;
+use hyper::header::{
+    HeaderMap, HeaderValue, ACCEPT, ACCEPT_ENCODING, ALT_SVC, AUTHORIZATION, CONTENT_ENCODING, CONTENT_RANGE,
+    CONTENT_TYPE, CONNECTION, HOST, LAST_MODIFIED, LOCATION, PROXY_AUTHENTICATE, RANGE, STRICT_TRANSPORT_SECURITY,
+    WWW_AUTHENTICATE,
+};
+use url::{Position, Url};
 use chrono::{DateTime, FixedOffset};
 use progress_streams::ProgressReader;
 use libc::*;
-use crate::{Options, Infos, mime};
+use crate::{Options, Infos, altsvc, auth, condition, connect, decoding, headers, hsts, protocols, resolve, sigv4, timeout, tls, trace};
+use crate::wire::{Event, Recorder, Wire};
+use crate::session::Session;
+use crate::ws::{self, WebSocket};
+use crate::transport::{self, Io, Transport, Upstream};
 use crate::raw::{
     CURLcode::{self, *},
+    CURL_NETRC_OPTION::CURL_NETRC_IGNORED,
+    _bindgen_ty_4::*,
+    curl_TimeCond::CURL_TIMECOND_NONE,
+    CURL_REDIR_POST_301,
+    CURL_REDIR_POST_302,
+    CURL_REDIR_POST_303,
     curl_off_t,
 };
 use crate::util::{
     borrow_raw::*,
     root_rc::RootRc,
 };
-use crate::error::{ErrorBuffer, ErrorSink};
+use crate::error::{Error, ErrorBuffer, ErrorSink};
+use crate::certinfo::CertInfo;
+use crate::rawx::{CURLH_HEADER, CURLH_PSEUDO, CURLHSTS_ENABLE, CURLHSTS_READONLYFILE, CURLWS_CLOSE};
+use crate::raw::CURLALTSVC_READONLYFILE;
+
+const FORM_URLENCODED: &str = "application/x-www-form-urlencoded";
+
+#[allow(clippy::upper_case_acronyms)]
 pub struct CURL {
     pub options: Options,
     pub infos: Infos,
-    mime: Option<mime::curl_mime>,
+    dns_cache: Arc<Mutex<resolve::Cache>>,
+    alt_svc: altsvc::Cache,
+    hsts: hsts::Cache,
+    // The connection of the last CURLOPT_CONNECT_ONLY transfer
+    pub session: Option<Session>,
+    pub websocket: Option<WebSocket>,
+    // What curl_easy_header and curl_easy_nextheader look at
+    pub headers: headers::Headers,
 }
 
 impl CURL {
@@ -27,7 +60,12 @@ impl CURL {
         Box::new(Self {
             options: Options::new(),
             infos: Infos::new(),
-            mime: None,
+            dns_cache: <_>::default(),
+            alt_svc: <_>::default(),
+            hsts: <_>::default(),
+            session: None,
+            websocket: None,
+            headers: <_>::default(),
         })
     }
 
@@ -40,6 +78,8 @@ impl CURL {
     }
 
     pub fn error(&mut self, code: CURLcode::Type, message: impl Into<String>) -> CURLcode::Type {
+        let message = message.into();
+        trace::Tracer::new(self).text(&message);
         self.options.error_buffer.borrow_mut().set_error(code, message)
     }
 
@@ -49,52 +89,480 @@ impl CURL {
 
     pub fn last_effective_url(&self) -> &CStr {
         self.infos
-            .last_effective_url.as_ref()
-            .map(CString::as_c_str)
+            .last_effective_url.as_deref()
             .unwrap_or_default()
     }
 
     pub fn perform(&mut self) -> CURLcode::Type {
+        self.session = None;
+        self.websocket = None;
+        self.headers.clear();
+
+        // A transfer that fails before connecting has no addresses to report
+        self.infos.primary_ip = CString::default();
+        self.infos.primary_port = 0;
+        self.infos.local_ip = CString::default();
+        self.infos.local_port = 0;
+
+        let tracer = trace::Tracer::new(self);
+        let handle: *mut CURL = self;
         let options = &mut self.options;
         let infos = &mut self.infos;
+        let alt_svc = &mut self.alt_svc;
+        let hsts = &mut self.hsts;
 
         let url = match options.url.as_ref() {
             Some(url) => url,
             None => return CURLE_OK,
         };
 
-        let redirect_policy = match options.follow_location {
-            true => RedirectPolicy::limited(30),
-            false => RedirectPolicy::none(),
+        if let Some(file) = options.alt_svc_file.as_ref().filter(|file| !file.is_empty()) {
+            alt_svc.load(file);
+        }
+
+        let uses_hsts = options.hsts_ctrl & CURLHSTS_ENABLE != 0;
+
+        if uses_hsts {
+            if let Some(file) = options.hsts_file.as_ref().filter(|file| !file.is_empty()) {
+                hsts.load(file);
+            }
+
+            if let Some(read_function) = options.hsts_read_function {
+                if let Err(e) = unsafe { hsts.read(handle, read_function, options.hsts_read_data) } {
+                    return self.error(e.code, e.message);
+                }
+            }
+        }
+
+        let deadline = timeout::Deadline::new(options.timeout);
+
+        let resolver = match resolve::Resolver::new(options, self.dns_cache.clone()) {
+            Ok(resolver) => resolver,
+            Err(e) => return self.error(e.code, e.message),
         };
+        let connect = connect::Settings::new(options, resolver, deadline);
 
-        let client = reqwest::Client::builder()
-            .redirect(redirect_policy)
-            .connect_timeout(options.connect_timeout)
-            .build()
-            .unwrap();
+        let http_1_0 = options.http_version == CURL_HTTP_VERSION_1_0 as c_long;
 
-        let mut request = client.request(options.method.clone(), url);
+        let mut url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(e) => return self.error(CURLE_URL_MALFORMAT, e.to_string()),
+        };
 
-        if let Some(post_fields) = &options.post_fields {
-            request = request.body(post_fields.to_owned());
-            request = request.header(
-                CONTENT_TYPE,
-                HeaderValue::from_static("application/x-www-form-urlencoded"),
-            );
+        if uses_hsts {
+            upgrade_to_https(&mut url, hsts, &tracer);
         }
 
-        if cfg!(debug_assertions) {
-            eprintln!("recurl: Requesting {:?}", options.url);
+        infos.cert_info = CertInfo::new();
+        infos.redirect_count = 0;
+        infos.redirect_time = Duration::from_secs(0);
+        infos.redirect_url = None;
+        infos.size_download = 0;
+        infos.condition_unmet = false;
+        infos.http_version = CURL_HTTP_VERSION_NONE as c_long;
+
+        let websocket = url.scheme() == "ws" || url.scheme() == "wss";
+
+        // CONNECT_ONLY leaves the connection to curl_easy_send and curl_easy_recv,
+        // or with 2 to the WebSocket API after the upgrade.
+        if options.connect_only != 0 || websocket {
+            tracer.text(&format!(
+                "  Trying {}:{}...",
+                url.host_str().unwrap_or_default(),
+                url.port_or_known_default().unwrap_or_default(),
+            ));
+
+            let mut session = match Session::open(&url, options, &connect) {
+                Ok(session) => session,
+                Err(e) => return self.error(e.code, e.message),
+            };
+
+            let chain = session.peer_certificates();
+
+            if let Some(pin) = &options.pinned_public_key {
+                if let Err(e) = tls::check_pinned_public_key(&chain, pin) {
+                    return self.error(e.code, e.message);
+                }
+            }
+
+            if options.cert_info {
+                infos.cert_info = CertInfo::from_chain(&chain);
+            }
+
+            if let Some((local, peer)) = session.addrs() {
+                infos.primary_ip = CString::new(peer.ip().to_string()).unwrap_or_default();
+                infos.primary_port = peer.port();
+                infos.local_ip = CString::new(local.ip().to_string()).unwrap_or_default();
+                infos.local_port = local.port();
+            }
+
+            infos.last_effective_url = CString::new(url.as_str()).ok();
+            infos.set_scheme(url.scheme());
+
+            if websocket && options.connect_only != 1 {
+                match ws::handshake(&mut session, &url, options, &tracer, &mut self.headers) {
+                    Ok(websocket) => self.websocket = Some(websocket),
+                    Err(e) => return self.error(e.code, e.message),
+                }
+
+                infos.response_code = 101;
+                infos.http_version = CURL_HTTP_VERSION_1_1 as c_long;
+            }
+
+            if options.connect_only == 0 {
+                self.session = Some(session);
+                return self.receive_websocket();
+            }
+
+            if let Err(e) = session.set_nonblocking(true) {
+                return self.error(CURLE_COULDNT_CONNECT, e.to_string());
+            }
+
+            tracer.text("Connection established, leaving it to the application");
+            self.session = Some(session);
+
+            return CURLE_OK;
         }
 
-        let response = match request.send() {
-            Ok(response) => response,
-            Err(e) => return self.error(CURLE_HTTP_RETURNED_ERROR, e.to_string()),
+        let mut credentials = match auth::Credentials::from_request(options, &url) {
+            Ok(credentials) => credentials,
+            Err(e) => return self.error(e.code, e.message),
+        };
+        let mut auth_origin = Origin::of(&url);
+        let mut authorization = auth::initial(options, credentials.as_ref());
+        let mut auth_attempted = false;
+        let mut method = options.method.clone();
+        let mut body = options.post_fields.clone();
+
+        // libcurl would send the range as Content-Range with the upload
+        if body.is_some() && range(options).is_some() {
+            return self.error(CURLE_NOT_BUILT_IN, "resuming or sending a range of an upload is not supported");
+        }
+
+        // Embedded credentials are sent through the Authorization header instead
+        url.set_username("").ok();
+        url.set_password(None).ok();
+
+        // The transport and connection of the request in flight, which have to outlive the response body.
+        // The transport comes first, so it is done with the connection before it is closed.
+        let mut hop = None;
+        let wire = Wire::new(options.verbose);
+
+        let response = loop {
+            // Like libcurl, alternatives are only used for and learned from origins reached over TLS
+            let uses_alt_svc = options.alt_svc_ctrl != 0 && url.scheme() == "https";
+            let host = url.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']').to_owned();
+            let port = url.port_or_known_default().unwrap_or_default();
+
+            let alternative = match uses_alt_svc {
+                true => alt_svc.lookup(&host, port, options.alt_svc_ctrl),
+                false => None,
+            };
+
+            if let Some(alternative) = &alternative {
+                tracer.text(&format!(
+                    "Alt-svc connecting from {}:{} to [{}]{}:{}",
+                    host, port, alternative.alpn.as_str(), alternative.host, alternative.port,
+                ));
+            }
+
+            // TLS still runs end to end with the origin, only the connection goes elsewhere
+            let (connect_host, connect_port) = alternative.map_or((host.clone(), port), |alternative| (alternative.host, alternative.port));
+
+            tracer.text(&format!("  Trying {}:{}...", connect_host, connect_port));
+
+            let stream = match connect::open(&connect_host, connect_port, &connect) {
+                Ok(stream) => stream,
+                Err(e) => return self.error(e.code, e.message),
+            };
+
+            let addrs = stream.addrs();
+
+            if let Some((local, peer)) = addrs {
+                infos.primary_ip = CString::new(peer.ip().to_string()).unwrap_or_default();
+                infos.primary_port = peer.port();
+                infos.local_ip = CString::new(local.ip().to_string()).unwrap_or_default();
+                infos.local_port = local.port();
+            }
+
+            let duplicate = match stream.duplicate() {
+                Ok(duplicate) => duplicate,
+                Err(e) => return self.error(CURLE_COULDNT_CONNECT, e.to_string()),
+            };
+
+            let io = Io::new(duplicate);
+
+            // Like libcurl, the connect timeout covers the TLS handshake
+            if let Err(e) = io.set_timeout(Some(deadline.connect_timeout(options.connect_timeout))) {
+                return self.error(CURLE_COULDNT_CONNECT, e.to_string());
+            }
+
+            // TLS is set up before hyper takes over, so the certificates of every hop can be checked
+            let upstream = match url.scheme() {
+                "https" => {
+                    let http2 = matches!(
+                        options.http_version as u32,
+                        CURL_HTTP_VERSION_2_0 | CURL_HTTP_VERSION_2TLS | CURL_HTTP_VERSION_2_PRIOR_KNOWLEDGE
+                    );
+
+                    let stream = tls::handshake(&host, Recorder::new(io, wire.sink()), options, http2);
+                    tracer.wire(wire.drain());
+
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(e) => return self.error(e.code, e.message),
+                    };
+                    let chain = tls::peer_chain(stream.ssl());
+
+                    if let Some(pin) = &options.pinned_public_key {
+                        if let Err(e) = tls::check_pinned_public_key(&chain, pin) {
+                            return self.error(e.code, e.message);
+                        }
+                    }
+
+                    // Each hop replaces the chain, so the one reported is from the connection the response came over
+                    if options.cert_info {
+                        infos.cert_info = CertInfo::from_chain(&chain);
+                    }
+
+                    Upstream::Tls(stream)
+                },
+                _ => {
+                    infos.cert_info = CertInfo::new();
+                    Upstream::Plain(io)
+                },
+            };
+
+            let http2 = match &upstream {
+                Upstream::Tls(stream) => tls::is_http2(stream.ssl()),
+                Upstream::Plain(_) => options.http_version == CURL_HTTP_VERSION_2_PRIOR_KNOWLEDGE as c_long,
+            };
+
+            if !connect.callbacks.allows_request(addrs) {
+                return self.error(CURLE_ABORTED_BY_CALLBACK, "operation aborted by pre-request callback");
+            }
+
+            // Every request gets its own transport, so the connection it was given is the one used
+            drop(hop.take());
+
+            let mut transport = match Transport::start(upstream, http2, method == Method::HEAD, wire.sink()) {
+                Ok(transport) => transport,
+                Err(e) => return self.error(CURLE_FAILED_INIT, e.to_string()),
+            };
+
+            let mut headers = HeaderMap::new();
+
+            if body.is_some() {
+                headers.insert(CONTENT_TYPE, HeaderValue::from_static(FORM_URLENCODED));
+            }
+
+            if let Some(range) = range(options) {
+                match HeaderValue::from_str(&format!("bytes={}", range)) {
+                    Ok(range) => { headers.insert(RANGE, range); },
+                    Err(e) => return self.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+                }
+            }
+
+            if let Some((name, value)) = condition::header(options) {
+                headers.insert(name, value);
+            }
+
+            if let Some(accept_encoding) = &options.accept_encoding {
+                let accept_encoding = match accept_encoding.as_str() {
+                    "" => decoding::SUPPORTED,
+                    accept_encoding => accept_encoding,
+                };
+
+                match HeaderValue::from_str(accept_encoding) {
+                    Ok(accept_encoding) => { headers.insert(ACCEPT_ENCODING, accept_encoding); },
+                    Err(e) => return self.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+                }
+            }
+
+            let may_authenticate = options.unrestricted_auth || Origin::of(&url) == auth_origin;
+
+            if let Some(authorization) = authorization.as_ref().filter(|_| may_authenticate) {
+                headers.insert(AUTHORIZATION, authorization.clone());
+            }
+
+            if may_authenticate {
+                let signed = sigv4::Request {
+                    method: &method,
+                    url: &url,
+                    content_type: body.as_ref().map(|_| FORM_URLENCODED),
+                    payload: body.as_deref().unwrap_or_default(),
+                };
+
+                match sigv4::sign_if_enabled(options, credentials.as_ref(), &signed) {
+                    Ok(signed) => headers.extend(signed),
+                    Err(e) => return self.error(e.code, e.message),
+                }
+            }
+
+            if deadline.is_expired() {
+                return self.error(CURLE_OPERATION_TIMEDOUT, deadline.message(0, None));
+            }
+
+            let request = match request(&method, &url, headers, body.clone(), http2, http_1_0) {
+                Ok(request) => request,
+                Err(e) => return self.error(e.code, e.message),
+            };
+
+            // HTTP/1 messages are traced as the transport sends them
+            if http2 {
+                tracer.request(&request, body.as_deref());
+            }
+
+            let response = transport.send(request, timeout::OperationTimeout::new(options, deadline));
+            tracer.wire(wire.drain());
+            hop = Some((transport, stream));
+
+            let response = match response {
+                Ok(response) => response,
+                Err(ref e) if timeout::is_timeout(e) && deadline.is_expired() => {
+                    return self.error(CURLE_OPERATION_TIMEDOUT, deadline.message(0, None));
+                },
+                Err(ref e) if timeout::is_timeout(e) => {
+                    let message = format!("Operation timed out after {} milliseconds", deadline.elapsed().as_millis());
+                    return self.error(CURLE_OPERATION_TIMEDOUT, message);
+                },
+                Err(e) => return self.error(CURLE_HTTP_RETURNED_ERROR, e.to_string()),
+            };
+
+            if http2 {
+                tracer.response(&response);
+            }
+
+            // HTTP/1 heads are stored as the transport received them, with 1xx responses and the original case
+            self.headers.start_request();
+            store_received(&mut self.headers, &wire);
+
+            if http2 {
+                self.headers.push(CURLH_PSEUDO, Some((":status", response.status().as_str().as_bytes())));
+                self.headers.push(
+                    CURLH_HEADER,
+                    response.headers().iter().map(|(name, value)| (name.as_str(), value.as_bytes())),
+                );
+            }
+
+            if uses_alt_svc {
+                let alpn = match response.version() {
+                    Version::HTTP_2 => altsvc::Alpn::H2,
+                    _ => altsvc::Alpn::H1,
+                };
+
+                for value in response.headers().get_all(ALT_SVC).iter().filter_map(|value| value.to_str().ok()) {
+                    alt_svc.update(alpn, &host, port, value);
+                }
+            }
+
+            // Only the first header counts, and only if it came over TLS
+            if uses_hsts && url.scheme() == "https" {
+                if let Some(value) = response.headers().get(STRICT_TRANSPORT_SECURITY).and_then(|value| value.to_str().ok()) {
+                    hsts.update(&host, value);
+                }
+            }
+
+            let challenges = auth::challenges(response.headers(), WWW_AUTHENTICATE);
+            infos.http_auth_avail = auth::available(&challenges);
+            infos.proxy_auth_avail = auth::available(&auth::challenges(response.headers(), PROXY_AUTHENTICATE));
+
+            if response.status() == StatusCode::UNAUTHORIZED && may_authenticate && !auth_attempted {
+                auth_attempted = true;
+
+                // Credentials that were already sent and rejected aren't tried again
+                let answer = auth::respond(options, credentials.as_ref(), &challenges, &method, &url)
+                    .filter(|answer| authorization.as_ref() != Some(answer));
+
+                if let Some(answer) = answer {
+                    authorization = Some(answer);
+                    continue;
+                }
+            }
+
+            let location = Some(response.status())
+                .filter(|&status| is_redirect(status))
+                .and_then(|_| response.headers().get(LOCATION))
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok());
+
+            let mut location = match location {
+                Some(location) => location,
+                None => break response,
+            };
+
+            // CURLINFO_REDIRECT_URL reports where a redirect would have gone
+            if !options.follow_location {
+                infos.redirect_url = CString::new(location.as_str()).ok();
+                break response;
+            }
+
+            if options.max_redirects == Some(infos.redirect_count) {
+                let max = infos.redirect_count;
+                return self.error(CURLE_TOO_MANY_REDIRECTS, format!("Maximum ({}) redirects followed", max));
+            }
+
+            let allowed = protocols::bit(location.scheme())
+                .is_some_and(|bit| options.redirect_protocols & bit != 0);
+
+            if !allowed {
+                let message = format!("Protocol \"{}\" not supported or disabled in libcurl", location.scheme());
+                return self.error(CURLE_UNSUPPORTED_PROTOCOL, message);
+            }
+
+            if uses_hsts {
+                upgrade_to_https(&mut location, hsts, &tracer);
+            }
+
+            tracer.text(&format!("Issue another request to this URL: '{}'", location));
+
+            infos.redirect_count += 1;
+            infos.redirect_time = deadline.elapsed();
+
+            // Like browsers, POST becomes GET unless CURLOPT_POSTREDIR says otherwise
+            let keeps_post = |bit: u32| options.post_redirect & bit as c_long != 0;
+            let is_post = method == Method::POST;
+            let post_to_get = match response.status() {
+                StatusCode::MOVED_PERMANENTLY => is_post && !keeps_post(CURL_REDIR_POST_301),
+                StatusCode::FOUND => is_post && !keeps_post(CURL_REDIR_POST_302),
+                StatusCode::SEE_OTHER => method != Method::HEAD && !(is_post && keeps_post(CURL_REDIR_POST_303)),
+                _ => false,
+            };
+
+            if post_to_get {
+                method = Method::GET;
+                body = None;
+            }
+
+            // .netrc credentials belong to a host, so look them up again for the new one
+            let has_explicit_credentials = options.username.is_some() || options.password.is_some();
+            let uses_netrc = options.netrc != CURL_NETRC_IGNORED && !has_explicit_credentials;
+
+            if uses_netrc && Origin::of(&location) != auth_origin {
+                match auth::Credentials::from_request(options, &location) {
+                    Ok(Some(netrc_credentials)) => {
+                        credentials = Some(netrc_credentials);
+                        auth_origin = Origin::of(&location);
+                    },
+                    Ok(None) => {},
+                    Err(e) => return self.error(e.code, e.message),
+                }
+            }
+
+            url = location;
+            authorization = auth::initial(options, credentials.as_ref());
+            auth_attempted = false;
         };
 
         infos.response_code = response.status().as_u16();
-        infos.content_length_download = response.content_length();
+        infos.http_version = match response.version() {
+            Version::HTTP_10 => CURL_HTTP_VERSION_1_0 as c_long,
+            Version::HTTP_11 => CURL_HTTP_VERSION_1_1 as c_long,
+            Version::HTTP_2 => CURL_HTTP_VERSION_2_0 as c_long,
+            _ => CURL_HTTP_VERSION_NONE as c_long,
+        };
+        infos.set_scheme(url.scheme());
+        infos.content_length_download = response.body().content_length();
 
         if options.file_time {
             infos.file_time = response.headers()
@@ -103,7 +571,7 @@ impl CURL {
         }
 
         // TODO: Improve handling of null in URLs
-        infos.last_effective_url = CStr::from_bytes_with_nul(response.url().as_str().as_bytes()).ok().map(<_>::into);
+        infos.last_effective_url = CStr::from_bytes_with_nul(url.as_str().as_bytes()).ok().map(<_>::into);
 
         let mut header_function = options.header_function;
         if !options.header_data.is_null() {
@@ -127,14 +595,67 @@ impl CURL {
             }
         }
 
+        // Like libcurl, a document that doesn't meet the time condition has no body
+        if options.time_condition != CURL_TIMECOND_NONE {
+            let last_modified = response.headers()
+                .get(LAST_MODIFIED)
+                .and_then(parse_last_modified);
+
+            if response.status() == StatusCode::NOT_MODIFIED || !condition::is_met(options, last_modified.as_ref()) {
+                infos.condition_unmet = true;
+                return CURLE_OK;
+            }
+        }
+
+        // Like libcurl, a resumed download must get a partial response,
+        // unless there was nothing left to download. A 416 doesn't count as one.
+        if options.resume_from > 0 && method == Method::GET {
+            let partial = response.status() != StatusCode::RANGE_NOT_SATISFIABLE
+                && response.headers().contains_key(CONTENT_RANGE);
+
+            if !partial {
+                if infos.content_length_download == Some(options.resume_from) {
+                    return CURLE_OK;
+                }
+
+                return self.error(CURLE_RANGE_ERROR, "HTTP server doesn't seem to support byte ranges. Cannot resume.");
+            }
+        }
+
         let mut writer = FFIWriter {
             write_function: options.write_function,
             write_data: options.write_data,
         };
 
-        infos.size_download = 0;
+        // Like libcurl, only decode if an encoding was asked for
+        let encodings = match options.accept_encoding {
+            Some(_) if options.content_decoding => response.headers()
+                .get_all(CONTENT_ENCODING)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .filter(|encoding| !encoding.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            _ => Vec::new(),
+        };
 
-        let mut reader = ProgressReader::new(response, |dl_progress| {
+        let transport = match &mut hop {
+            Some((transport, _)) => transport,
+            None => return self.error(CURLE_FAILED_INIT, "no connection to read the response from"),
+        };
+        let response = transport::Reader::new(transport, response.into_body(), timeout::OperationTimeout::new(options, deadline));
+
+        let response = timeout::Reader::new(
+            trace::Reader::new(response, tracer, &wire),
+            deadline,
+            timeout::SpeedCheck::new(options),
+            infos.content_length_download,
+        );
+
+        // Progress counts the bytes on the wire, before decoding
+        let reader = ProgressReader::new(response, |dl_progress| {
             infos.size_download += dl_progress as u64;
 
             if options.no_progress {
@@ -155,15 +676,119 @@ impl CURL {
         });
 
         // TODO: Handle CURL_WRITEFUNC_PAUSE
-        infos.size_download = match io::copy(&mut reader, &mut writer) {
-            Ok(size_download) => size_download,
-            Err(e) => return self.error(CURLE_HTTP_RETURNED_ERROR, e.to_string()),
+        let copied = decoding::Decoder::new(reader, &encodings).map(|mut reader| {
+            let result = copy(&mut reader, &mut writer, options.buffer_size);
+            (result, reader.is_corrupt())
+        });
+
+        let (result, corrupt) = match copied {
+            Ok(copied) => copied,
+            Err(e) => return self.error(e.code, e.message),
         };
 
+        // Trailers come in with the end of the body
+        tracer.wire(wire.drain());
+        store_received(&mut self.headers, &wire);
+
+        match result {
+            Ok(_) => {},
+            Err(ref e) if corrupt => return self.error(CURLE_BAD_CONTENT_ENCODING, e.to_string()),
+            Err(ref e) if timeout::is_timeout(e) => return self.error(CURLE_OPERATION_TIMEDOUT, e.to_string()),
+            Err(e) => return self.error(CURLE_HTTP_RETURNED_ERROR, e.to_string()),
+        }
+
         CURLE_OK
     }
 }
 
+impl CURL {
+    // Like libcurl, failing to write a cache file doesn't fail the transfer.
+    // A transfer that ended early didn't load the files, which mustn't be emptied.
+    fn save_caches(&mut self) {
+        if let Some(file) = self.options.alt_svc_file.as_ref().filter(|file| !file.is_empty()) {
+            if self.options.alt_svc_ctrl & CURLALTSVC_READONLYFILE as c_long == 0 {
+                self.alt_svc.load(file);
+                self.alt_svc.save(file).ok();
+            }
+        }
+
+        if let Some(file) = self.options.hsts_file.as_ref().filter(|file| !file.is_empty()) {
+            if self.options.hsts_ctrl & CURLHSTS_ENABLE != 0 && self.options.hsts_ctrl & CURLHSTS_READONLYFILE == 0 {
+                self.hsts.load(file);
+                self.hsts.save(file).ok();
+            }
+        }
+    }
+
+    // Like libcurl, the HSTS entries go to the write callback when the handle is cleaned up
+    fn write_hsts(&mut self) {
+        let write_function = match self.options.hsts_write_function {
+            Some(write_function) if self.options.hsts_ctrl & CURLHSTS_ENABLE != 0 => write_function,
+            _ => return,
+        };
+        let handle: *mut CURL = self;
+
+        unsafe {
+            self.hsts.write(handle, write_function, self.options.hsts_write_data);
+        }
+    }
+
+    // Hands the frames of an upgraded connection to the write function until the server closes it
+    fn receive_websocket(&mut self) -> CURLcode::Type {
+        let mut writer = FFIWriter {
+            write_function: self.options.write_function,
+            write_data: self.options.write_data,
+        };
+        let mut buffer = vec![0; self.options.buffer_size];
+
+        loop {
+            let result = match (&mut self.session, &mut self.websocket) {
+                (Some(session), Some(websocket)) => websocket.recv(session, &mut buffer),
+                _ => return CURLE_OK,
+            };
+
+            let len = match result {
+                Ok(len) => len,
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return CURLE_OK,
+                Err(e) => return self.error(CURLE_RECV_ERROR, e.to_string()),
+            };
+
+            let (raw, flags, bytes_left) = match &mut self.websocket {
+                Some(websocket) => {
+                    websocket.in_callback = true;
+                    (websocket.is_raw(), websocket.meta.flags as c_uint, websocket.meta.bytesleft)
+                },
+                None => return CURLE_OK,
+            };
+
+            if raw && len == 0 {
+                return CURLE_OK;
+            }
+
+            // The write function may call curl_ws_meta and curl_ws_send
+            let written = writer.write(&buffer[..len]);
+
+            if let Some(websocket) = &mut self.websocket {
+                websocket.in_callback = false;
+            }
+
+            match written {
+                Ok(written) if written == len => {},
+                _ => return self.error(CURLE_WRITE_ERROR, "Failure writing output to destination"),
+            }
+
+            // The close frame was echoed when it arrived, so that reply only has to go out
+            if !raw && flags & CURLWS_CLOSE != 0 && bytes_left == 0 {
+                if let (Some(session), Some(websocket)) = (&mut self.session, &mut self.websocket) {
+                    websocket.flush(session).ok();
+                }
+
+                return CURLE_OK;
+            }
+        }
+    }
+}
+
 impl ErrorSink for CURL {
     fn with_error_buffer<F>(&self, f: F) where F: FnOnce(&mut ErrorBuffer) {
         f(&mut self.options.error_buffer.borrow_mut())
@@ -171,28 +796,142 @@ impl ErrorSink for CURL {
 }
 
 #[no_mangle]
-pub extern fn curl_easy_init() -> *mut CURL {
+pub extern "C" fn curl_easy_init() -> *mut CURL {
     CURL::init().into_raw()
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_easy_reset(this: *mut CURL) {
+pub unsafe extern "C" fn curl_easy_reset(this: *mut CURL) {
     this.borrow_raw_mut(|this| {
         this.options = <_>::default();
+        this.infos = Infos::new();
+        this.session = None;
+        this.websocket = None;
+        this.headers.clear();
     });
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_easy_cleanup(curl: *mut CURL) {
+pub unsafe extern "C" fn curl_easy_cleanup(curl: *mut CURL) {
     if !curl.is_null() {
-        CURL::from_raw(curl);
+        CURL::from_raw(curl).write_hsts();
     }
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_easy_perform(this: *mut CURL) -> CURLcode::Type {
-    this.borrow_raw_mut(CURL::perform)
-        .unwrap_or(CURLE_BAD_FUNCTION_ARGUMENT)
+pub unsafe extern "C" fn curl_easy_perform(this: *mut CURL) -> CURLcode::Type {
+    this.borrow_raw_mut(|this| {
+        let code = this.perform();
+        this.save_caches();
+        code
+    })
+    .unwrap_or(CURLE_BAD_FUNCTION_ARGUMENT)
+}
+
+// Like `io::copy`, but hands the write function chunks of at most `CURLOPT_BUFFERSIZE` bytes
+fn copy(reader: &mut impl io::Read, writer: &mut impl Write, buffer_size: usize) -> io::Result<u64> {
+    let mut buffer = vec![0; buffer_size];
+    let mut copied = 0;
+
+    loop {
+        let len = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(copied),
+            Ok(len) => len,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        writer.write_all(&buffer[..len])?;
+        copied += len as u64;
+    }
+}
+
+// Builds the request of one hop, for the connection that was opened for it
+fn request(method: &Method, url: &Url, headers: HeaderMap, body: Option<Vec<u8>>, http2: bool, http_1_0: bool) -> Result<Request<Body>, Error> {
+    let malformed = |e: &dyn fmt::Display| Error::new(CURLE_URL_MALFORMAT, e.to_string());
+
+    // HTTP/1 requests only hold the path, the rest goes into the Host header
+    let target = match http2 {
+        true => url.as_str(),
+        false => &url[Position::BeforePath..Position::AfterQuery],
+    };
+
+    let mut request = Request::new(body.map_or_else(Body::empty, Body::from));
+    *request.method_mut() = method.clone();
+    *request.uri_mut() = target.parse::<Uri>().map_err(|e| malformed(&e))?;
+
+    if !http2 {
+        let authority = &url[Position::BeforeHost..Position::BeforePath];
+        let host = HeaderValue::from_str(authority).map_err(|e| malformed(&e))?;
+        request.headers_mut().insert(HOST, host);
+
+        // The connection belongs to this request only, so it ends with the response
+        request.headers_mut().insert(CONNECTION, HeaderValue::from_static("close"));
+
+        if http_1_0 {
+            *request.version_mut() = Version::HTTP_10;
+        }
+    }
+
+    request.headers_mut().insert(ACCEPT, HeaderValue::from_static("*/*"));
+    request.headers_mut().extend(headers);
+
+    Ok(request)
+}
+
+// Stores the heads and trailers the transport received since the last call
+fn store_received(headers: &mut headers::Headers, wire: &Wire) {
+    for event in wire.take_received() {
+        match event {
+            Event::Head(head) => headers.push_head(&head),
+            Event::Trailers(trailers) => headers.push_trailers(&trailers),
+            _ => {},
+        }
+    }
+}
+
+// Switches to HTTPS for hosts that asked for it with Strict-Transport-Security
+fn upgrade_to_https(url: &mut Url, hsts: &mut hsts::Cache, tracer: &trace::Tracer) {
+    let host = url.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']').to_owned();
+
+    if url.scheme() == "http" && hsts.is_known(&host) && url.set_scheme("https").is_ok() {
+        tracer.text(&format!("Switched from HTTP to HTTPS due to HSTS => {}", url));
+    }
+}
+
+// CURLOPT_RESUME_FROM takes precedence over CURLOPT_RANGE
+fn range(options: &Options) -> Option<String> {
+    match options.resume_from {
+        0 => options.range.clone(),
+        offset => Some(format!("{}-", offset)),
+    }
+}
+
+fn is_redirect(status: StatusCode) -> bool {
+    matches!(status,
+        StatusCode::MOVED_PERMANENTLY |
+        StatusCode::FOUND |
+        StatusCode::SEE_OTHER |
+        StatusCode::TEMPORARY_REDIRECT |
+        StatusCode::PERMANENT_REDIRECT
+    )
+}
+
+#[derive(PartialEq)]
+struct Origin {
+    scheme: String,
+    host: Option<String>,
+    port: Option<u16>,
+}
+
+impl Origin {
+    fn of(url: &Url) -> Self {
+        Self {
+            scheme: url.scheme().to_owned(),
+            host: url.host_str().map(str::to_ascii_lowercase),
+            port: url.port_or_known_default(),
+        }
+    }
 }
 
 fn parse_last_modified(last_modified: &HeaderValue) -> Option<DateTime<FixedOffset>> {
@@ -202,7 +941,7 @@ fn parse_last_modified(last_modified: &HeaderValue) -> Option<DateTime<FixedOffs
 }
 
 struct FFIWriter {
-    write_function: unsafe extern fn(
+    write_function: unsafe extern "C" fn(
         ptr: *const c_char,
         size: size_t,
         nmemb: size_t,
@@ -227,3 +966,606 @@ impl Write for FFIWriter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::ptr::null_mut;
+    use std::slice;
+    use std::thread;
+    use std::time::Instant;
+    use openssl::sha::sha256;
+    use openssl::ssl::SslVersion;
+    use openssl::symm::Cipher;
+    use openssl::x509::X509;
+    use crate::testing::{self, Server};
+    use crate::raw::{CURLALTSVC_H1, CURLSSLOPT_ALLOW_BEAST, CURLSSLOPT_NO_REVOKE};
+    use crate::raw::curl_TimeCond::{CURL_TIMECOND_IFMODSINCE, CURL_TIMECOND_IFUNMODSINCE};
+    use crate::rawx::{CURLHE_NOHEADERS, CURLSSLOPT_NO_PARTIALCHAIN};
+    use crate::session::{curl_easy_recv, curl_easy_send};
+    use crate::raw::curl_infotype::{self, *};
+    use crate::tls::{Source, TlsVersion};
+    use super::*;
+
+    fn pin(cert: &X509) -> String {
+        let spki = cert.public_key().unwrap().public_key_to_der().unwrap();
+        format!("sha256//{}", base64::encode(&sha256(&spki)))
+    }
+
+    #[test]
+    fn checks_the_pin_against_the_server_connected_to() {
+        let (acceptor, cert) = testing::acceptor();
+        let server = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], "pinned"));
+
+        let mut curl = server.handle("/");
+        curl.options.pinned_public_key = Some(pin(&cert));
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"pinned".to_vec()));
+
+        let (_, other) = testing::issue("other");
+        curl.options.pinned_public_key = Some(pin(&other));
+        assert_eq!(testing::perform(&mut curl).0, CURLE_SSL_PINNEDPUBKEYNOTMATCH);
+    }
+
+    #[test]
+    fn connects_to_the_alternative_the_origin_advertised() {
+        let (acceptor, _) = testing::acceptor();
+        let alternative = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], "alternative"));
+        let alt_svc = format!("Alt-Svc: h1=\":{}\"", alternative.port());
+
+        let (acceptor, _) = testing::acceptor();
+        let origin = Server::https(acceptor.build(), move |_| testing::response("200 OK", &[&alt_svc], "origin"));
+
+        let mut curl = origin.handle("/");
+        curl.options.alt_svc_ctrl = CURLALTSVC_H1 as c_long;
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"origin".to_vec()));
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"alternative".to_vec()));
+        assert_eq!(curl.infos.primary_port, alternative.port());
+
+        // The request is still meant for the origin
+        let request = String::from_utf8(alternative.requests().remove(0)).unwrap();
+        assert!(request.contains(&format!("\r\nhost: 127.0.0.1:{}\r\n", origin.port())));
+        assert_eq!(origin.requests().len(), 1);
+    }
+
+    #[test]
+    fn checks_the_pin_on_every_redirect() {
+        let (acceptor, _) = testing::acceptor();
+        let target = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], "target"));
+        let location = format!("Location: {}", target.url("/"));
+
+        let (acceptor, cert) = testing::acceptor();
+        let origin = Server::https(acceptor.build(), move |_| testing::response("302 Found", &[&location], ""));
+
+        let mut curl = origin.handle("/");
+        curl.options.follow_location = true;
+        curl.options.pinned_public_key = Some(pin(&cert));
+
+        assert_eq!(testing::perform(&mut curl).0, CURLE_SSL_PINNEDPUBKEYNOTMATCH);
+        assert_eq!(curl.infos.redirect_count, 1);
+        assert!(target.requests().is_empty());
+    }
+
+    // The PEM of every certificate reported by CURLINFO_CERTINFO
+    fn reported_certs(curl: &CURL) -> Vec<String> {
+        let cert_info = unsafe { &*curl.infos.cert_info.as_ptr() };
+        let mut certs = Vec::new();
+
+        for i in 0..cert_info.num_of_certs as usize {
+            let mut list = unsafe { *cert_info.certinfo.add(i) };
+
+            while let Some(node) = unsafe { list.as_ref() } {
+                let field = unsafe { CStr::from_ptr(node.data) }.to_string_lossy();
+
+                if let Some(pem) = field.strip_prefix("Cert:") {
+                    certs.push(pem.to_owned());
+                }
+
+                list = node.next;
+            }
+        }
+
+        certs
+    }
+
+    #[test]
+    fn reports_the_chain_of_the_last_hop() {
+        let (acceptor, cert) = testing::acceptor();
+        let target = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], ""));
+        let location = format!("Location: {}", target.url("/"));
+        let plain = Server::http(|_| testing::response("200 OK", &[], ""));
+
+        let (acceptor, _) = testing::acceptor();
+        let origin = Server::https(acceptor.build(), move |_| testing::response("302 Found", &[&location], ""));
+
+        let mut curl = origin.handle("/");
+        curl.options.follow_location = true;
+        curl.options.cert_info = true;
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+        assert_eq!(curl.infos.redirect_count, 1);
+        assert_eq!(reported_certs(&curl), vec![String::from_utf8(cert.to_pem().unwrap()).unwrap()]);
+
+        curl.options.url = Some(plain.url("/"));
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+        assert!(reported_certs(&curl).is_empty());
+    }
+
+    #[test]
+    fn enforces_the_tls_version_range() {
+        let (mut acceptor, _) = testing::acceptor();
+        acceptor.set_max_proto_version(Some(SslVersion::TLS1_2)).unwrap();
+        let server = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], ""));
+
+        let mut curl = server.handle("/");
+        curl.options.ssl_version_min = Some(TlsVersion::Tls13);
+        assert_eq!(testing::perform(&mut curl).0, CURLE_SSL_CONNECT_ERROR);
+
+        curl.options.ssl_version_min = Some(TlsVersion::Tls12);
+        curl.options.ssl_version_max = Some(TlsVersion::Tls12);
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+
+        let (mut acceptor, _) = testing::acceptor();
+        acceptor.set_min_proto_version(Some(SslVersion::TLS1_3)).unwrap();
+        let server = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], ""));
+
+        curl.options.url = Some(server.url("/"));
+        assert_eq!(testing::perform(&mut curl).0, CURLE_SSL_CONNECT_ERROR);
+    }
+
+    fn mutual_tls_server() -> Server {
+        let (mut acceptor, _) = testing::acceptor();
+        testing::require_client_cert(&mut acceptor);
+        Server::https(acceptor.build(), |_| testing::response("200 OK", &[], "authenticated"))
+    }
+
+    #[test]
+    fn authenticates_with_a_pem_certificate_and_encrypted_key() {
+        let server = mutual_tls_server();
+        let (key, cert) = testing::issue("client");
+
+        let mut curl = server.handle("/");
+        assert_ne!(testing::perform(&mut curl).0, CURLE_OK);
+
+        let key = key.private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), b"secret").unwrap();
+        curl.options.ssl_cert = Some(Source::Blob(cert.to_pem().unwrap()));
+        curl.options.ssl_key = Some(Source::Blob(key));
+        curl.options.key_passwd = Some("wrong".into());
+        assert_eq!(testing::perform(&mut curl).0, CURLE_SSL_CERTPROBLEM);
+
+        curl.options.key_passwd = Some("secret".into());
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"authenticated".to_vec()));
+    }
+
+    #[test]
+    fn authenticates_with_der_files_whether_or_not_the_key_is_encrypted() {
+        let server = mutual_tls_server();
+        let (key, cert) = testing::issue("client");
+
+        let mut curl = server.handle("/");
+        curl.options.ssl_cert = Some(Source::Blob(cert.to_der().unwrap()));
+        curl.options.ssl_cert_type = Some("DER".into());
+        curl.options.ssl_key_type = Some("DER".into());
+        curl.options.key_passwd = Some("secret".into());
+
+        curl.options.ssl_key = Some(Source::Blob(key.private_key_to_der().unwrap()));
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+
+        let encrypted = key.private_key_to_pkcs8_passphrase(Cipher::aes_256_cbc(), b"secret").unwrap();
+        curl.options.ssl_key = Some(Source::Blob(encrypted));
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+    }
+
+    #[test]
+    fn rejects_unknown_ciphers() {
+        let (acceptor, _) = testing::acceptor();
+        let server = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], ""));
+
+        let mut curl = server.handle("/");
+        curl.options.ssl_cipher_list = Some("NO-SUCH-CIPHER".into());
+        assert_eq!(testing::perform(&mut curl).0, CURLE_SSL_CIPHER);
+
+        curl.options.ssl_cipher_list = Some("ECDHE-ECDSA-AES128-GCM-SHA256".into());
+        curl.options.tls13_ciphers = Some("TLS_NO_SUCH_SUITE".into());
+        assert_eq!(testing::perform(&mut curl).0, CURLE_SSL_CIPHER);
+
+        curl.options.tls13_ciphers = Some("TLS_AES_128_GCM_SHA256".into());
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+    }
+
+    #[test]
+    fn trusts_a_certificate_that_is_not_self_signed_unless_told_otherwise() {
+        let (acceptor, cert) = testing::acceptor();
+        let server = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], ""));
+
+        let mut curl = server.handle("/");
+        curl.options.ca_info = Some(Source::Blob(cert.to_pem().unwrap()));
+        curl.options.ssl_options = CURLSSLOPT_ALLOW_BEAST as c_long | CURLSSLOPT_NO_REVOKE as c_long;
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+
+        curl.options.ssl_options |= CURLSSLOPT_NO_PARTIALCHAIN;
+        assert_eq!(testing::perform(&mut curl).0, CURLE_PEER_FAILED_VERIFICATION);
+    }
+
+    #[test]
+    fn trusts_the_ca_files_it_is_given() {
+        let (acceptor, _) = testing::acceptor();
+        let server = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], ""));
+
+        let mut curl = server.handle("/");
+        curl.options.ca_info = None;
+        assert_eq!(testing::perform(&mut curl).0, CURLE_PEER_FAILED_VERIFICATION);
+
+        let dir = testing::TempDir::new();
+        fs::write(dir.file("ca.pem"), testing::ca_pem()).unwrap();
+        curl.options.ca_info = Some(Source::File(dir.file("ca.pem")));
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+
+        curl.options.ca_info = Some(Source::File(dir.file("missing.pem")));
+        assert_eq!(testing::perform(&mut curl).0, CURLE_SSL_CACERT_BADFILE);
+
+        // A directory is searched by the hash of the subject name
+        let ca = X509::from_pem(&testing::ca_pem()).unwrap();
+        fs::rename(dir.file("ca.pem"), dir.file(&format!("{:08x}.0", ca.subject_name_hash()))).unwrap();
+        curl.options.ca_info = None;
+        curl.options.ca_path = Some(dir.path().to_str().unwrap().to_owned());
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+    }
+
+    unsafe extern "C" fn record(_: *mut CURL, kind: curl_infotype::Type, data: *mut c_char, size: size_t, userptr: *mut c_void) -> c_int {
+        let data = slice::from_raw_parts(data as *const u8, size);
+        (*(userptr as *mut Vec<(curl_infotype::Type, Vec<u8>)>)).push((kind, data.to_vec()));
+        0
+    }
+
+    fn traced(curl: &mut CURL) -> Vec<(curl_infotype::Type, Vec<u8>)> {
+        let mut trace = Vec::new();
+        curl.options.verbose = true;
+        curl.options.debug_function = Some(record);
+        curl.options.debug_data = &mut trace as *mut Vec<_> as *mut c_void;
+        assert_eq!(testing::perform(&mut *curl), (CURLE_OK, b"hello".to_vec()));
+        curl.options.debug_data = null_mut();
+        trace
+    }
+
+    fn joined(trace: &[(curl_infotype::Type, Vec<u8>)], kind: curl_infotype::Type) -> Vec<u8> {
+        trace.iter().filter(|(k, _)| *k == kind).flat_map(|(_, data)| data.clone()).collect()
+    }
+
+    #[test]
+    fn traces_the_bytes_on_the_wire() {
+        let chunked = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\nhello\r\n0\r\n\r\n";
+        let server = Server::http(move |_| chunked.as_bytes().to_vec());
+
+        let mut curl = server.handle("/path");
+        curl.options.method = Method::POST;
+        curl.options.post_fields = Some(b"a=1".to_vec());
+        let trace = traced(&mut curl);
+
+        let received = server.requests().remove(0);
+        let head_len = received.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+        assert_eq!(joined(&trace, CURLINFO_HEADER_OUT), &received[..head_len]);
+        assert_eq!(joined(&trace, CURLINFO_DATA_OUT), b"a=1");
+        assert_eq!(joined(&trace, CURLINFO_DATA_IN), b"hello");
+
+        let header_in = trace.iter().filter(|(kind, _)| *kind == CURLINFO_HEADER_IN).map(|(_, line)| line.clone()).collect::<Vec<_>>();
+        assert_eq!(header_in, vec![
+            b"HTTP/1.1 200 OK\r\n".to_vec(),
+            b"Transfer-Encoding: chunked\r\n".to_vec(),
+            b"Connection: close\r\n".to_vec(),
+            b"\r\n".to_vec(),
+        ]);
+    }
+
+    #[test]
+    fn traces_tls_records() {
+        let (acceptor, _) = testing::acceptor();
+        let server = Server::https(acceptor.build(), |_| testing::response("200 OK", &[], "hello"));
+
+        let mut curl = server.handle("/");
+        let trace = traced(&mut curl);
+
+        // Every TLS record starts with its content type, the first one being a handshake
+        let ssl_out = joined(&trace, CURLINFO_SSL_DATA_OUT);
+        let ssl_in = joined(&trace, CURLINFO_SSL_DATA_IN);
+        assert_eq!(ssl_out.first(), Some(&22));
+        assert_eq!(ssl_in.first(), Some(&22));
+
+        let kinds = trace.iter().map(|(kind, _)| *kind).collect::<Vec<_>>();
+        let first_header = kinds.iter().position(|&kind| kind == CURLINFO_HEADER_OUT).unwrap();
+        assert!(kinds[..first_header].contains(&CURLINFO_SSL_DATA_OUT));
+        assert!(kinds[first_header..].contains(&CURLINFO_SSL_DATA_IN));
+    }
+
+    #[test]
+    fn times_out_waiting_for_the_response() {
+        let server = Server::stalling(Duration::from_secs(60), b"");
+
+        let mut curl = server.handle("/");
+        curl.options.timeout = Some(Duration::from_millis(500));
+        let started = Instant::now();
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OPERATION_TIMEDOUT);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn the_timeout_covers_the_whole_transfer() {
+        // The head takes half of the time, the rest of the body never comes
+        let server = Server::stalling(Duration::from_secs(1), b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0");
+
+        let mut curl = server.handle("/");
+        curl.options.timeout = Some(Duration::from_secs(2));
+        let started = Instant::now();
+        assert_eq!(testing::perform(&mut curl), (CURLE_OPERATION_TIMEDOUT, b"0".to_vec()));
+        assert!(started.elapsed() >= Duration::from_secs(2));
+        assert!(started.elapsed() < Duration::from_millis(2700));
+    }
+
+    #[test]
+    fn the_connect_timeout_covers_the_tls_handshake() {
+        let server = Server::stalling(Duration::from_secs(60), b"");
+
+        let mut curl = server.handle("/");
+        curl.options.url = Some(server.url("/").replacen("http", "https", 1));
+        curl.options.connect_timeout = Duration::from_millis(500);
+        let started = Instant::now();
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OPERATION_TIMEDOUT);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn gives_up_on_slow_transfers() {
+        let server = Server::stalling(Duration::from_secs(0), b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0");
+
+        let mut curl = server.handle("/");
+        curl.options.low_speed_limit = 100;
+        curl.options.low_speed_time = Duration::from_secs(1);
+        let started = Instant::now();
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OPERATION_TIMEDOUT);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    // The path of the request, or an empty string
+    fn path(request: &[u8]) -> String {
+        testing::request_line(request).split(' ').nth(1).unwrap_or_default().to_owned()
+    }
+
+    #[test]
+    fn follows_redirects_up_to_the_limit() {
+        // "/0" leads to "/1" and so on, up to "/3"
+        let server = Server::http(|request| match path(request).as_str() {
+            "/3" => testing::response("200 OK", &[], "done"),
+            path => {
+                let next = path[1..].parse::<u32>().unwrap() + 1;
+                testing::response("302 Found", &[&format!("Location: /{}", next)], "")
+            },
+        });
+
+        let mut curl = server.handle("/0");
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, Vec::new()));
+        assert_eq!(curl.infos.response_code, 302);
+        assert_eq!(curl.infos.redirect_url, CString::new(server.url("/1")).ok());
+
+        curl.options.follow_location = true;
+        curl.options.max_redirects = Some(3);
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"done".to_vec()));
+        assert_eq!(curl.infos.redirect_count, 3);
+        assert_eq!(curl.infos.redirect_url, None);
+
+        curl.options.max_redirects = Some(2);
+        assert_eq!(testing::perform(&mut curl).0, CURLE_TOO_MANY_REDIRECTS);
+        assert_eq!(curl.infos.redirect_count, 2);
+    }
+
+    #[test]
+    fn turns_post_into_get_on_redirects_unless_told_otherwise() {
+        // "/301" redirects with that status and so on
+        let server = Server::http(|request| match path(request).as_str() {
+            "/target" => testing::response("200 OK", &[], ""),
+            status => testing::response(&format!("{} Redirect", &status[1..]), &["Location: /target"], ""),
+        });
+
+        for &(status, post_redirect, method) in &[
+            ("301", 0, "GET"),
+            ("302", 0, "GET"),
+            ("303", 0, "GET"),
+            ("301", CURL_REDIR_POST_301, "POST"),
+            ("302", CURL_REDIR_POST_302, "POST"),
+            ("303", CURL_REDIR_POST_303, "POST"),
+            ("302", CURL_REDIR_POST_301 | CURL_REDIR_POST_303, "GET"),
+        ] {
+            let mut curl = server.handle(&format!("/{}", status));
+            curl.options.follow_location = true;
+            curl.options.post_redirect = post_redirect as c_long;
+            curl.options.method = Method::POST;
+            curl.options.post_fields = Some(b"a=1".to_vec());
+            assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+
+            let request = server.requests().pop().unwrap();
+            assert_eq!(testing::request_line(&request), format!("{} /target HTTP/1.1", method), "{}", status);
+            assert_eq!(request.ends_with(b"\r\n\r\na=1"), method == "POST", "{}", status);
+        }
+    }
+
+    #[test]
+    fn asks_for_ranges_and_checks_resumed_downloads() {
+        let server = Server::http(|request| match path(request).as_str() {
+            "/partial" => testing::response("206 Partial Content", &["Content-Range: bytes 5-9/10"], "56789"),
+            "/unsatisfiable" => testing::response("416 Range Not Satisfiable", &["Content-Range: bytes */10"], ""),
+            _ => testing::response("200 OK", &[], "0123456789"),
+        });
+
+        let mut curl = server.handle("/full");
+        curl.options.range = Some("0-4".into());
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"0123456789".to_vec()));
+        let request = String::from_utf8(server.requests().pop().unwrap()).unwrap();
+        assert!(request.contains("\r\nrange: bytes=0-4\r\n"));
+
+        // CURLOPT_RESUME_FROM wins
+        let mut curl = server.handle("/partial");
+        curl.options.range = Some("0-4".into());
+        curl.options.resume_from = 5;
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"56789".to_vec()));
+        let request = String::from_utf8(server.requests().pop().unwrap()).unwrap();
+        assert!(request.contains("\r\nrange: bytes=5-\r\n"));
+
+        let mut curl = server.handle("/full");
+        curl.options.resume_from = 5;
+        assert_eq!(testing::perform(&mut curl).0, CURLE_RANGE_ERROR);
+
+        let mut curl = server.handle("/unsatisfiable");
+        curl.options.resume_from = 5;
+        assert_eq!(testing::perform(&mut curl).0, CURLE_RANGE_ERROR);
+
+        // Nothing was left to download
+        let mut curl = server.handle("/full");
+        curl.options.resume_from = 10;
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, Vec::new()));
+    }
+
+    #[test]
+    fn leaves_out_documents_that_do_not_meet_the_time_condition() {
+        let server = Server::http(|request| match path(request).as_str() {
+            "/not-modified" => b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_vec(),
+            "/old" => testing::response("200 OK", &["Last-Modified: Sat, 01 Jan 2000 00:00:00 GMT"], "old"),
+            _ => testing::response("200 OK", &["Last-Modified: Sat, 01 Jan 2022 00:00:00 GMT"], "new"),
+        });
+
+        let perform = |path: &str, time_condition| {
+            let mut curl = server.handle(path);
+            curl.options.time_condition = time_condition;
+            curl.options.time_value = 1_000_000_000;
+            let (code, body) = testing::perform(&mut curl);
+            assert_eq!(code, CURLE_OK);
+            (curl.infos.condition_unmet, String::from_utf8(body).unwrap())
+        };
+
+        assert_eq!(perform("/not-modified", CURL_TIMECOND_IFMODSINCE), (true, String::new()));
+        let request = String::from_utf8(server.requests().pop().unwrap()).unwrap();
+        assert!(request.contains("\r\nif-modified-since: Sun, 09 Sep 2001 01:46:40 GMT\r\n"));
+
+        assert_eq!(perform("/old", CURL_TIMECOND_IFMODSINCE), (true, String::new()));
+        assert_eq!(perform("/new", CURL_TIMECOND_IFMODSINCE), (false, "new".into()));
+        assert_eq!(perform("/old", CURL_TIMECOND_IFUNMODSINCE), (false, "old".into()));
+        assert_eq!(perform("/new", CURL_TIMECOND_IFUNMODSINCE), (true, String::new()));
+    }
+
+    #[test]
+    fn connects_to_the_unix_socket_instead() {
+        let dir = testing::TempDir::new();
+        let socket = dir.file("socket");
+        let server = Server::unix(Path::new(&socket), |_| testing::response("200 OK", &[], "unix"));
+
+        let mut curl = server.handle("/");
+        curl.options.unix_socket = Some(connect::UnixSocket::Path(socket));
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"unix".to_vec()));
+
+        let request = String::from_utf8(server.requests().remove(0)).unwrap();
+        assert!(request.starts_with("GET / HTTP/1.1\r\n"));
+        assert!(request.contains("\r\nhost: 127.0.0.1\r\n"));
+    }
+
+    #[test]
+    fn sends_and_receives_over_a_connect_only_connection() {
+        let server = Server::http(|_| testing::response("200 OK", &[], "raw"));
+        let mut curl = server.handle("/");
+        let mut n = 0;
+
+        let code = unsafe { curl_easy_send(&mut *curl, b"x".as_ptr() as *const c_void, 1, &mut n) };
+        assert_eq!(code, CURLE_UNSUPPORTED_PROTOCOL);
+
+        curl.options.connect_only = 1;
+        assert_eq!(curl.perform(), CURLE_OK);
+
+        let request = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let mut sent = 0;
+
+        while sent < request.len() {
+            let rest = &request[sent..];
+            match unsafe { curl_easy_send(&mut *curl, rest.as_ptr() as *const c_void, rest.len(), &mut n) } {
+                CURLE_OK => sent += n,
+                CURLE_AGAIN => thread::sleep(Duration::from_millis(10)),
+                code => panic!("curl_easy_send failed with {}", code),
+            }
+        }
+
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 256];
+
+        loop {
+            match unsafe { curl_easy_recv(&mut *curl, buffer.as_mut_ptr() as *mut c_void, buffer.len(), &mut n) } {
+                CURLE_OK if n == 0 => break,
+                CURLE_OK => response.extend_from_slice(&buffer[..n]),
+                CURLE_AGAIN => thread::sleep(Duration::from_millis(10)),
+                code => panic!("curl_easy_recv failed with {}", code),
+            }
+        }
+
+        assert_eq!(server.requests(), vec![request.to_vec()]);
+        assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(b"\r\n\r\nraw"));
+    }
+
+    #[test]
+    fn speaks_the_http_version_asked_for() {
+        let server = Server::http(|_| testing::response("200 OK", &[], ""));
+
+        for &version in &[CURL_HTTP_VERSION_1_0, CURL_HTTP_VERSION_1_1, CURL_HTTP_VERSION_NONE] {
+            let mut curl = server.handle("/");
+            curl.options.http_version = version as c_long;
+            assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+            assert_eq!(curl.infos.http_version, CURL_HTTP_VERSION_1_1 as c_long);
+        }
+
+        let lines = server.requests().iter().map(|request| testing::request_line(request)).collect::<Vec<_>>();
+        assert_eq!(lines, ["GET / HTTP/1.0", "GET / HTTP/1.1", "GET / HTTP/1.1"]);
+
+        let server = Server::h2c("over h2");
+        let mut curl = server.handle("/path?query");
+        curl.options.http_version = CURL_HTTP_VERSION_2_PRIOR_KNOWLEDGE as c_long;
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"over h2".to_vec()));
+        assert_eq!(curl.infos.http_version, CURL_HTTP_VERSION_2_0 as c_long);
+
+        let request = testing::request_line(&server.requests().remove(0));
+        assert_eq!(request, format!("GET {} HTTP/2.0", server.url("/path?query")));
+    }
+
+    #[test]
+    fn reports_no_addresses_when_connecting_fails() {
+        let server = Server::http(|_| testing::response("200 OK", &[], ""));
+
+        let mut curl = server.handle("/");
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+        assert_eq!(curl.infos.primary_port, server.port());
+        assert_ne!(curl.infos.local_port, 0);
+
+        // Nothing listens there once the listener is gone
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        curl.options.url = Some(format!("http://127.0.0.1:{}/", port));
+        assert_eq!(testing::perform(&mut curl).0, CURLE_COULDNT_CONNECT);
+        assert_eq!(curl.infos.primary_ip.as_bytes(), b"");
+        assert_eq!(curl.infos.primary_port, 0);
+        assert_eq!(curl.infos.local_port, 0);
+    }
+
+    #[test]
+    fn reset_forgets_the_last_transfer() {
+        let server = Server::http(|_| testing::response("200 OK", &["X-Test: 1"], ""));
+
+        let mut curl = server.handle("/");
+        assert_eq!(testing::perform(&mut curl).0, CURLE_OK);
+        curl.options.connect_only = 1;
+        assert_eq!(curl.perform(), CURLE_OK);
+        assert!(curl.session.is_some());
+
+        unsafe { curl_easy_reset(&mut *curl) };
+        assert!(curl.options.url.is_none());
+        assert!(curl.session.is_none());
+        assert!(curl.websocket.is_none());
+        assert_eq!(curl.infos.primary_port, 0);
+
+        let mut header = null_mut();
+        let code = unsafe { headers::curl_easy_header(&mut *curl, c_str!("x-test").as_ptr(), 0, CURLH_HEADER, -1, &mut header) };
+        assert_eq!(code, CURLHE_NOHEADERS);
+    }
+}
@@ -0,0 +1,205 @@
+use std::cell::Cell;
+use std::io::{self, BufRead, BufReader, Read};
+use std::rc::Rc;
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use crate::error::Error;
+use crate::raw::CURLcode::*;
+
+/// The value sent for an empty `CURLOPT_ACCEPT_ENCODING`.
+pub const SUPPORTED: &str = "deflate, gzip, br, zstd";
+
+/// Undoes the `Content-Encoding` of a response body while it is read.
+pub struct Decoder<'a> {
+    reader: Box<dyn Read + 'a>,
+    source_failed: Rc<Cell<bool>>,
+    corrupt: bool,
+}
+
+impl<'a> Decoder<'a> {
+    /// `encodings` lists the encodings in the order they were applied.
+    pub fn new<R: Read + 'a>(source: R, encodings: &[String]) -> Result<Self, Error> {
+        let source_failed = Rc::new(Cell::new(false));
+        let mut reader: Box<dyn Read + 'a> = Box::new(Source {
+            inner: source,
+            failed: source_failed.clone(),
+        });
+
+        for encoding in encodings.iter().rev() {
+            reader = match encoding.to_ascii_lowercase().as_str() {
+                "identity" | "none" => reader,
+                "gzip" | "x-gzip" => Box::new(GzDecoder::new(reader)),
+                "deflate" => Box::new(Deflate::new(reader)),
+                "br" => Box::new(brotli::Decompressor::new(reader, 4096)),
+                "zstd" => Box::new(zstd::stream::read::Decoder::new(reader)
+                    .map_err(|e| Error::new(CURLE_BAD_CONTENT_ENCODING, e.to_string()))?),
+                _ => return Err(Error::new(
+                    CURLE_BAD_CONTENT_ENCODING,
+                    format!("Unrecognized content encoding type. libcurl understands {} content encodings.", SUPPORTED),
+                )),
+            };
+        }
+
+        Ok(Self {
+            reader,
+            source_failed,
+            corrupt: false,
+        })
+    }
+
+    /// Whether reading failed because of the encoded data rather than the connection.
+    pub fn is_corrupt(&self) -> bool {
+        self.corrupt
+    }
+}
+
+impl<'a> Read for Decoder<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf).map_err(|e| {
+            if self.source_failed.get() {
+                return e;
+            }
+
+            self.corrupt = true;
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Error while processing content unencoding: {}", e),
+            )
+        })
+    }
+}
+
+// Remembers whether an error came from below the decoders
+struct Source<R> {
+    inner: R,
+    failed: Rc<Cell<bool>>,
+}
+
+impl<R: Read> Read for Source<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf).inspect_err(|_| {
+            self.failed.set(true);
+        })
+    }
+}
+
+// Some servers send "deflate" without the zlib wrapper,
+// so like libcurl, look at the first bytes to tell both apart.
+struct Deflate<'a> {
+    inner: Option<BufReader<Box<dyn Read + 'a>>>,
+    decoder: Option<Box<dyn Read + 'a>>,
+}
+
+impl<'a> Deflate<'a> {
+    fn new(inner: Box<dyn Read + 'a>) -> Self {
+        Self {
+            inner: Some(BufReader::new(inner)),
+            decoder: None,
+        }
+    }
+}
+
+impl<'a> Read for Deflate<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.decoder.is_none() {
+            let zlib = match &mut self.inner {
+                Some(inner) => has_zlib_header(inner.fill_buf()?),
+                None => return Ok(0),
+            };
+            let inner = self.inner.take().expect("deflate input");
+
+            self.decoder = Some(match zlib {
+                true => Box::new(ZlibDecoder::new(inner)),
+                false => Box::new(DeflateDecoder::new(inner)),
+            });
+        }
+
+        match &mut self.decoder {
+            Some(decoder) => decoder.read(buf),
+            None => Ok(0),
+        }
+    }
+}
+
+fn has_zlib_header(data: &[u8]) -> bool {
+    match data {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use flate2::Compression;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use crate::testing::{self, Server};
+    use super::*;
+
+    const BODY: &[u8] = b"hello, hello, hello";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decode(data: &[u8], encodings: &[&str]) -> io::Result<Vec<u8>> {
+        let encodings = encodings.iter().map(|&encoding| encoding.to_owned()).collect::<Vec<_>>();
+        let mut decoded = Vec::new();
+        Decoder::new(data, &encodings).unwrap().read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn decodes_every_supported_encoding() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(BODY).unwrap();
+        let mut deflate = DeflateEncoder::new(Vec::new(), Compression::default());
+        deflate.write_all(BODY).unwrap();
+        let mut br = Vec::new();
+        brotli::CompressorReader::new(BODY, 4096, 5, 22).read_to_end(&mut br).unwrap();
+        let zstd = zstd::stream::encode_all(BODY, 0).unwrap();
+
+        assert_eq!(decode(&gzip(BODY), &["gzip"]).unwrap(), BODY);
+        assert_eq!(decode(&gzip(BODY), &["X-GZIP"]).unwrap(), BODY);
+        assert_eq!(decode(&zlib.finish().unwrap(), &["deflate"]).unwrap(), BODY);
+        assert_eq!(decode(&deflate.finish().unwrap(), &["deflate"]).unwrap(), BODY);
+        assert_eq!(decode(&br, &["br"]).unwrap(), BODY);
+        assert_eq!(decode(&zstd, &["zstd"]).unwrap(), BODY);
+        assert_eq!(decode(BODY, &["identity"]).unwrap(), BODY);
+
+        // Applied in order, so undone the other way round
+        let mut both = Vec::new();
+        brotli::CompressorReader::new(&gzip(BODY)[..], 4096, 5, 22).read_to_end(&mut both).unwrap();
+        assert_eq!(decode(&both, &["gzip", "br"]).unwrap(), BODY);
+    }
+
+    #[test]
+    fn rejects_unknown_and_corrupt_encodings() {
+        let error = Decoder::new(BODY, &["compress".to_owned()]).err().unwrap();
+        assert_eq!(error.code, CURLE_BAD_CONTENT_ENCODING);
+
+        let mut decoder = Decoder::new(BODY, &["gzip".to_owned()]).unwrap();
+        assert!(decoder.read_to_end(&mut Vec::new()).is_err());
+        assert!(decoder.is_corrupt());
+    }
+
+    #[test]
+    fn an_empty_accept_encoding_advertises_everything_supported() {
+        let server = Server::http(|_| {
+            let head = "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Encoding: gzip\r\n\r\n";
+            [head.as_bytes(), &gzip(BODY)].concat()
+        });
+
+        let mut curl = server.handle("/");
+        curl.options.accept_encoding = Some(String::new());
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, BODY.to_vec()));
+
+        let request = String::from_utf8(server.requests().remove(0)).unwrap();
+        assert!(request.contains(&format!("\r\naccept-encoding: {}\r\n", SUPPORTED)));
+
+        // Without the option, the body is passed on as it is
+        let mut curl = server.handle("/");
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, gzip(BODY)));
+    }
+}
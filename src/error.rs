@@ -54,12 +54,27 @@ impl ErrorBuffer {
 }
 
 #[no_mangle]
-pub extern fn curl_easy_strerror(code: CURLcode::Type) -> *const c_char {
+pub extern "C" fn curl_easy_strerror(code: CURLcode::Type) -> *const c_char {
     use crate::CURLcode::*;
     match code {
         CURLE_BAD_FUNCTION_ARGUMENT => c_str!("Bad function argument"),
         CURLE_UNKNOWN_OPTION => c_str!("Unknown option"),
         CURLE_NOT_BUILT_IN => c_str!("Not built-in"),
+        CURLE_UNSUPPORTED_PROTOCOL => c_str!("Unsupported protocol"),
+        CURLE_COULDNT_RESOLVE_HOST => c_str!("Couldn't resolve host name"),
+        CURLE_COULDNT_CONNECT => c_str!("Couldn't connect to server"),
+        CURLE_INTERFACE_FAILED => c_str!("Failed binding local connection end"),
+        CURLE_ABORTED_BY_CALLBACK => c_str!("Operation was aborted by an application callback"),
+        CURLE_SSL_CONNECT_ERROR => c_str!("SSL connect error"),
+        CURLE_PEER_FAILED_VERIFICATION => c_str!("SSL peer certificate or SSH remote key was not OK"),
+        CURLE_AGAIN => c_str!("Socket not ready for send/recv"),
+        CURLE_RANGE_ERROR => c_str!("Requested range was not delivered by the server"),
+        CURLE_BAD_CONTENT_ENCODING => c_str!("Unrecognized or bad HTTP Content or Transfer-Encoding"),
+        CURLE_OPERATION_TIMEDOUT => c_str!("Timeout was reached"),
+        CURLE_TOO_MANY_REDIRECTS => c_str!("Number of redirects hit maximum amount"),
+        CURLE_SSL_CERTPROBLEM => c_str!("Problem with the local SSL certificate"),
+        CURLE_SSL_CIPHER => c_str!("Couldn't use specified SSL cipher"),
+        CURLE_SSL_PINNEDPUBKEYNOTMATCH => c_str!("SSL public key does not match pinned public key"),
         _ => c_str!("Unknown error code"),
     }
     .as_ptr()
@@ -70,3 +85,18 @@ impl Default for ErrorBuffer {
         Self::new()
     }
 }
+
+#[derive(Debug)]
+pub struct Error {
+    pub code: CURLcode::Type,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(code: CURLcode::Type, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::ffi::{CStr, CString};
+use std::ptr::null_mut;
+use libc::*;
+use crate::CURL;
+use crate::rawx::*;
+use crate::util::borrow_raw::*;
+
+// Every origin an application may ask for
+const ALL_ORIGINS: c_uint = CURLH_HEADER | CURLH_TRAILER | CURLH_CONNECT | CURLH_1XX | CURLH_PSEUDO;
+// Like libcurl, a reserved bit is set in the origins handed out so they can't be compared with ==
+const RESERVED_ORIGIN: c_uint = 1 << 27;
+
+struct Header {
+    name: CString,
+    value: CString,
+    origin: c_uint,
+    request: usize,
+}
+
+/// The response headers of every request of the last transfer, redirects included.
+///
+/// Names and values stay valid until the next transfer or cleanup, while each of
+/// `curl_easy_header` and `curl_easy_nextheader` reuses its own `curl_header`.
+/// There is no proxy support, so there are never any `CURLH_CONNECT` headers.
+pub struct Headers {
+    headers: Vec<Header>,
+    requests: usize,
+    found: curl_header,
+    next: curl_header,
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Self {
+            headers: Vec::new(),
+            requests: 0,
+            found: empty_header(),
+            next: empty_header(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Starts a new request, which the headers pushed from now on belong to.
+    pub fn start_request(&mut self) {
+        self.requests += 1;
+    }
+
+    /// Stores headers of the current request.
+    pub fn push<'a>(&mut self, origin: c_uint, fields: impl IntoIterator<Item = (&'a str, &'a [u8])>) {
+        let request = self.requests.saturating_sub(1);
+
+        for (name, value) in fields {
+            self.headers.push(Header {
+                name: CString::new(name).unwrap_or_default(),
+                value: CString::new(value).unwrap_or_default(),
+                origin,
+                request,
+            });
+        }
+    }
+
+    /// Stores the fields of a response head as received, which are informational for 1xx responses.
+    pub fn push_head(&mut self, head: &[u8]) {
+        let head = String::from_utf8_lossy(head);
+        let mut lines = head.split("\r\n");
+        let informational = lines.next()
+            .and_then(|status_line| status_line.split(' ').nth(1))
+            .is_some_and(|status| status.starts_with('1'));
+
+        let origin = match informational {
+            true => CURLH_1XX,
+            false => CURLH_HEADER,
+        };
+        self.push(origin, fields(lines));
+    }
+
+    /// Stores the trailer fields that followed a chunked body.
+    pub fn push_trailers(&mut self, trailers: &[u8]) {
+        let trailers = String::from_utf8_lossy(trailers);
+        self.push(CURLH_TRAILER, fields(trailers.split("\r\n")));
+    }
+
+    // -1 is the last request
+    fn request(&self, request: c_int) -> Option<usize> {
+        match request {
+            -1 => self.requests.checked_sub(1),
+            request if (request as usize) < self.requests => Some(request as usize),
+            _ => None,
+        }
+    }
+
+    // How many headers of the request share the name of the one at `position`, and which of them it is
+    fn count(&self, position: usize, origin: c_uint) -> (size_t, size_t) {
+        let header = &self.headers[position];
+        let same = |other: &Header| {
+            other.request == header.request && other.origin & origin != 0 && eq_ignore_case(&other.name, &header.name)
+        };
+
+        let amount = self.headers.iter().filter(|&other| same(other)).count();
+        let index = self.headers[..position].iter().filter(|&other| same(other)).count();
+
+        (amount, index)
+    }
+
+    fn describe(&self, position: usize, origin: c_uint) -> curl_header {
+        let header = &self.headers[position];
+        let (amount, index) = self.count(position, origin);
+
+        curl_header {
+            name: header.name.as_ptr() as *mut c_char,
+            value: header.value.as_ptr() as *mut c_char,
+            amount,
+            index,
+            origin: header.origin | RESERVED_ORIGIN,
+            anchor: header as *const Header as *mut c_void,
+        }
+    }
+
+    fn find(&mut self, name: &CStr, index: size_t, origin: c_uint, request: c_int) -> Result<*mut curl_header, c_int> {
+        if self.headers.is_empty() {
+            return Err(CURLHE_NOHEADERS);
+        }
+
+        let request = self.request(request).ok_or(CURLHE_NOREQUEST)?;
+
+        let matches: Vec<_> = self.headers.iter()
+            .enumerate()
+            .filter(|(_, header)| header.request == request && header.origin & origin != 0)
+            .filter(|(_, header)| eq_ignore_case(&header.name, name))
+            .map(|(position, _)| position)
+            .collect();
+
+        let position = match matches.get(index) {
+            Some(&position) => position,
+            None if matches.is_empty() => return Err(CURLHE_MISSING),
+            None => return Err(CURLHE_BADINDEX),
+        };
+
+        self.found = self.describe(position, origin);
+
+        Ok(&mut self.found)
+    }
+
+    fn next(&mut self, origin: c_uint, request: c_int, prev: *const curl_header) -> *mut curl_header {
+        let request = match self.request(request) {
+            Some(request) => request,
+            None => return null_mut(),
+        };
+
+        // The anchor points at the stored header, which is gone if prev came from an earlier transfer
+        let start = match unsafe { prev.as_ref() } {
+            None => 0,
+            Some(prev) => match self.headers.iter().position(|header| header as *const Header as *mut c_void == prev.anchor) {
+                Some(position) => position + 1,
+                None => return null_mut(),
+            },
+        };
+
+        let position = (start..self.headers.len()).find(|&position| {
+            let header = &self.headers[position];
+            header.request == request && header.origin & origin != 0
+        });
+
+        match position {
+            Some(position) => {
+                self.next = self.describe(position, origin);
+                &mut self.next
+            },
+            None => null_mut(),
+        }
+    }
+}
+
+impl Default for Headers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn fields<'a>(lines: impl Iterator<Item = &'a str>) -> impl Iterator<Item = (&'a str, &'a [u8])> {
+    lines
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut field = line.splitn(2, ':');
+            let name = field.next().unwrap_or_default().trim();
+            let value = field.next().unwrap_or_default().trim();
+            (name, value.as_bytes())
+        })
+}
+
+fn empty_header() -> curl_header {
+    curl_header {
+        name: null_mut(),
+        value: null_mut(),
+        amount: 0,
+        index: 0,
+        origin: 0,
+        anchor: null_mut(),
+    }
+}
+
+fn eq_ignore_case(a: &CStr, b: &CStr) -> bool {
+    a.to_bytes().eq_ignore_ascii_case(b.to_bytes())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn curl_easy_header(
+    easy: *mut CURL,
+    name: *const c_char,
+    index: size_t,
+    origin: c_uint,
+    request: c_int,
+    hout: *mut *mut curl_header,
+) -> c_int {
+    if name.is_null() || hout.is_null() || origin == 0 || origin & !ALL_ORIGINS != 0 || request < -1 {
+        return CURLHE_BAD_ARGUMENT;
+    }
+
+    easy.borrow_raw_mut(|curl| {
+        match curl.headers.find(CStr::from_ptr(name), index, origin, request) {
+            Ok(header) => { *hout = header; CURLHE_OK },
+            Err(code) => code,
+        }
+    })
+    .unwrap_or(CURLHE_BAD_ARGUMENT)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn curl_easy_nextheader(
+    easy: *mut CURL,
+    origin: c_uint,
+    request: c_int,
+    prev: *mut curl_header,
+) -> *mut curl_header {
+    if request < -1 {
+        return null_mut();
+    }
+
+    easy.borrow_raw_mut(|curl| curl.headers.next(origin, request, prev))
+        .unwrap_or_else(null_mut)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raw::CURLcode::CURLE_OK;
+    use crate::testing::{self, Server};
+    use super::*;
+
+    fn header(curl: &mut CURL, name: &CStr, origin: c_uint) -> Result<(String, String), c_int> {
+        let mut hout = null_mut();
+        let code = unsafe { curl_easy_header(curl, name.as_ptr(), 0, origin, -1, &mut hout) };
+        if code != CURLHE_OK {
+            return Err(code);
+        }
+
+        let header = unsafe { &*hout };
+        let name = unsafe { CStr::from_ptr(header.name) }.to_string_lossy().into_owned();
+        let value = unsafe { CStr::from_ptr(header.value) }.to_string_lossy().into_owned();
+        Ok((name, value))
+    }
+
+    #[test]
+    fn stores_informational_heads_and_trailers_as_received() {
+        let server = Server::http(|_| {
+            b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>\r\n\r\n\
+            HTTP/1.1 200 OK\r\nX-Mixed-Case: final\r\nTransfer-Encoding: chunked\r\nTrailer: Checksum\r\n\r\n\
+            5\r\nhello\r\n0\r\nChecksum: abc\r\n\r\n".to_vec()
+        });
+
+        let mut curl = server.handle("/");
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"hello".to_vec()));
+
+        assert_eq!(header(&mut curl, c_str!("link"), CURLH_1XX), Ok(("Link".into(), "</style.css>".into())));
+        assert_eq!(header(&mut curl, c_str!("link"), CURLH_HEADER), Err(CURLHE_MISSING));
+        assert_eq!(header(&mut curl, c_str!("x-mixed-case"), CURLH_HEADER), Ok(("X-Mixed-Case".into(), "final".into())));
+        assert_eq!(header(&mut curl, c_str!("checksum"), CURLH_TRAILER), Ok(("Checksum".into(), "abc".into())));
+        assert_eq!(header(&mut curl, c_str!("checksum"), CURLH_HEADER), Err(CURLHE_MISSING));
+        assert_eq!(header(&mut curl, c_str!("checksum"), CURLH_CONNECT), Err(CURLHE_MISSING));
+    }
+}
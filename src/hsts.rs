@@ -0,0 +1,354 @@
+use std::ffi::CStr;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::net::IpAddr;
+use std::time::Duration as StdDuration;
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use libc::*;
+use crate::CURL;
+use crate::error::Error;
+use crate::raw::CURLcode::*;
+use crate::rawx::{curl_hstsentry, curl_index, CURLSTS_DONE, CURLSTS_FAIL, CURLSTS_OK};
+
+const DATE_FORMAT: &str = "%Y%m%d %H:%M:%S";
+const UNLIMITED: &str = "unlimited";
+const MAX_HOST_LEN: usize = 256;
+const FILE_HEADER: &str = "# Your HSTS cache. https://curl.se/docs/hsts.html\n\
+                           # This file was generated by libcurl! Edit at your own risk.\n";
+
+pub type ReadFunction = unsafe extern "C" fn(
+    easy: *mut CURL,
+    sts: *mut curl_hstsentry,
+    userp: *mut c_void,
+) -> c_int;
+
+pub type WriteFunction = unsafe extern "C" fn(
+    easy: *mut CURL,
+    sts: *mut curl_hstsentry,
+    count: *mut curl_index,
+    userp: *mut c_void,
+) -> c_int;
+
+struct Entry {
+    host: String,
+    include_subdomains: bool,
+    // `None` never expires
+    expires: Option<DateTime<Utc>>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn expire_str(&self) -> String {
+        match self.expires {
+            Some(expires) => expires.format(DATE_FORMAT).to_string(),
+            None => UNLIMITED.to_owned(),
+        }
+    }
+}
+
+/// The per-handle cache of hosts that only want to be reached over HTTPS, see RFC 6797.
+#[derive(Default)]
+pub struct Cache {
+    entries: Vec<Entry>,
+    // The CURLOPT_HSTS file the entries were loaded from
+    file: Option<String>,
+}
+
+impl Cache {
+    /// Reads a cache file in libcurl's format, unless it was already loaded.
+    ///
+    /// A missing file is an empty cache, it is created when saving.
+    pub fn load(&mut self, path: &str) {
+        if self.file.as_deref() == Some(path) {
+            return;
+        }
+
+        self.file = Some(path.to_owned());
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.splitn(2, ' ');
+            let host = fields.next().unwrap_or_default();
+
+            if let Some(expires) = parse_expiry(fields.next().unwrap_or_default().trim().trim_matches('"')) {
+                self.insert(host.trim_start_matches('.'), host.starts_with('.'), expires);
+            }
+        }
+    }
+
+    /// Writes the unexpired entries in libcurl's format.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let now = Utc::now();
+        let mut contents = String::from(FILE_HEADER);
+
+        for entry in self.entries.iter().filter(|entry| !entry.is_expired(now)) {
+            let expire = match entry.expires {
+                Some(_) => format!("\"{}\"", entry.expire_str()),
+                None => UNLIMITED.to_owned(),
+            };
+
+            contents.push_str(&format!(
+                "{}{} {}\n",
+                if entry.include_subdomains { "." } else { "" },
+                entry.host,
+                expire,
+            ));
+        }
+
+        fs::write(path, contents)
+    }
+
+    /// Adds the entries `CURLOPT_HSTSREADFUNCTION` hands out, until it says it's done.
+    pub unsafe fn read(&mut self, curl: *mut CURL, function: ReadFunction, data: *mut c_void) -> Result<(), Error> {
+        loop {
+            let mut name = [0 as c_char; MAX_HOST_LEN + 1];
+            let mut entry = curl_hstsentry {
+                name: name.as_mut_ptr(),
+                namelen: MAX_HOST_LEN,
+                include_sub_domains: 0,
+                expire: [0; 18],
+            };
+
+            match function(curl, &mut entry, data) {
+                CURLSTS_OK => {},
+                CURLSTS_DONE => return Ok(()),
+                CURLSTS_FAIL => return Err(Error::new(CURLE_ABORTED_BY_CALLBACK, "HSTS read callback failed")),
+                // Like libcurl, anything else ends the list too
+                _ => return Ok(()),
+            }
+
+            let host = CStr::from_ptr(entry.name).to_string_lossy();
+            let expire = CStr::from_ptr(entry.expire.as_ptr()).to_string_lossy();
+
+            if host.is_empty() {
+                return Err(Error::new(CURLE_BAD_FUNCTION_ARGUMENT, "HSTS read callback returned no host name"));
+            }
+
+            // A missing date never expires, while one that can't be parsed counts as expired
+            if let Some(expires) = parse_expiry(&expire) {
+                self.insert(&host, entry.include_sub_domains & 1 != 0, expires);
+            }
+        }
+    }
+
+    /// Hands the unexpired entries to `CURLOPT_HSTSWRITEFUNCTION`, until it stops taking them.
+    pub unsafe fn write(&self, curl: *mut CURL, function: WriteFunction, data: *mut c_void) {
+        let now = Utc::now();
+        let entries: Vec<_> = self.entries.iter().filter(|entry| !entry.is_expired(now)).collect();
+
+        for (index, entry) in entries.iter().enumerate() {
+            let mut name = entry.host.as_bytes().to_vec();
+            name.push(0);
+
+            let mut expire = [0 as c_char; 18];
+            for (dst, &src) in expire.iter_mut().zip(entry.expire_str().as_bytes()) {
+                *dst = src as c_char;
+            }
+
+            let mut sts = curl_hstsentry {
+                name: name.as_mut_ptr() as *mut c_char,
+                namelen: entry.host.len(),
+                include_sub_domains: entry.include_subdomains as c_uint,
+                expire,
+            };
+            let mut count = curl_index {
+                index,
+                total: entries.len(),
+            };
+
+            if function(curl, &mut sts, &mut count, data) != CURLSTS_OK {
+                break;
+            }
+        }
+    }
+
+    /// Applies a Strict-Transport-Security header received from `host` over HTTPS.
+    pub fn update(&mut self, host: &str, value: &str) {
+        let host = host.trim_end_matches('.');
+
+        // IP addresses can't be pinned to HTTPS
+        if host.parse::<IpAddr>().is_ok() {
+            return;
+        }
+
+        let (max_age, include_subdomains) = match parse_header(value) {
+            Some(directives) => directives,
+            None => return,
+        };
+
+        if max_age == 0 {
+            self.entries.retain(|entry| !entry.host.eq_ignore_ascii_case(host));
+            return;
+        }
+
+        // An overflowing max-age is as good as forever
+        let expires = Duration::from_std(StdDuration::from_secs(max_age)).ok()
+            .and_then(|max_age| Utc::now().checked_add_signed(max_age));
+
+        self.insert(host, include_subdomains, expires);
+    }
+
+    /// Whether requests to `host` have to use HTTPS.
+    pub fn is_known(&mut self, host: &str) -> bool {
+        let now = Utc::now();
+        self.entries.retain(|entry| !entry.is_expired(now));
+
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+        self.entries.iter().any(|entry| {
+            host == entry.host || (entry.include_subdomains && host.ends_with(&format!(".{}", entry.host)))
+        })
+    }
+
+    fn insert(&mut self, host: &str, include_subdomains: bool, expires: Option<DateTime<Utc>>) {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+        self.entries.retain(|entry| entry.host != host);
+        self.entries.push(Entry { host, include_subdomains, expires });
+    }
+}
+
+// "unlimited" or empty is `Some(None)`, a date `Some(Some(date))`
+fn parse_expiry(expire: &str) -> Option<Option<DateTime<Utc>>> {
+    match expire {
+        "" | UNLIMITED => Some(None),
+        expire => NaiveDateTime::parse_from_str(expire, DATE_FORMAT).ok()
+            .map(|expires| Some(Utc.from_utc_datetime(&expires))),
+    }
+}
+
+// Parses `max-age=31536000; includeSubDomains`, which is invalid without max-age
+// or with a directive given twice.
+fn parse_header(value: &str) -> Option<(u64, bool)> {
+    let mut max_age = None;
+    let mut include_subdomains = false;
+
+    for directive in value.split(';').map(str::trim).filter(|directive| !directive.is_empty()) {
+        let mut parts = directive.splitn(2, '=');
+        let name = parts.next().unwrap_or_default().trim();
+
+        if name.eq_ignore_ascii_case("max-age") {
+            if max_age.is_some() {
+                return None;
+            }
+
+            let seconds = parts.next()?.trim().trim_matches('"');
+
+            if seconds.is_empty() || !seconds.bytes().all(|byte| byte.is_ascii_digit()) {
+                return None;
+            }
+
+            // Too many digits for a number is as good as forever
+            max_age = Some(seconds.parse().unwrap_or(u64::MAX));
+        } else if name.eq_ignore_ascii_case("includeSubDomains") {
+            if include_subdomains {
+                return None;
+            }
+
+            include_subdomains = true;
+        }
+    }
+
+    Some((max_age?, include_subdomains))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr::null_mut;
+    use super::*;
+
+    // Hands out the (host, expire) pairs `userp` points to, one per call
+    unsafe extern "C" fn read_entries(_: *mut CURL, sts: *mut curl_hstsentry, userp: *mut c_void) -> c_int {
+        let entries = &mut *(userp as *mut Vec<(&str, &str)>);
+        let (host, expire) = match entries.pop() {
+            Some(entry) => entry,
+            None => return CURLSTS_DONE,
+        };
+
+        let sts = &mut *sts;
+        for (i, &byte) in host.as_bytes().iter().chain(&[0]).enumerate() {
+            *sts.name.add(i) = byte as c_char;
+        }
+        for (dst, &src) in sts.expire.iter_mut().zip(expire.as_bytes().iter().chain(&[0])) {
+            *dst = src as c_char;
+        }
+
+        CURLSTS_OK
+    }
+
+    #[test]
+    fn update_and_match() {
+        let mut cache = Cache::default();
+        cache.update("example.com", "max-age=3600; includeSubDomains");
+        cache.update("other.com", "max-age=3600");
+        cache.update("127.0.0.1", "max-age=3600");
+        cache.update("broken.com", "includeSubDomains");
+        cache.update("twice.com", "max-age=1; max-age=2");
+
+        assert!(cache.is_known("example.com"));
+        assert!(cache.is_known("www.EXAMPLE.com."));
+        assert!(cache.is_known("other.com"));
+        assert!(!cache.is_known("www.other.com"));
+        assert!(!cache.is_known("127.0.0.1"));
+        assert!(!cache.is_known("broken.com"));
+        assert!(!cache.is_known("twice.com"));
+
+        cache.update("example.com", "max-age=0");
+        assert!(!cache.is_known("example.com"));
+    }
+
+    #[test]
+    fn load_and_save() {
+        let dir = crate::testing::TempDir::new();
+        let path = &dir.file("hsts.txt");
+
+        fs::write(path, "# comment\n\
+                         .example.com \"20991231 00:00:00\"\n\
+                         forever.com unlimited\n\
+                         expired.com \"20000101 00:00:00\"\n\
+                         garbage.com \"not a date\"\n").unwrap();
+
+        let mut cache = Cache::default();
+        cache.load(path);
+
+        assert!(cache.is_known("sub.example.com"));
+        assert!(cache.is_known("forever.com"));
+        assert!(!cache.is_known("expired.com"));
+        assert!(!cache.is_known("garbage.com"));
+
+        cache.save(path).unwrap();
+        let saved = fs::read_to_string(path).unwrap();
+
+        assert!(saved.starts_with(FILE_HEADER));
+        assert!(saved.contains(".example.com \"20991231 00:00:00\"\n"));
+        assert!(saved.contains("forever.com unlimited\n"));
+        assert!(!saved.contains("expired.com"));
+    }
+
+    #[test]
+    fn read_callback_skips_unparseable_dates() {
+        let mut entries = vec![("garbage.com", "not a date"), ("forever.com", ""), ("example.com", "20991231 00:00:00")];
+        let mut cache = Cache::default();
+
+        unsafe {
+            cache.read(null_mut(), read_entries, &mut entries as *mut _ as *mut c_void).unwrap();
+        }
+
+        assert!(cache.is_known("example.com"));
+        assert!(cache.is_known("forever.com"));
+        assert!(!cache.is_known("garbage.com"));
+    }
+}
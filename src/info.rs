@@ -1,11 +1,17 @@
 use std::ffi::{VaList, CStr, CString};
 use std::convert::TryFrom;
+use std::ptr::null;
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
 use libc::*;
 use chrono::{DateTime, FixedOffset};
 use crate::util::borrow_raw::*;
 use crate::CURL;
+use crate::certinfo::CertInfo;
+use crate::protocols;
 use crate::raw::CURLINFO::{self, *};
 use crate::raw::CURLcode::{self, *};
+use crate::raw::{curl_off_t, curl_socket_t, CURL_SOCKET_BAD};
 
 pub struct Infos {
     pub last_effective_url: Option<CString>,
@@ -13,6 +19,20 @@ pub struct Infos {
     pub content_length_download: Option<u64>,
     pub size_download: u64,
     pub response_code: u16,
+    pub condition_unmet: bool,
+    pub cert_info: CertInfo,
+    pub http_auth_avail: c_ulong,
+    pub proxy_auth_avail: c_ulong,
+    pub redirect_count: u32,
+    pub redirect_time: Duration,
+    pub redirect_url: Option<CString>,
+    pub primary_ip: CString,
+    pub primary_port: u16,
+    pub local_ip: CString,
+    pub local_port: u16,
+    pub http_version: c_long,
+    pub protocol: c_long,
+    pub scheme: Option<CString>,
 }
 
 impl Infos {
@@ -23,8 +43,28 @@ impl Infos {
             content_length_download: None,
             size_download: 0,
             response_code: 0,
+            condition_unmet: false,
+            cert_info: CertInfo::new(),
+            http_auth_avail: 0,
+            proxy_auth_avail: 0,
+            redirect_count: 0,
+            redirect_time: Duration::from_secs(0),
+            redirect_url: None,
+            primary_ip: CString::default(),
+            primary_port: 0,
+            local_ip: CString::default(),
+            local_port: 0,
+            http_version: 0,
+            protocol: 0,
+            scheme: None,
         }
     }
+
+    /// Sets `CURLINFO_SCHEME` and `CURLINFO_PROTOCOL`, which libcurl reports in upper case.
+    pub fn set_scheme(&mut self, scheme: &str) {
+        self.protocol = protocols::bit(scheme).unwrap_or(0);
+        self.scheme = CString::new(scheme.to_ascii_uppercase()).ok();
+    }
 }
 
 unsafe fn str_info(mut args: VaList, str: &CStr) {
@@ -32,13 +72,33 @@ unsafe fn str_info(mut args: VaList, str: &CStr) {
     *ret = str.as_ptr();
 }
 
+unsafe fn ptr_info<T>(mut args: VaList, ptr: *const T) {
+    let ret = args.arg::<*mut *const T>();
+    *ret = ptr;
+}
+
+unsafe fn double_info(mut args: VaList, value: c_double) {
+    let ret = args.arg::<*mut c_double>();
+    *ret = value;
+}
+
+unsafe fn off_t_info(mut args: VaList, value: curl_off_t) {
+    let ret = args.arg::<*mut curl_off_t>();
+    *ret = value;
+}
+
 unsafe fn long_info(mut args: VaList, value: c_long) {
     let ret = args.arg::<*mut c_long>();
     *ret = value;
 }
 
+unsafe fn socket_info(mut args: VaList, value: curl_socket_t) {
+    let ret = args.arg::<*mut curl_socket_t>();
+    *ret = value;
+}
+
 #[no_mangle]
-pub unsafe extern fn curl_easy_getinfo(
+pub unsafe extern "C" fn curl_easy_getinfo(
     curl: *mut CURL,
     info: CURLINFO::Type,
     args:...
@@ -52,7 +112,7 @@ pub unsafe extern fn curl_easy_getinfo(
             CURLINFO_FILETIME => long_info(args, infos.file_time.map(|t| t.timestamp()).unwrap_or(-1)),
             CURLINFO_CONTENT_LENGTH_DOWNLOAD => long_info(args, infos.content_length_download.and_then(|l| i64::try_from(l).ok()).unwrap_or(-1)),
             CURLINFO_SIZE_DOWNLOAD => long_info(args, infos.size_download as c_long),
-            CURLINFO_CONDITION_UNMET => long_info(args, 0), // TODO: implement conditions
+            CURLINFO_CONDITION_UNMET => long_info(args, infos.condition_unmet as c_long),
             CURLINFO_RESPONSE_CODE => long_info(args, infos.response_code as c_long),
             CURLINFO_TOTAL_TIME => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_TOTAL_TIME)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_NAMELOOKUP_TIME => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_NAMELOOKUP_TIME)); return CURLE_BAD_FUNCTION_ARGUMENT},
@@ -74,42 +134,42 @@ pub unsafe extern fn curl_easy_getinfo(
             CURLINFO_CONTENT_LENGTH_UPLOAD_T => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_CONTENT_LENGTH_UPLOAD_T)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_STARTTRANSFER_TIME => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_STARTTRANSFER_TIME)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_CONTENT_TYPE => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_CONTENT_TYPE)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_REDIRECT_TIME => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_REDIRECT_TIME)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_REDIRECT_COUNT => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_REDIRECT_COUNT)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_REDIRECT_TIME => double_info(args, infos.redirect_time.as_secs_f64()),
+            CURLINFO_REDIRECT_COUNT => long_info(args, infos.redirect_count as c_long),
             CURLINFO_PRIVATE => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_PRIVATE)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_HTTP_CONNECTCODE => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_HTTP_CONNECTCODE)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_HTTPAUTH_AVAIL => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_HTTPAUTH_AVAIL)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_PROXYAUTH_AVAIL => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_PROXYAUTH_AVAIL)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_HTTPAUTH_AVAIL => long_info(args, infos.http_auth_avail as c_long),
+            CURLINFO_PROXYAUTH_AVAIL => long_info(args, infos.proxy_auth_avail as c_long),
             CURLINFO_OS_ERRNO => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_OS_ERRNO)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_NUM_CONNECTS => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_NUM_CONNECTS)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_SSL_ENGINES => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_SSL_ENGINES)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_COOKIELIST => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_COOKIELIST)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_LASTSOCKET => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_LASTSOCKET)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_LASTSOCKET => long_info(args, curl.session.as_ref().map_or(-1, |session| session.as_raw_fd() as c_long)),
             CURLINFO_FTP_ENTRY_PATH => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_FTP_ENTRY_PATH)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_REDIRECT_URL => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_REDIRECT_URL)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_PRIMARY_IP => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_PRIMARY_IP)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_REDIRECT_URL => ptr_info(args, infos.redirect_url.as_ref().map_or(null(), |url| url.as_ptr())),
+            CURLINFO_PRIMARY_IP => str_info(args, &infos.primary_ip),
             CURLINFO_APPCONNECT_TIME => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_APPCONNECT_TIME)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_CERTINFO => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_CERTINFO)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_CERTINFO => ptr_info(args, infos.cert_info.as_ptr()),
             CURLINFO_RTSP_SESSION_ID => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_RTSP_SESSION_ID)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_RTSP_CLIENT_CSEQ => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_RTSP_CLIENT_CSEQ)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_RTSP_SERVER_CSEQ => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_RTSP_SERVER_CSEQ)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_RTSP_CSEQ_RECV => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_RTSP_CSEQ_RECV)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_PRIMARY_PORT => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_PRIMARY_PORT)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_LOCAL_IP => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_LOCAL_IP)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_LOCAL_PORT => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_LOCAL_PORT)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_PRIMARY_PORT => long_info(args, infos.primary_port as c_long),
+            CURLINFO_LOCAL_IP => str_info(args, &infos.local_ip),
+            CURLINFO_LOCAL_PORT => long_info(args, infos.local_port as c_long),
             CURLINFO_TLS_SESSION => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_TLS_SESSION)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_ACTIVESOCKET => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_ACTIVESOCKET)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_ACTIVESOCKET => socket_info(args, curl.session.as_ref().map_or(CURL_SOCKET_BAD, AsRawFd::as_raw_fd)),
             CURLINFO_TLS_SSL_PTR => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_TLS_SSL_PTR)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_HTTP_VERSION => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_HTTP_VERSION)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_HTTP_VERSION => long_info(args, infos.http_version),
             CURLINFO_PROXY_SSL_VERIFYRESULT => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_PROXY_SSL_VERIFYRESULT)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_PROTOCOL => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_PROTOCOL)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_SCHEME => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_SCHEME)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_PROTOCOL => long_info(args, infos.protocol),
+            CURLINFO_SCHEME => ptr_info(args, infos.scheme.as_ref().map_or(null(), |scheme| scheme.as_ptr())),
             CURLINFO_TOTAL_TIME_T => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_TOTAL_TIME_T)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_NAMELOOKUP_TIME_T => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_NAMELOOKUP_TIME_T)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_CONNECT_TIME_T => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_CONNECT_TIME_T)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_PRETRANSFER_TIME_T => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_PRETRANSFER_TIME_T)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_STARTTRANSFER_TIME_T => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_STARTTRANSFER_TIME_T)); return CURLE_BAD_FUNCTION_ARGUMENT},
-            CURLINFO_REDIRECT_TIME_T => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_REDIRECT_TIME_T)); return CURLE_BAD_FUNCTION_ARGUMENT},
+            CURLINFO_REDIRECT_TIME_T => off_t_info(args, infos.redirect_time.as_micros() as curl_off_t),
             CURLINFO_APPCONNECT_TIME_T => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_APPCONNECT_TIME_T)); return CURLE_BAD_FUNCTION_ARGUMENT},
             CURLINFO_LASTONE => {eprintln!("recurl: unimplemented '{}'", stringify!(CURLINFO_LASTONE)); return CURLE_BAD_FUNCTION_ARGUMENT},
             _ => {
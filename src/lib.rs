@@ -21,29 +21,157 @@ mod util;
 mod slist;
 mod mime;
 mod error;
+mod tls;
+mod certinfo;
+mod auth;
+mod sigv4;
+mod netrc;
+mod timeout;
+mod connect;
+mod protocols;
+mod decoding;
+mod condition;
+mod trace;
+mod resolve;
+mod transport;
+mod wire;
+mod socket;
+mod session;
+mod ws;
+mod altsvc;
+mod hsts;
+mod headers;
+#[cfg(test)]
+mod testing;
 
 mod rawx {
     use libc::*;
     use crate::raw::CURLoption::{Type as CURLoption, *};
+    use crate::raw::curl_off_t;
 
-    pub const CURL_ZERO_TERMINATED: size_t = size_t::max_value() - 1;
+    pub const CURL_ZERO_TERMINATED: size_t = size_t::MAX - 1;
     pub const CURLOPT_XFERINFODATA: CURLoption = CURLOPT_PROGRESSDATA;
+    pub const CURLOPT_SSLCERT_BLOB: CURLoption = 40291;
+    pub const CURLOPT_SSLKEY_BLOB: CURLoption = 40292;
+    pub const CURLOPT_CAINFO_BLOB: CURLoption = 40309;
+    pub const CURLOPT_AWS_SIGV4: CURLoption = 10305;
+    pub const CURLOPT_SERVER_RESPONSE_TIMEOUT: CURLoption = CURLOPT_FTP_RESPONSE_TIMEOUT;
+    pub const CURLOPT_SERVER_RESPONSE_TIMEOUT_MS: CURLoption = 324;
+    pub const CURLOPT_REDIR_PROTOCOLS_STR: CURLoption = 10319;
+    pub const CURLOPT_PREREQFUNCTION: CURLoption = 20312;
+    pub const CURLOPT_PREREQDATA: CURLoption = 10313;
+    pub const CURLOPT_WS_OPTIONS: CURLoption = 320;
+    pub const CURLOPT_HSTS_CTRL: CURLoption = 299;
+    pub const CURLOPT_HSTS: CURLoption = 10300;
+    pub const CURLOPT_HSTSREADFUNCTION: CURLoption = 20301;
+    pub const CURLOPT_HSTSREADDATA: CURLoption = 10302;
+    pub const CURLOPT_HSTSWRITEFUNCTION: CURLoption = 20303;
+    pub const CURLOPT_HSTSWRITEDATA: CURLoption = 10304;
+
+    pub const CURL_PREREQFUNC_OK: c_int = 0;
+
+    pub const CURLHSTS_ENABLE: c_long = 1 << 0;
+    pub const CURLHSTS_READONLYFILE: c_long = 1 << 1;
+
+    pub const CURLSTS_OK: c_int = 0;
+    pub const CURLSTS_DONE: c_int = 1;
+    pub const CURLSTS_FAIL: c_int = 2;
+
+    pub const CURLH_HEADER: c_uint = 1 << 0;
+    pub const CURLH_TRAILER: c_uint = 1 << 1;
+    pub const CURLH_CONNECT: c_uint = 1 << 2;
+    pub const CURLH_1XX: c_uint = 1 << 3;
+    pub const CURLH_PSEUDO: c_uint = 1 << 4;
+
+    pub const CURLHE_OK: c_int = 0;
+    pub const CURLHE_BADINDEX: c_int = 1;
+    pub const CURLHE_MISSING: c_int = 2;
+    pub const CURLHE_NOHEADERS: c_int = 3;
+    pub const CURLHE_NOREQUEST: c_int = 4;
+    pub const CURLHE_BAD_ARGUMENT: c_int = 6;
+
+    pub const CURLWS_RAW_MODE: c_long = 1 << 0;
+
+    pub const CURLWS_TEXT: c_uint = 1 << 0;
+    pub const CURLWS_BINARY: c_uint = 1 << 1;
+    pub const CURLWS_CONT: c_uint = 1 << 2;
+    pub const CURLWS_CLOSE: c_uint = 1 << 3;
+    pub const CURLWS_PING: c_uint = 1 << 4;
+    pub const CURLWS_OFFSET: c_uint = 1 << 5;
+    pub const CURLWS_PONG: c_uint = 1 << 6;
+
+    pub const CURLSSLOPT_NO_PARTIALCHAIN: c_long = 1 << 2;
+    pub const CURLSSLOPT_NATIVE_CA: c_long = 1 << 4;
+
+    pub const CURLPROTO_WS: u32 = 1 << 30;
+    pub const CURLPROTO_WSS: u32 = 1 << 31;
+
+    pub const CURLAUTH_NONE: c_ulong = 0;
+    pub const CURLAUTH_BASIC: c_ulong = 1 << 0;
+    pub const CURLAUTH_DIGEST: c_ulong = 1 << 1;
+    pub const CURLAUTH_NEGOTIATE: c_ulong = 1 << 2;
+    pub const CURLAUTH_NTLM: c_ulong = 1 << 3;
+    pub const CURLAUTH_BEARER: c_ulong = 1 << 6;
+    pub const CURLAUTH_AWS_SIGV4: c_ulong = 1 << 7;
+    pub const CURLAUTH_ONLY: c_ulong = 1 << 31;
+
+    #[repr(C)]
+    #[allow(non_camel_case_types)]
+    pub struct curl_ws_frame {
+        pub age: c_int,
+        pub flags: c_int,
+        pub offset: curl_off_t,
+        pub bytesleft: curl_off_t,
+        pub len: size_t,
+    }
+
+    #[repr(C)]
+    #[allow(non_camel_case_types)]
+    pub struct curl_hstsentry {
+        pub name: *mut c_char,
+        pub namelen: size_t,
+        // The `includeSubDomains:1` bit field
+        pub include_sub_domains: c_uint,
+        pub expire: [c_char; 18],
+    }
+
+    #[repr(C)]
+    #[allow(non_camel_case_types)]
+    pub struct curl_header {
+        pub name: *mut c_char,
+        pub value: *mut c_char,
+        pub amount: size_t,
+        pub index: size_t,
+        pub origin: c_uint,
+        pub anchor: *mut c_void,
+    }
+
+    #[repr(C)]
+    #[allow(non_camel_case_types)]
+    pub struct curl_index {
+        pub index: size_t,
+        pub total: size_t,
+    }
+
+    #[repr(C)]
+    #[allow(non_camel_case_types)]
+    pub struct curl_blob {
+        pub data: *mut c_void,
+        pub len: size_t,
+        pub flags: c_uint,
+    }
 }
 
 #[no_mangle]
-pub extern fn curl_global_init(flags: c_long) -> CURLcode::Type {
+pub extern "C" fn curl_global_init(_flags: c_long) -> CURLcode::Type {
     CURLE_OK
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_free(ptr: *mut c_void) {
-    if ptr.is_null() {
-        return
-    }
-
+pub extern "C" fn curl_free(_ptr: *mut c_void) {
     // TODO
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_global_cleanup() {
+pub extern "C" fn curl_global_cleanup() {
 }
@@ -12,6 +12,7 @@ use std::slice;
 pub struct curl_mime {
     // Invariant: curl_mimepart is pinned to the heap,
     // because pointers to it are handed out to C-land
+    #[allow(clippy::vec_box)]
     parts: Vec<Box<curl_mimepart>>,
     error_buffer: WeakErrorBuffer,
 }
@@ -34,7 +35,7 @@ impl curl_mime {
 
     pub unsafe fn from_raw(this: *mut Self) {
         if !this.is_null() {
-            Box::from_raw(this);
+            drop(Box::from_raw(this));
         }
     }
 }
@@ -91,12 +92,12 @@ impl ErrorSink for curl_mimepart {
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_mime_init(curl: *mut CURL) -> *mut curl_mime {
+pub unsafe extern "C" fn curl_mime_init(curl: *mut CURL) -> *mut curl_mime {
     curl.borrow_raw_opt(curl_mime::new).into_raw()
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_mime_addpart(mime: *mut curl_mime) -> *mut curl_mimepart {
+pub unsafe extern "C" fn curl_mime_addpart(mime: *mut curl_mime) -> *mut curl_mimepart {
     mime.borrow_raw_mut(|mime| {
         let error_buffer = mime.error_buffer.clone();
         let part_ptr = curl_mimepart::new(error_buffer).into_raw();
@@ -110,12 +111,12 @@ pub unsafe extern fn curl_mime_addpart(mime: *mut curl_mime) -> *mut curl_mimepa
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_mime_free(mime: *mut curl_mime) {
+pub unsafe extern "C" fn curl_mime_free(mime: *mut curl_mime) {
     curl_mime::from_raw(mime);
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_mime_data(
+pub unsafe extern "C" fn curl_mime_data(
     part: *mut curl_mimepart,
     data: *const c_char,
     mut datasize: size_t,
@@ -141,7 +142,7 @@ pub unsafe extern fn curl_mime_data(
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_mime_name(
+pub unsafe extern "C" fn curl_mime_name(
     part: *mut curl_mimepart,
     name: *const c_char,
 ) -> CURLcode::Type {
@@ -164,7 +165,7 @@ pub unsafe extern fn curl_mime_name(
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_mime_type(
+pub unsafe extern "C" fn curl_mime_type(
     part: *mut curl_mimepart,
     mime_type: *const c_char,
 ) -> CURLcode::Type {
@@ -0,0 +1,224 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Entry {
+    pub login: Option<String>,
+    pub password: Option<String>,
+}
+
+struct Machine {
+    // `None` for the `default` entry
+    host: Option<String>,
+    entry: Entry,
+}
+
+/// The netrc file to use if `CURLOPT_NETRC_FILE` is not set.
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".netrc"))
+}
+
+/// Looks up the credentials for `host` in the netrc file at `path`.
+///
+/// A missing file is not an error, it just doesn't contain any credentials.
+/// If `login` is given, only entries for that login are considered.
+pub fn lookup(path: &Path, host: &str, login: Option<&str>) -> Result<Option<Entry>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(None),
+    };
+
+    let machines = parse(&contents)?;
+
+    let matches_login = |machine: &&Machine| match login {
+        Some(login) => machine.entry.login.as_ref().is_none_or(|entry_login| entry_login == login),
+        None => true,
+    };
+
+    let machine = machines.iter()
+        .filter(|machine| machine.host.as_ref().is_some_and(|name| name.eq_ignore_ascii_case(host)))
+        .find(matches_login)
+        .or_else(|| machines.iter().filter(|machine| machine.host.is_none()).find(matches_login));
+
+    Ok(machine.map(|machine| Entry {
+        login: machine.entry.login.clone(),
+        password: machine.entry.password.clone(),
+    }))
+}
+
+fn parse(contents: &str) -> Result<Vec<Machine>, String> {
+    let mut machines = Vec::<Machine>::new();
+    let mut tokens = Tokens::new(contents);
+
+    while let Some(token) = tokens.next() {
+        match token.as_str() {
+            "machine" => {
+                let host = tokens.next().ok_or("missing host name after 'machine'")?;
+                machines.push(Machine {
+                    host: Some(host),
+                    entry: Entry { login: None, password: None },
+                });
+            },
+            "default" => machines.push(Machine {
+                host: None,
+                entry: Entry { login: None, password: None },
+            }),
+            "login" | "password" | "account" => {
+                let value = tokens.next().ok_or_else(|| format!("missing value after '{}'", token))?;
+                let machine = machines.last_mut().ok_or_else(|| format!("'{}' outside of a machine", token))?;
+
+                match token.as_str() {
+                    "login" => machine.entry.login = Some(value),
+                    "password" => machine.entry.password = Some(value),
+                    _ => {},
+                }
+            },
+            "macdef" => {
+                tokens.next().ok_or("missing name after 'macdef'")?;
+                tokens.skip_macro();
+            },
+            // Like libcurl, anything else is skipped
+            _ => {},
+        }
+    }
+
+    Ok(machines)
+}
+
+struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(contents: &'a str) -> Self {
+        Self { rest: contents }
+    }
+
+    // Macro definitions start on the next line and run until an empty one
+    fn skip_macro(&mut self) {
+        let mut lines = self.rest.split_inclusive('\n');
+        let mut skipped = lines.next().map_or(0, str::len);
+
+        for line in lines {
+            skipped += line.len();
+
+            if line.trim_end_matches(['\r', '\n']).is_empty() {
+                break;
+            }
+        }
+
+        self.rest = &self.rest[skipped..];
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            self.rest = self.rest.trim_start();
+
+            if !self.rest.starts_with('#') {
+                break;
+            }
+
+            self.rest = match self.rest.find('\n') {
+                Some(end) => &self.rest[end..],
+                None => "",
+            };
+        }
+
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        if self.rest.starts_with('"') {
+            let mut token = String::new();
+            let mut chars = self.rest[1..].char_indices();
+
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        self.rest = &self.rest[i + 2..];
+                        return Some(token);
+                    },
+                    '\\' => match chars.next() {
+                        Some((_, 'n')) => token.push('\n'),
+                        Some((_, 'r')) => token.push('\r'),
+                        Some((_, 't')) => token.push('\t'),
+                        Some((_, c)) => token.push(c),
+                        None => {},
+                    },
+                    c => token.push(c),
+                }
+            }
+
+            self.rest = "";
+            return Some(token);
+        }
+
+        let end = self.rest.find(char::is_whitespace).unwrap_or(self.rest.len());
+        let (token, rest) = self.rest.split_at(end);
+        self.rest = rest;
+
+        Some(token.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::testing::TempDir;
+    use super::*;
+
+    fn find(contents: &str, host: &str, login: Option<&str>) -> Option<(Option<String>, Option<String>)> {
+        let dir = TempDir::new();
+        let path = dir.file("netrc");
+        fs::write(&path, contents).unwrap();
+
+        lookup(Path::new(&path), host, login).unwrap().map(|entry| (entry.login, entry.password))
+    }
+
+    fn credentials(login: &str, password: &str) -> Option<(Option<String>, Option<String>)> {
+        Some((Some(login.to_owned()), Some(password.to_owned())))
+    }
+
+    #[test]
+    fn finds_the_machine_or_the_default() {
+        let netrc = "machine example.com login alice password one\n\
+                     machine example.com login bob password two\n\
+                     default login anonymous password guest\n";
+
+        assert_eq!(find(netrc, "EXAMPLE.com", None), credentials("alice", "one"));
+        assert_eq!(find(netrc, "example.com", Some("bob")), credentials("bob", "two"));
+        assert_eq!(find(netrc, "other.com", None), credentials("anonymous", "guest"));
+        assert_eq!(find(netrc, "example.com", Some("carol")), None);
+        assert_eq!(find("machine example.com login alice", "other.com", None), None);
+    }
+
+    #[test]
+    fn reads_quoted_tokens() {
+        let netrc = r#"machine example.com login "a b" password "q\"uote\\d\n""#;
+        assert_eq!(find(netrc, "example.com", None), credentials("a b", "q\"uote\\d\n"));
+    }
+
+    #[test]
+    fn skips_macros_and_unknown_tokens() {
+        let netrc = "macdef init\r\nmachine evil.com login mallory password x\r\n\r\n\
+                     # a comment\r\n\
+                     machine example.com account acct port 21 login alice password one\r\n";
+
+        assert_eq!(find(netrc, "example.com", None), credentials("alice", "one"));
+        assert_eq!(find(netrc, "evil.com", None), None);
+
+        let netrc = "machine example.com login alice\nmacdef init\n  cd /pub\n\npassword one\n";
+        assert_eq!(find(netrc, "example.com", None), credentials("alice", "one"));
+    }
+
+    #[test]
+    fn fails_on_missing_values() {
+        assert!(parse("machine").is_err());
+        assert!(parse("machine example.com login").is_err());
+        assert!(parse("login alice").is_err());
+    }
+}
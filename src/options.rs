@@ -3,36 +3,136 @@ use std::str::Utf8Error;
 use std::time::Duration;
 use std::mem::transmute;
 use std::ptr::null_mut;
+use std::slice;
 use libc::*;
-use reqwest::Method;
+use hyper::Method;
 use crate::CURL;
 use crate::util::borrow_raw::*;
 use crate::raw::{
     stdout,
+    stderr,
+    CURL_REDIR_GET_ALL,
+    CURL_IPRESOLVE_WHATEVER,
+    CURL_IPRESOLVE_V6,
+    CURL_REDIR_POST_ALL,
+    CURLALTSVC_H1,
+    CURLALTSVC_H2,
+    CURLALTSVC_H3,
+    CURLSSLOPT_ALLOW_BEAST,
+    CURLSSLOPT_NO_REVOKE,
     CURLoption::{Type as CURLoption, *},
     CURLcode::{Type as CURLcode, *},
+    CURL_NETRC_OPTION::{self, *},
+    _bindgen_ty_4::*,
+    curl_TimeCond::{self, *},
     curl_off_t,
 };
 use crate::rawx::*;
 use crate::error::RootRcErrorBuffer;
+use crate::{hsts, protocols, resolve, sigv4, socket, timeout, tls, trace};
+use crate::connect::UnixSocket;
+use crate::slist::curl_slist;
 
-const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(300);
+const DEFAULT_MAX_REDIRECTS: u32 = 30;
+const DEFAULT_BUFFER_SIZE: usize = 16 * 1024;
+const MIN_BUFFER_SIZE: usize = 1024;
+const MAX_BUFFER_SIZE: usize = 10 * 1024 * 1024;
+const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60);
 
 pub struct Options {
     pub url: Option<String>,
     pub follow_location: bool,
+    // `None` for no limit
+    pub max_redirects: Option<u32>,
+    pub post_redirect: c_long,
+    pub redirect_protocols: c_long,
     pub post_fields: Option<Vec<u8>>,
+    pub range: Option<String>,
+    pub time_condition: curl_TimeCond::Type,
+    pub time_value: i64,
+    pub accept_encoding: Option<String>,
+    pub content_decoding: bool,
+    pub resume_from: u64,
     pub method: Method,
     pub error_buffer: RootRcErrorBuffer,
-    pub connect_timeout: Option<Duration>,
+    pub connect_timeout: Duration,
+    pub happy_eyeballs_timeout: Duration,
+    pub resolve: Vec<String>,
+    pub connect_to: Vec<String>,
+    // `None` caches forever
+    pub dns_cache_timeout: Option<Duration>,
+    pub dns_shuffle_addresses: bool,
+    pub ip_resolve: c_long,
+    pub unix_socket: Option<UnixSocket>,
+    pub interface: Option<String>,
+    pub local_port: u16,
+    pub local_port_range: u16,
+    pub tcp_nodelay: bool,
+    pub tcp_keepalive: bool,
+    pub tcp_keepidle: Duration,
+    pub tcp_keepintvl: Duration,
+    pub buffer_size: usize,
+    // 2 additionally performs the WebSocket upgrade of ws:// and wss:// URLs
+    pub connect_only: c_long,
+    pub ws_options: c_long,
+    pub http_version: c_long,
+    pub alt_svc_file: Option<String>,
+    // Zero while the Alt-Svc engine is off
+    pub alt_svc_ctrl: c_long,
+    pub hsts_file: Option<String>,
+    // Without CURLHSTS_ENABLE the HSTS engine is off
+    pub hsts_ctrl: c_long,
+    pub hsts_read_function: Option<hsts::ReadFunction>,
+    pub hsts_read_data: *mut c_void,
+    pub hsts_write_function: Option<hsts::WriteFunction>,
+    pub hsts_write_data: *mut c_void,
+    pub open_socket_function: Option<socket::OpenSocketFunction>,
+    pub open_socket_data: *mut c_void,
+    pub close_socket_function: Option<socket::CloseSocketFunction>,
+    pub close_socket_data: *mut c_void,
+    pub sockopt_function: Option<socket::SockoptFunction>,
+    pub sockopt_data: *mut c_void,
+    pub prereq_function: Option<socket::PrereqFunction>,
+    pub prereq_data: *mut c_void,
     pub file_time: bool,
     pub no_progress: bool,
     pub write_function: WriteFunction,
     pub write_data: *mut c_void,
     pub header_function: Option<WriteFunction>,
+    pub verbose: bool,
+    pub debug_function: Option<trace::DebugFunction>,
+    pub debug_data: *mut c_void,
+    pub stderr: *mut FILE,
     pub header_data: *mut c_void,
     pub xfer_info_function: XferInfoFunction,
     pub xfer_info_data: *mut c_void,
+    pub ca_info: Option<tls::Source>,
+    pub ca_path: Option<String>,
+    pub ssl_cert: Option<tls::Source>,
+    pub ssl_cert_type: Option<String>,
+    pub ssl_key: Option<tls::Source>,
+    pub ssl_key_type: Option<String>,
+    pub key_passwd: Option<String>,
+    pub pinned_public_key: Option<String>,
+    pub ssl_version_min: Option<tls::TlsVersion>,
+    pub ssl_version_max: Option<tls::TlsVersion>,
+    pub ssl_cipher_list: Option<String>,
+    pub tls13_ciphers: Option<String>,
+    pub ssl_enable_alpn: bool,
+    pub ssl_options: c_long,
+    pub cert_info: bool,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub bearer: Option<String>,
+    pub http_auth: c_ulong,
+    pub unrestricted_auth: bool,
+    pub aws_sigv4: Option<sigv4::Spec>,
+    pub netrc: CURL_NETRC_OPTION::Type,
+    pub netrc_file: Option<String>,
+    pub timeout: Option<Duration>,
+    pub low_speed_limit: u64,
+    pub low_speed_time: Duration,
+    pub server_response_timeout: Option<Duration>,
 }
 
 impl Options {
@@ -40,18 +140,92 @@ impl Options {
         Self {
             url: None,
             follow_location: false,
+            max_redirects: Some(DEFAULT_MAX_REDIRECTS),
+            post_redirect: CURL_REDIR_GET_ALL as c_long,
+            redirect_protocols: protocols::DEFAULT_REDIRECT,
             post_fields: None,
+            range: None,
+            time_condition: CURL_TIMECOND_NONE,
+            time_value: 0,
+            accept_encoding: None,
+            content_decoding: true,
+            resume_from: 0,
             method: Method::GET,
             error_buffer: <_>::default(),
-            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            connect_timeout: timeout::DEFAULT_CONNECT_TIMEOUT,
+            happy_eyeballs_timeout: timeout::DEFAULT_HAPPY_EYEBALLS_TIMEOUT,
+            resolve: Vec::new(),
+            connect_to: Vec::new(),
+            dns_cache_timeout: Some(resolve::DEFAULT_CACHE_TIMEOUT),
+            dns_shuffle_addresses: false,
+            ip_resolve: CURL_IPRESOLVE_WHATEVER as c_long,
+            unix_socket: None,
+            interface: None,
+            local_port: 0,
+            local_port_range: 1,
+            tcp_nodelay: true,
+            tcp_keepalive: false,
+            tcp_keepidle: DEFAULT_KEEPALIVE_INTERVAL,
+            tcp_keepintvl: DEFAULT_KEEPALIVE_INTERVAL,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            connect_only: 0,
+            ws_options: 0,
+            http_version: CURL_HTTP_VERSION_NONE as c_long,
+            alt_svc_file: None,
+            alt_svc_ctrl: 0,
+            hsts_file: None,
+            hsts_ctrl: 0,
+            hsts_read_function: None,
+            hsts_read_data: null_mut(),
+            hsts_write_function: None,
+            hsts_write_data: null_mut(),
+            open_socket_function: None,
+            open_socket_data: null_mut(),
+            close_socket_function: None,
+            close_socket_data: null_mut(),
+            sockopt_function: None,
+            sockopt_data: null_mut(),
+            prereq_function: None,
+            prereq_data: null_mut(),
             file_time: false,
             no_progress: true,
             write_function: default_write_function,
             write_data: unsafe { stdout as *mut c_void },
             header_function: None,
+            verbose: false,
+            debug_function: None,
+            debug_data: null_mut(),
+            stderr: unsafe { stderr as *mut FILE },
             header_data: null_mut(),
             xfer_info_function: default_xfer_info_function,
             xfer_info_data: null_mut(),
+            ca_info: None,
+            ca_path: None,
+            ssl_cert: None,
+            ssl_cert_type: None,
+            ssl_key: None,
+            ssl_key_type: None,
+            key_passwd: None,
+            pinned_public_key: None,
+            ssl_version_min: None,
+            ssl_version_max: None,
+            ssl_cipher_list: None,
+            tls13_ciphers: None,
+            ssl_enable_alpn: true,
+            ssl_options: 0,
+            cert_info: false,
+            username: None,
+            password: None,
+            bearer: None,
+            http_auth: CURLAUTH_BASIC,
+            unrestricted_auth: false,
+            aws_sigv4: None,
+            netrc: CURL_NETRC_IGNORED,
+            netrc_file: None,
+            timeout: None,
+            low_speed_limit: 0,
+            low_speed_time: Duration::from_secs(0),
+            server_response_timeout: None,
         }
     }
 }
@@ -63,7 +237,7 @@ impl Default for Options {
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_easy_setopt(
+pub unsafe extern "C" fn curl_easy_setopt(
     this: *mut CURL,
     option: CURLoption,
     mut args: ...
@@ -80,12 +254,96 @@ pub unsafe extern fn curl_easy_setopt(
                 CURLE_OK
             }),
 
+            CURLOPT_MAXREDIRS => long_opt(args, |max| {
+                curl.options.max_redirects = match max {
+                    -1 => None,
+                    max if max >= 0 => Some(max as u32),
+                    _ => return CURLE_BAD_FUNCTION_ARGUMENT,
+                };
+                CURLE_OK
+            }),
+
+            CURLOPT_POSTREDIR => long_opt(args, |bitmask| {
+                curl.options.post_redirect = bitmask & CURL_REDIR_POST_ALL as c_long;
+                CURLE_OK
+            }),
+
+            CURLOPT_REDIR_PROTOCOLS => long_opt(args, |bitmask| {
+                curl.options.redirect_protocols = bitmask;
+                CURLE_OK
+            }),
+
+            CURLOPT_REDIR_PROTOCOLS_STR => str_opt(args, |list| match list {
+                Ok(None) => { curl.options.redirect_protocols = protocols::DEFAULT_REDIRECT; CURLE_OK },
+                Ok(Some(list)) => match protocols::parse(list) {
+                    Ok(bitmask) => { curl.options.redirect_protocols = bitmask; CURLE_OK },
+                    Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e),
+                },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
             CURLOPT_POSTFIELDS => bytes_opt(args, |fields| {
                 curl.options.method = Method::POST;
                 curl.options.post_fields = fields.map(<_>::to_owned);
                 CURLE_OK
             }),
 
+            CURLOPT_TIMECONDITION => long_opt(args, |condition| match condition as curl_TimeCond::Type {
+                condition @ CURL_TIMECOND_NONE ..= CURL_TIMECOND_LASTMOD => {
+                    curl.options.time_condition = condition;
+                    CURLE_OK
+                },
+                _ => CURLE_BAD_FUNCTION_ARGUMENT,
+            }),
+
+            CURLOPT_TIMEVALUE => long_opt(args, |time| {
+                curl.options.time_value = time;
+                CURLE_OK
+            }),
+
+            CURLOPT_TIMEVALUE_LARGE => off_t_opt(args, |time| {
+                curl.options.time_value = time;
+                CURLE_OK
+            }),
+
+            CURLOPT_ACCEPT_ENCODING => owned_str_opt(args, |encoding| match encoding {
+                Ok(encoding) => { curl.options.accept_encoding = encoding; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_HTTP_CONTENT_DECODING => bool_opt(args, |state| {
+                curl.options.content_decoding = state;
+                CURLE_OK
+            }),
+
+            // hyper always undoes chunked transfer encoding
+            CURLOPT_HTTP_TRANSFER_DECODING => bool_opt(args, |state| match state {
+                true => CURLE_OK,
+                false => CURLE_NOT_BUILT_IN,
+            }),
+
+            CURLOPT_RANGE => owned_str_opt(args, |range| match range {
+                Ok(range) => { curl.options.range = range; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            // TODO: Resume uploads once they are supported
+            CURLOPT_RESUME_FROM => long_opt(args, |offset| {
+                if offset < 0 {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.resume_from = offset as u64;
+                CURLE_OK
+            }),
+
+            CURLOPT_RESUME_FROM_LARGE => off_t_opt(args, |offset| {
+                if offset < 0 {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.resume_from = offset as u64;
+                CURLE_OK
+            }),
+
             CURLOPT_ERRORBUFFER => {
                 let buffer = args.arg::<*mut c_char>();
                 curl.options.error_buffer
@@ -94,22 +352,291 @@ pub unsafe extern fn curl_easy_setopt(
                 CURLE_OK
             },
 
-            CURLOPT_CONNECTTIMEOUT => long_opt(args, |timeout| {
-                curl.options.connect_timeout = Some(match timeout {
-                    0 => DEFAULT_CONNECT_TIMEOUT,
-                    _ => Duration::from_secs(timeout as u64),
-                });
+            CURLOPT_CONNECTTIMEOUT => timeout_opt(args, Duration::from_secs, |timeout| {
+                curl.options.connect_timeout = timeout.unwrap_or(timeout::DEFAULT_CONNECT_TIMEOUT);
+            }),
+
+            CURLOPT_CONNECTTIMEOUT_MS => timeout_opt(args, Duration::from_millis, |timeout| {
+                curl.options.connect_timeout = timeout.unwrap_or(timeout::DEFAULT_CONNECT_TIMEOUT);
+            }),
+
+            CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS => timeout_opt(args, Duration::from_millis, |timeout| {
+                curl.options.happy_eyeballs_timeout = timeout.unwrap_or(timeout::DEFAULT_HAPPY_EYEBALLS_TIMEOUT);
+            }),
+
+            CURLOPT_RESOLVE => slist_opt(args, |entries| {
+                curl.options.resolve = entries;
                 CURLE_OK
             }),
 
-            CURLOPT_CONNECTTIMEOUT_MS => long_opt(args, |timeout| {
-                curl.options.connect_timeout = match timeout {
-                    0 => None,
-                    _ => Some(Duration::from_millis(timeout as u64)),
+            CURLOPT_CONNECT_TO => slist_opt(args, |entries| {
+                curl.options.connect_to = entries;
+                CURLE_OK
+            }),
+
+            CURLOPT_DNS_CACHE_TIMEOUT => long_opt(args, |timeout| {
+                curl.options.dns_cache_timeout = match timeout {
+                    -1 => None,
+                    timeout if timeout >= 0 => Some(Duration::from_secs(timeout as u64)),
+                    _ => return CURLE_BAD_FUNCTION_ARGUMENT,
                 };
                 CURLE_OK
             }),
 
+            CURLOPT_DNS_SHUFFLE_ADDRESSES => bool_opt(args, |state| {
+                curl.options.dns_shuffle_addresses = state;
+                CURLE_OK
+            }),
+
+            CURLOPT_IPRESOLVE => long_opt(args, |ip_resolve| {
+                if ip_resolve < 0 || ip_resolve > CURL_IPRESOLVE_V6 as c_long {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.ip_resolve = ip_resolve;
+                CURLE_OK
+            }),
+
+            CURLOPT_UNIX_SOCKET_PATH => owned_str_opt(args, |path| match path {
+                Ok(path) => { curl.options.unix_socket = path.map(UnixSocket::Path); CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_ABSTRACT_UNIX_SOCKET => owned_str_opt(args, |name| match name {
+                Ok(name) => { curl.options.unix_socket = name.map(UnixSocket::Abstract); CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_INTERFACE => owned_str_opt(args, |interface| match interface {
+                Ok(interface) => { curl.options.interface = interface; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_LOCALPORT => long_opt(args, |port| {
+                if port < 0 || port > u16::MAX as c_long {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.local_port = port as u16;
+                CURLE_OK
+            }),
+
+            CURLOPT_LOCALPORTRANGE => long_opt(args, |range| {
+                if range < 0 || range > u16::MAX as c_long {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.local_port_range = (range as u16).max(1);
+                CURLE_OK
+            }),
+
+            CURLOPT_TCP_NODELAY => bool_opt(args, |state| {
+                curl.options.tcp_nodelay = state;
+                CURLE_OK
+            }),
+
+            CURLOPT_TCP_KEEPALIVE => bool_opt(args, |state| {
+                curl.options.tcp_keepalive = state;
+                CURLE_OK
+            }),
+
+            CURLOPT_TCP_KEEPIDLE => long_opt(args, |secs| {
+                if secs <= 0 {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.tcp_keepidle = Duration::from_secs(secs as u64);
+                CURLE_OK
+            }),
+
+            CURLOPT_TCP_KEEPINTVL => long_opt(args, |secs| {
+                if secs <= 0 {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.tcp_keepintvl = Duration::from_secs(secs as u64);
+                CURLE_OK
+            }),
+
+            // Clamped like libcurl does instead of being rejected
+            CURLOPT_BUFFERSIZE => long_opt(args, |size| {
+                curl.options.buffer_size = match size {
+                    size if size < 1 => DEFAULT_BUFFER_SIZE,
+                    size => (size as usize).clamp(MIN_BUFFER_SIZE, MAX_BUFFER_SIZE),
+                };
+                CURLE_OK
+            }),
+
+            // Uploads aren't supported yet, so there is no buffer to size
+            CURLOPT_UPLOAD_BUFFERSIZE => long_opt(args, |_| CURLE_OK),
+
+            CURLOPT_CONNECT_ONLY => long_opt(args, |connect_only| {
+                if !(0..=2).contains(&connect_only) {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.connect_only = connect_only;
+                CURLE_OK
+            }),
+
+            CURLOPT_HTTP_VERSION => long_opt(args, |version| {
+                match version as u32 {
+                    CURL_HTTP_VERSION_NONE |
+                    CURL_HTTP_VERSION_1_0 |
+                    CURL_HTTP_VERSION_1_1 |
+                    CURL_HTTP_VERSION_2_0 |
+                    CURL_HTTP_VERSION_2TLS |
+                    CURL_HTTP_VERSION_2_PRIOR_KNOWLEDGE => {},
+                    // Including HTTP/3, like a libcurl built without QUIC
+                    _ => return CURLE_UNSUPPORTED_PROTOCOL,
+                }
+                curl.options.http_version = version;
+                CURLE_OK
+            }),
+
+            // Setting a file also turns the engine on, for every protocol
+            CURLOPT_ALTSVC => owned_str_opt(args, |path| match path {
+                Ok(path) => {
+                    if curl.options.alt_svc_ctrl == 0 {
+                        curl.options.alt_svc_ctrl = (CURLALTSVC_H1 | CURLALTSVC_H2 | CURLALTSVC_H3) as c_long;
+                    }
+                    curl.options.alt_svc_file = path;
+                    CURLE_OK
+                },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_ALTSVC_CTRL => long_opt(args, |ctrl| {
+                if ctrl == 0 {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.alt_svc_ctrl = ctrl;
+                CURLE_OK
+            }),
+
+            // Like the Alt-Svc file, setting one turns the engine on
+            CURLOPT_HSTS => owned_str_opt(args, |path| match path {
+                Ok(path) => {
+                    curl.options.hsts_ctrl |= CURLHSTS_ENABLE;
+                    curl.options.hsts_file = path;
+                    CURLE_OK
+                },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_HSTS_CTRL => long_opt(args, |ctrl| {
+                curl.options.hsts_ctrl = ctrl & (CURLHSTS_ENABLE | CURLHSTS_READONLYFILE);
+                CURLE_OK
+            }),
+
+            CURLOPT_HSTSREADFUNCTION => {
+                let ptr = args.arg::<*const c_void>();
+                let hsts_read_function = transmute::<*const c_void, hsts::ReadFunction>(ptr);
+                curl.options.hsts_read_function = (!ptr.is_null()).then_some(hsts_read_function);
+                CURLE_OK
+            }
+
+            CURLOPT_HSTSREADDATA => {
+                curl.options.hsts_read_data = args.arg::<*mut c_void>();
+                CURLE_OK
+            }
+
+            CURLOPT_HSTSWRITEFUNCTION => {
+                let ptr = args.arg::<*const c_void>();
+                let hsts_write_function = transmute::<*const c_void, hsts::WriteFunction>(ptr);
+                curl.options.hsts_write_function = (!ptr.is_null()).then_some(hsts_write_function);
+                CURLE_OK
+            }
+
+            CURLOPT_HSTSWRITEDATA => {
+                curl.options.hsts_write_data = args.arg::<*mut c_void>();
+                CURLE_OK
+            }
+
+            CURLOPT_WS_OPTIONS => long_opt(args, |bitmask| {
+                curl.options.ws_options = bitmask & CURLWS_RAW_MODE;
+                CURLE_OK
+            }),
+
+            CURLOPT_OPENSOCKETFUNCTION => {
+                let ptr = args.arg::<*const c_void>();
+                let open_socket_function = transmute::<*const c_void, socket::OpenSocketFunction>(ptr);
+                curl.options.open_socket_function = (!ptr.is_null()).then_some(open_socket_function);
+                CURLE_OK
+            }
+
+            CURLOPT_OPENSOCKETDATA => {
+                curl.options.open_socket_data = args.arg::<*mut c_void>();
+                CURLE_OK
+            }
+
+            CURLOPT_CLOSESOCKETFUNCTION => {
+                let ptr = args.arg::<*const c_void>();
+                let close_socket_function = transmute::<*const c_void, socket::CloseSocketFunction>(ptr);
+                curl.options.close_socket_function = (!ptr.is_null()).then_some(close_socket_function);
+                CURLE_OK
+            }
+
+            CURLOPT_CLOSESOCKETDATA => {
+                curl.options.close_socket_data = args.arg::<*mut c_void>();
+                CURLE_OK
+            }
+
+            CURLOPT_SOCKOPTFUNCTION => {
+                let ptr = args.arg::<*const c_void>();
+                let sockopt_function = transmute::<*const c_void, socket::SockoptFunction>(ptr);
+                curl.options.sockopt_function = (!ptr.is_null()).then_some(sockopt_function);
+                CURLE_OK
+            }
+
+            CURLOPT_SOCKOPTDATA => {
+                curl.options.sockopt_data = args.arg::<*mut c_void>();
+                CURLE_OK
+            }
+
+            CURLOPT_PREREQFUNCTION => {
+                let ptr = args.arg::<*const c_void>();
+                let prereq_function = transmute::<*const c_void, socket::PrereqFunction>(ptr);
+                curl.options.prereq_function = (!ptr.is_null()).then_some(prereq_function);
+                CURLE_OK
+            }
+
+            CURLOPT_PREREQDATA => {
+                curl.options.prereq_data = args.arg::<*mut c_void>();
+                CURLE_OK
+            }
+
+            // Only used by FTP active mode, which isn't supported
+            CURLOPT_ACCEPTTIMEOUT_MS => long_opt(args, |_| curl.error(CURLE_NOT_BUILT_IN, "FTP is not supported")),
+
+            CURLOPT_TIMEOUT => timeout_opt(args, Duration::from_secs, |timeout| {
+                curl.options.timeout = timeout;
+            }),
+
+            CURLOPT_TIMEOUT_MS => timeout_opt(args, Duration::from_millis, |timeout| {
+                curl.options.timeout = timeout;
+            }),
+
+            CURLOPT_LOW_SPEED_LIMIT => long_opt(args, |limit| {
+                if limit < 0 {
+                    return CURLE_BAD_FUNCTION_ARGUMENT;
+                }
+                curl.options.low_speed_limit = limit as u64;
+                CURLE_OK
+            }),
+
+            CURLOPT_LOW_SPEED_TIME => timeout_opt(args, Duration::from_secs, |time| {
+                curl.options.low_speed_time = time.unwrap_or_default();
+            }),
+
+            // Stored, but like in libcurl it only applies to FTP, SFTP, IMAP, POP3 and SMTP, none of which are supported
+            CURLOPT_SERVER_RESPONSE_TIMEOUT => timeout_opt(args, Duration::from_secs, |timeout| {
+                curl.options.server_response_timeout = timeout;
+            }),
+
+            CURLOPT_SERVER_RESPONSE_TIMEOUT_MS => timeout_opt(args, Duration::from_millis, |timeout| {
+                curl.options.server_response_timeout = timeout;
+            }),
+
+            // Accepted but without effect, as no signal is ever raised: names are resolved on a
+            // helper thread rather than under alarm(), std sends over TCP with MSG_NOSIGNAL
+            // and Unix sockets are written with it too (`connect::send_unix`)
+            CURLOPT_NOSIGNAL => bool_opt(args, |_| CURLE_OK),
+
             CURLOPT_FILETIME => bool_opt(args, |state| {
                 curl.options.file_time = state;
                 CURLE_OK
@@ -127,8 +654,8 @@ pub unsafe extern fn curl_easy_setopt(
 
             CURLOPT_HEADERFUNCTION => {
                 let ptr = args.arg::<*const c_void>();
-                let header_function = transmute::<_, WriteFunction>(ptr);
-                curl.options.header_function = Some(header_function).filter(|_| !ptr.is_null());
+                let header_function = transmute::<*const c_void, WriteFunction>(ptr);
+                curl.options.header_function = (!ptr.is_null()).then_some(header_function);
                 CURLE_OK
             }
 
@@ -137,9 +664,35 @@ pub unsafe extern fn curl_easy_setopt(
                 CURLE_OK
             }
 
+            CURLOPT_VERBOSE => bool_opt(args, |state| {
+                curl.options.verbose = state;
+                CURLE_OK
+            }),
+
+            CURLOPT_DEBUGFUNCTION => {
+                let ptr = args.arg::<*const c_void>();
+                let debug_function = transmute::<*const c_void, trace::DebugFunction>(ptr);
+                curl.options.debug_function = (!ptr.is_null()).then_some(debug_function);
+                CURLE_OK
+            }
+
+            CURLOPT_DEBUGDATA => {
+                curl.options.debug_data = args.arg::<*mut c_void>();
+                CURLE_OK
+            }
+
+            CURLOPT_STDERR => {
+                let file = args.arg::<*mut FILE>();
+                curl.options.stderr = match file.is_null() {
+                    true => stderr as *mut FILE,
+                    false => file,
+                };
+                CURLE_OK
+            }
+
             CURLOPT_XFERINFOFUNCTION => {
                 let ptr = args.arg::<*const c_void>();
-                let xfer_info_function = transmute::<_, XferInfoFunction>(ptr);
+                let xfer_info_function = transmute::<*const c_void, XferInfoFunction>(ptr);
                 curl.options.xfer_info_function = xfer_info_function;
                 CURLE_OK
             }
@@ -149,6 +702,177 @@ pub unsafe extern fn curl_easy_setopt(
                 CURLE_OK
             }
 
+            CURLOPT_CAINFO => owned_str_opt(args, |path| match path {
+                Ok(path) => { curl.options.ca_info = path.map(tls::Source::File); CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_CAINFO_BLOB => blob_opt(args, |blob| {
+                curl.options.ca_info = blob.map(tls::Source::Blob);
+                CURLE_OK
+            }),
+
+            CURLOPT_CAPATH => owned_str_opt(args, |path| match path {
+                Ok(path) => { curl.options.ca_path = path; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_SSLCERT => owned_str_opt(args, |path| match path {
+                Ok(path) => { curl.options.ssl_cert = path.map(tls::Source::File); CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_SSLCERT_BLOB => blob_opt(args, |blob| {
+                curl.options.ssl_cert = blob.map(tls::Source::Blob);
+                CURLE_OK
+            }),
+
+            CURLOPT_SSLCERTTYPE => owned_str_opt(args, |cert_type| match cert_type {
+                Ok(cert_type) => { curl.options.ssl_cert_type = cert_type; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_SSLKEY => owned_str_opt(args, |path| match path {
+                Ok(path) => { curl.options.ssl_key = path.map(tls::Source::File); CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_SSLKEY_BLOB => blob_opt(args, |blob| {
+                curl.options.ssl_key = blob.map(tls::Source::Blob);
+                CURLE_OK
+            }),
+
+            CURLOPT_SSLKEYTYPE => owned_str_opt(args, |key_type| match key_type {
+                Ok(key_type) => { curl.options.ssl_key_type = key_type; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_KEYPASSWD => owned_str_opt(args, |passwd| match passwd {
+                Ok(passwd) => { curl.options.key_passwd = passwd; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_PINNEDPUBLICKEY => owned_str_opt(args, |pin| match pin {
+                Ok(pin) => { curl.options.pinned_public_key = pin; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_SSLVERSION => long_opt(args, |version| match tls::ssl_version(version) {
+                Ok((min, max)) => {
+                    curl.options.ssl_version_min = min;
+                    curl.options.ssl_version_max = max;
+                    CURLE_OK
+                },
+                Err(e) => curl.error(e.code, e.message),
+            }),
+
+            // Checked by OpenSSL when the connection is set up, which fails with CURLE_SSL_CIPHER
+            CURLOPT_SSL_CIPHER_LIST => owned_str_opt(args, |ciphers| match ciphers {
+                Ok(ciphers) => { curl.options.ssl_cipher_list = ciphers; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_TLS13_CIPHERS => owned_str_opt(args, |ciphers| match ciphers {
+                Ok(ciphers) => { curl.options.tls13_ciphers = ciphers; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_SSL_ENABLE_ALPN => bool_opt(args, |state| {
+                curl.options.ssl_enable_alpn = state;
+                CURLE_OK
+            }),
+
+            // Revocation is never checked, so CURLSSLOPT_NO_REVOKE has nothing to turn off
+            CURLOPT_SSL_OPTIONS => long_opt(args, |ssl_options| {
+                let supported = CURLSSLOPT_ALLOW_BEAST as c_long
+                    | CURLSSLOPT_NO_REVOKE as c_long
+                    | CURLSSLOPT_NO_PARTIALCHAIN
+                    | CURLSSLOPT_NATIVE_CA;
+
+                match ssl_options & !supported {
+                    0 => { curl.options.ssl_options = ssl_options; CURLE_OK },
+                    _ => curl.error(CURLE_NOT_BUILT_IN, "only CURLSSLOPT_ALLOW_BEAST, CURLSSLOPT_NO_REVOKE, CURLSSLOPT_NO_PARTIALCHAIN and CURLSSLOPT_NATIVE_CA are supported"),
+                }
+            }),
+
+            CURLOPT_CERTINFO => bool_opt(args, |state| {
+                curl.options.cert_info = state;
+                CURLE_OK
+            }),
+
+            CURLOPT_USERPWD => str_opt(args, |userpwd| match userpwd {
+                Ok(None) => {
+                    curl.options.username = None;
+                    curl.options.password = None;
+                    CURLE_OK
+                },
+                Ok(Some(userpwd)) => {
+                    let mut userpwd = userpwd.splitn(2, ':');
+                    curl.options.username = userpwd.next().map(str::to_owned);
+                    curl.options.password = userpwd.next().map(str::to_owned);
+                    CURLE_OK
+                },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_USERNAME => owned_str_opt(args, |username| match username {
+                Ok(username) => { curl.options.username = username; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_PASSWORD => owned_str_opt(args, |password| match password {
+                Ok(password) => { curl.options.password = password; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_XOAUTH2_BEARER => owned_str_opt(args, |bearer| match bearer {
+                Ok(bearer) => { curl.options.bearer = bearer; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_HTTPAUTH => long_opt(args, |http_auth| {
+                let http_auth = http_auth as c_ulong;
+                let supported = CURLAUTH_BASIC | CURLAUTH_DIGEST | CURLAUTH_BEARER | CURLAUTH_AWS_SIGV4;
+
+                if http_auth & !CURLAUTH_ONLY & supported == 0 {
+                    return curl.error(CURLE_NOT_BUILT_IN, "none of the requested auth methods are supported");
+                }
+
+                curl.options.http_auth = http_auth;
+                CURLE_OK
+            }),
+
+            CURLOPT_AWS_SIGV4 => str_opt(args, |spec| match spec {
+                Ok(None) => { curl.options.aws_sigv4 = None; CURLE_OK },
+                Ok(Some(spec)) => match sigv4::Spec::parse(spec) {
+                    Ok(spec) => {
+                        curl.options.aws_sigv4 = Some(spec);
+                        curl.options.http_auth = CURLAUTH_AWS_SIGV4;
+                        CURLE_OK
+                    },
+                    Err(e) => curl.error(e.code, e.message),
+                },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_NETRC => long_opt(args, |netrc| match netrc as CURL_NETRC_OPTION::Type {
+                netrc @ CURL_NETRC_IGNORED ..= CURL_NETRC_REQUIRED => {
+                    curl.options.netrc = netrc;
+                    CURLE_OK
+                },
+                _ => CURLE_BAD_FUNCTION_ARGUMENT,
+            }),
+
+            CURLOPT_NETRC_FILE => owned_str_opt(args, |path| match path {
+                Ok(path) => { curl.options.netrc_file = path; CURLE_OK },
+                Err(e) => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            }),
+
+            CURLOPT_UNRESTRICTED_AUTH => bool_opt(args, |state| {
+                curl.options.unrestricted_auth = state;
+                CURLE_OK
+            }),
+
             _ => {
                 if cfg!(debug_assertions) {
                     eprintln!("recurl: unknown option ({})", option);
@@ -198,6 +922,39 @@ where
     f(Some(bytes))
 }
 
+// The blob is always copied, so CURL_BLOB_NOCOPY is treated like CURL_BLOB_COPY
+unsafe fn blob_opt<F, R>(mut args: VaList, f: F) -> R
+where
+    F: FnOnce(Option<Vec<u8>>) -> R
+{
+    let blob = args.arg::<*const curl_blob>();
+
+    let blob = match blob.as_ref() {
+        Some(blob) => blob,
+        None => return f(None),
+    };
+
+    if blob.data.is_null() {
+        return f(Some(Vec::new()));
+    }
+
+    let bytes = slice::from_raw_parts(blob.data as *const u8, blob.len);
+    f(Some(bytes.to_owned()))
+}
+
+// The list is copied, so later changes by the caller don't apply
+unsafe fn slist_opt<F, R>(mut args: VaList, f: F) -> R
+where
+    F: FnOnce(Vec<String>) -> R
+{
+    let list = args.arg::<*const curl_slist>();
+
+    match list.as_ref() {
+        Some(list) => f(list.elements().to_vec()),
+        None => f(Vec::new()),
+    }
+}
+
 unsafe fn long_opt<F, R>(mut args: VaList, f: F) -> R
 where
     F: FnOnce(c_long) -> R
@@ -206,6 +963,29 @@ where
     f(value)
 }
 
+unsafe fn off_t_opt<F, R>(mut args: VaList, f: F) -> R
+where
+    F: FnOnce(curl_off_t) -> R
+{
+    let value = args.arg::<curl_off_t>();
+    f(value)
+}
+
+// Timeout options count in `unit`s, where 0 selects the default (`None`)
+unsafe fn timeout_opt<F>(args: VaList, unit: fn(u64) -> Duration, f: F) -> CURLcode
+where
+    F: FnOnce(Option<Duration>)
+{
+    long_opt(args, |value| {
+        match value {
+            0 => f(None),
+            value if value > 0 => f(Some(unit(value as u64))),
+            _ => return CURLE_BAD_FUNCTION_ARGUMENT,
+        }
+        CURLE_OK
+    })
+}
+
 unsafe fn bool_opt<F, R>(args: VaList, f: F) -> R
 where
     F: FnOnce(bool) -> R
@@ -214,14 +994,14 @@ where
 }
 
 
-type WriteFunction = unsafe extern fn(
+type WriteFunction = unsafe extern "C" fn(
     ptr: *const c_char,
     size: size_t,
     nitems: size_t,
     userdata: *mut c_void,
 ) -> size_t;
 
-unsafe extern fn default_write_function(
+unsafe extern "C" fn default_write_function(
     ptr: *const c_char,
     size: size_t,
     nmemb: size_t,
@@ -230,7 +1010,7 @@ unsafe extern fn default_write_function(
     fwrite(ptr as *const c_void,size, nmemb, userdata as *mut FILE)
 }
 
-type XferInfoFunction = unsafe extern fn(
+type XferInfoFunction = unsafe extern "C" fn(
     userdata: *mut c_void,
     dl_total: curl_off_t,
     dl_now: curl_off_t,
@@ -238,7 +1018,7 @@ type XferInfoFunction = unsafe extern fn(
     ul_now: curl_off_t,
 ) -> c_int;
 
-unsafe extern fn default_xfer_info_function(
+unsafe extern "C" fn default_xfer_info_function(
     _userdata: *mut c_void,
     dl_total: curl_off_t,
     dl_now: curl_off_t,
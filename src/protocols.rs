@@ -0,0 +1,59 @@
+use libc::*;
+use crate::raw::*;
+use crate::rawx::{CURLPROTO_WS, CURLPROTO_WSS};
+
+const PROTOCOLS: &[(&str, u32)] = &[
+    ("http", CURLPROTO_HTTP),
+    ("https", CURLPROTO_HTTPS),
+    ("ftp", CURLPROTO_FTP),
+    ("ftps", CURLPROTO_FTPS),
+    ("scp", CURLPROTO_SCP),
+    ("sftp", CURLPROTO_SFTP),
+    ("telnet", CURLPROTO_TELNET),
+    ("ldap", CURLPROTO_LDAP),
+    ("ldaps", CURLPROTO_LDAPS),
+    ("dict", CURLPROTO_DICT),
+    ("file", CURLPROTO_FILE),
+    ("tftp", CURLPROTO_TFTP),
+    ("imap", CURLPROTO_IMAP),
+    ("imaps", CURLPROTO_IMAPS),
+    ("pop3", CURLPROTO_POP3),
+    ("pop3s", CURLPROTO_POP3S),
+    ("smtp", CURLPROTO_SMTP),
+    ("smtps", CURLPROTO_SMTPS),
+    ("rtsp", CURLPROTO_RTSP),
+    ("rtmp", CURLPROTO_RTMP),
+    ("rtmpt", CURLPROTO_RTMPT),
+    ("rtmpe", CURLPROTO_RTMPE),
+    ("rtmpte", CURLPROTO_RTMPTE),
+    ("rtmps", CURLPROTO_RTMPS),
+    ("rtmpts", CURLPROTO_RTMPTS),
+    ("gopher", CURLPROTO_GOPHER),
+    ("smb", CURLPROTO_SMB),
+    ("smbs", CURLPROTO_SMBS),
+    ("ws", CURLPROTO_WS),
+    ("wss", CURLPROTO_WSS),
+];
+
+/// The protocols libcurl follows redirects to by default.
+pub const DEFAULT_REDIRECT: c_long = (CURLPROTO_HTTP | CURLPROTO_HTTPS | CURLPROTO_FTP | CURLPROTO_FTPS) as c_long;
+
+/// The `CURLPROTO_*` bit of a URL scheme.
+pub fn bit(scheme: &str) -> Option<c_long> {
+    PROTOCOLS.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(scheme))
+        .map(|&(_, bit)| bit as c_long)
+}
+
+/// Parses a `CURLOPT_*PROTOCOLS_STR` list like "http,https" into `CURLPROTO_*` bits.
+pub fn parse(list: &str) -> Result<c_long, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .try_fold(0, |bits, name| match name {
+            _ if name.eq_ignore_ascii_case("all") => Ok(CURLPROTO_ALL as c_long),
+            _ => bit(name)
+                .map(|bit| bits | bit)
+                .ok_or_else(|| format!("unknown protocol '{}'", name)),
+        })
+}
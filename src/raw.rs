@@ -696,7 +696,7 @@ fn bindgen_test_layout___fsid_t() {
         concat!("Alignment of ", stringify!(__fsid_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__fsid_t>())).__val as *const _ as usize },
+        ::std::mem::offset_of!(__fsid_t, __val),
         0usize,
         concat!(
             "Offset of field: ",
@@ -783,7 +783,7 @@ fn bindgen_test_layout___sigset_t() {
         concat!("Alignment of ", stringify!(__sigset_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__sigset_t>())).__val as *const _ as usize },
+        ::std::mem::offset_of!(__sigset_t, __val),
         0usize,
         concat!(
             "Offset of field: ",
@@ -813,7 +813,7 @@ fn bindgen_test_layout_timeval() {
         concat!("Alignment of ", stringify!(timeval))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<timeval>())).tv_sec as *const _ as usize },
+        ::std::mem::offset_of!(timeval, tv_sec),
         0usize,
         concat!(
             "Offset of field: ",
@@ -823,7 +823,7 @@ fn bindgen_test_layout_timeval() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<timeval>())).tv_usec as *const _ as usize },
+        ::std::mem::offset_of!(timeval, tv_usec),
         8usize,
         concat!(
             "Offset of field: ",
@@ -852,7 +852,7 @@ fn bindgen_test_layout_timespec() {
         concat!("Alignment of ", stringify!(timespec))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<timespec>())).tv_sec as *const _ as usize },
+        ::std::mem::offset_of!(timespec, tv_sec),
         0usize,
         concat!(
             "Offset of field: ",
@@ -862,7 +862,7 @@ fn bindgen_test_layout_timespec() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<timespec>())).tv_nsec as *const _ as usize },
+        ::std::mem::offset_of!(timespec, tv_nsec),
         8usize,
         concat!(
             "Offset of field: ",
@@ -892,7 +892,7 @@ fn bindgen_test_layout_fd_set() {
         concat!("Alignment of ", stringify!(fd_set))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<fd_set>())).__fds_bits as *const _ as usize },
+        ::std::mem::offset_of!(fd_set, __fds_bits),
         0usize,
         concat!(
             "Offset of field: ",
@@ -955,9 +955,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         concat!("Alignment of ", stringify!(__pthread_rwlock_arch_t))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__readers as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __readers),
         0usize,
         concat!(
             "Offset of field: ",
@@ -967,9 +965,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__writers as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __writers),
         4usize,
         concat!(
             "Offset of field: ",
@@ -979,9 +975,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__wrphase_futex as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __wrphase_futex),
         8usize,
         concat!(
             "Offset of field: ",
@@ -991,9 +985,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__writers_futex as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __writers_futex),
         12usize,
         concat!(
             "Offset of field: ",
@@ -1003,7 +995,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__pad3 as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __pad3),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1013,7 +1005,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__pad4 as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __pad4),
         20usize,
         concat!(
             "Offset of field: ",
@@ -1023,9 +1015,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__cur_writer as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __cur_writer),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1035,9 +1025,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__shared as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __shared),
         28usize,
         concat!(
             "Offset of field: ",
@@ -1047,9 +1035,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__rwelision as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __rwelision),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1059,7 +1045,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__pad1 as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __pad1),
         33usize,
         concat!(
             "Offset of field: ",
@@ -1069,7 +1055,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__pad2 as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __pad2),
         40usize,
         concat!(
             "Offset of field: ",
@@ -1079,7 +1065,7 @@ fn bindgen_test_layout___pthread_rwlock_arch_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_rwlock_arch_t>())).__flags as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_rwlock_arch_t, __flags),
         48usize,
         concat!(
             "Offset of field: ",
@@ -1108,7 +1094,7 @@ fn bindgen_test_layout___pthread_internal_list() {
         concat!("Alignment of ", stringify!(__pthread_internal_list))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_internal_list>())).__prev as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_internal_list, __prev),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1118,7 +1104,7 @@ fn bindgen_test_layout___pthread_internal_list() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_internal_list>())).__next as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_internal_list, __next),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1154,7 +1140,7 @@ fn bindgen_test_layout___pthread_mutex_s() {
         concat!("Alignment of ", stringify!(__pthread_mutex_s))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_mutex_s>())).__lock as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_mutex_s, __lock),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1164,7 +1150,7 @@ fn bindgen_test_layout___pthread_mutex_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_mutex_s>())).__count as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_mutex_s, __count),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1174,7 +1160,7 @@ fn bindgen_test_layout___pthread_mutex_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_mutex_s>())).__owner as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_mutex_s, __owner),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1184,7 +1170,7 @@ fn bindgen_test_layout___pthread_mutex_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_mutex_s>())).__nusers as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_mutex_s, __nusers),
         12usize,
         concat!(
             "Offset of field: ",
@@ -1194,7 +1180,7 @@ fn bindgen_test_layout___pthread_mutex_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_mutex_s>())).__kind as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_mutex_s, __kind),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1204,7 +1190,7 @@ fn bindgen_test_layout___pthread_mutex_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_mutex_s>())).__spins as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_mutex_s, __spins),
         20usize,
         concat!(
             "Offset of field: ",
@@ -1214,7 +1200,7 @@ fn bindgen_test_layout___pthread_mutex_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_mutex_s>())).__elision as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_mutex_s, __elision),
         22usize,
         concat!(
             "Offset of field: ",
@@ -1224,7 +1210,7 @@ fn bindgen_test_layout___pthread_mutex_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_mutex_s>())).__list as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_mutex_s, __list),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1277,10 +1263,7 @@ fn bindgen_test_layout___pthread_cond_s__bindgen_ty_1__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_cond_s__bindgen_ty_1__bindgen_ty_1>())).__low
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_cond_s__bindgen_ty_1__bindgen_ty_1, __low),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1290,10 +1273,7 @@ fn bindgen_test_layout___pthread_cond_s__bindgen_ty_1__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_cond_s__bindgen_ty_1__bindgen_ty_1>())).__high
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_cond_s__bindgen_ty_1__bindgen_ty_1, __high),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1316,9 +1296,7 @@ fn bindgen_test_layout___pthread_cond_s__bindgen_ty_1() {
         concat!("Alignment of ", stringify!(__pthread_cond_s__bindgen_ty_1))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_cond_s__bindgen_ty_1>())).__wseq as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_cond_s__bindgen_ty_1, __wseq),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1328,9 +1306,7 @@ fn bindgen_test_layout___pthread_cond_s__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_cond_s__bindgen_ty_1>())).__wseq32 as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_cond_s__bindgen_ty_1, __wseq32),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1372,10 +1348,7 @@ fn bindgen_test_layout___pthread_cond_s__bindgen_ty_2__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_cond_s__bindgen_ty_2__bindgen_ty_1>())).__low
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_cond_s__bindgen_ty_2__bindgen_ty_1, __low),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1385,10 +1358,7 @@ fn bindgen_test_layout___pthread_cond_s__bindgen_ty_2__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_cond_s__bindgen_ty_2__bindgen_ty_1>())).__high
-                as *const _ as usize
-        },
+        ::std::mem::offset_of!(__pthread_cond_s__bindgen_ty_2__bindgen_ty_1, __high),
         4usize,
         concat!(
             "Offset of field: ",
@@ -1411,10 +1381,7 @@ fn bindgen_test_layout___pthread_cond_s__bindgen_ty_2() {
         concat!("Alignment of ", stringify!(__pthread_cond_s__bindgen_ty_2))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_cond_s__bindgen_ty_2>())).__g1_start as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(__pthread_cond_s__bindgen_ty_2, __g1_start),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1424,10 +1391,7 @@ fn bindgen_test_layout___pthread_cond_s__bindgen_ty_2() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__pthread_cond_s__bindgen_ty_2>())).__g1_start32 as *const _
-                as usize
-        },
+        ::std::mem::offset_of!(__pthread_cond_s__bindgen_ty_2, __g1_start32),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1450,7 +1414,7 @@ fn bindgen_test_layout___pthread_cond_s() {
         concat!("Alignment of ", stringify!(__pthread_cond_s))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_cond_s>())).__g_refs as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_cond_s, __g_refs),
         16usize,
         concat!(
             "Offset of field: ",
@@ -1460,7 +1424,7 @@ fn bindgen_test_layout___pthread_cond_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_cond_s>())).__g_size as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_cond_s, __g_size),
         24usize,
         concat!(
             "Offset of field: ",
@@ -1470,7 +1434,7 @@ fn bindgen_test_layout___pthread_cond_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_cond_s>())).__g1_orig_size as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_cond_s, __g1_orig_size),
         32usize,
         concat!(
             "Offset of field: ",
@@ -1480,7 +1444,7 @@ fn bindgen_test_layout___pthread_cond_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_cond_s>())).__wrefs as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_cond_s, __wrefs),
         36usize,
         concat!(
             "Offset of field: ",
@@ -1490,7 +1454,7 @@ fn bindgen_test_layout___pthread_cond_s() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__pthread_cond_s>())).__g_signals as *const _ as usize },
+        ::std::mem::offset_of!(__pthread_cond_s, __g_signals),
         40usize,
         concat!(
             "Offset of field: ",
@@ -1521,7 +1485,7 @@ fn bindgen_test_layout_pthread_mutexattr_t() {
         concat!("Alignment of ", stringify!(pthread_mutexattr_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_mutexattr_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_mutexattr_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1531,7 +1495,7 @@ fn bindgen_test_layout_pthread_mutexattr_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_mutexattr_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_mutexattr_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1561,7 +1525,7 @@ fn bindgen_test_layout_pthread_condattr_t() {
         concat!("Alignment of ", stringify!(pthread_condattr_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_condattr_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_condattr_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1571,7 +1535,7 @@ fn bindgen_test_layout_pthread_condattr_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_condattr_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_condattr_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1603,7 +1567,7 @@ fn bindgen_test_layout_pthread_attr_t() {
         concat!("Alignment of ", stringify!(pthread_attr_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_attr_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_attr_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1613,7 +1577,7 @@ fn bindgen_test_layout_pthread_attr_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_attr_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_attr_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1644,7 +1608,7 @@ fn bindgen_test_layout_pthread_mutex_t() {
         concat!("Alignment of ", stringify!(pthread_mutex_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_mutex_t>())).__data as *const _ as usize },
+        ::std::mem::offset_of!(pthread_mutex_t, __data),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1654,7 +1618,7 @@ fn bindgen_test_layout_pthread_mutex_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_mutex_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_mutex_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1664,7 +1628,7 @@ fn bindgen_test_layout_pthread_mutex_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_mutex_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_mutex_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1695,7 +1659,7 @@ fn bindgen_test_layout_pthread_cond_t() {
         concat!("Alignment of ", stringify!(pthread_cond_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_cond_t>())).__data as *const _ as usize },
+        ::std::mem::offset_of!(pthread_cond_t, __data),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1705,7 +1669,7 @@ fn bindgen_test_layout_pthread_cond_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_cond_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_cond_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1715,7 +1679,7 @@ fn bindgen_test_layout_pthread_cond_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_cond_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_cond_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1746,7 +1710,7 @@ fn bindgen_test_layout_pthread_rwlock_t() {
         concat!("Alignment of ", stringify!(pthread_rwlock_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_rwlock_t>())).__data as *const _ as usize },
+        ::std::mem::offset_of!(pthread_rwlock_t, __data),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1756,7 +1720,7 @@ fn bindgen_test_layout_pthread_rwlock_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_rwlock_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_rwlock_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1766,7 +1730,7 @@ fn bindgen_test_layout_pthread_rwlock_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_rwlock_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_rwlock_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1796,7 +1760,7 @@ fn bindgen_test_layout_pthread_rwlockattr_t() {
         concat!("Alignment of ", stringify!(pthread_rwlockattr_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_rwlockattr_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_rwlockattr_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1806,7 +1770,7 @@ fn bindgen_test_layout_pthread_rwlockattr_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_rwlockattr_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_rwlockattr_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1837,7 +1801,7 @@ fn bindgen_test_layout_pthread_barrier_t() {
         concat!("Alignment of ", stringify!(pthread_barrier_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_barrier_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_barrier_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1847,7 +1811,7 @@ fn bindgen_test_layout_pthread_barrier_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_barrier_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_barrier_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1877,7 +1841,7 @@ fn bindgen_test_layout_pthread_barrierattr_t() {
         concat!("Alignment of ", stringify!(pthread_barrierattr_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_barrierattr_t>())).__size as *const _ as usize },
+        ::std::mem::offset_of!(pthread_barrierattr_t, __size),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1887,7 +1851,7 @@ fn bindgen_test_layout_pthread_barrierattr_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<pthread_barrierattr_t>())).__align as *const _ as usize },
+        ::std::mem::offset_of!(pthread_barrierattr_t, __align),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1916,7 +1880,7 @@ fn bindgen_test_layout_iovec() {
         concat!("Alignment of ", stringify!(iovec))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<iovec>())).iov_base as *const _ as usize },
+        ::std::mem::offset_of!(iovec, iov_base),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1926,7 +1890,7 @@ fn bindgen_test_layout_iovec() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<iovec>())).iov_len as *const _ as usize },
+        ::std::mem::offset_of!(iovec, iov_len),
         8usize,
         concat!(
             "Offset of field: ",
@@ -1969,7 +1933,7 @@ fn bindgen_test_layout_sockaddr() {
         concat!("Alignment of ", stringify!(sockaddr))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<sockaddr>())).sa_family as *const _ as usize },
+        ::std::mem::offset_of!(sockaddr, sa_family),
         0usize,
         concat!(
             "Offset of field: ",
@@ -1979,7 +1943,7 @@ fn bindgen_test_layout_sockaddr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<sockaddr>())).sa_data as *const _ as usize },
+        ::std::mem::offset_of!(sockaddr, sa_data),
         2usize,
         concat!(
             "Offset of field: ",
@@ -2009,7 +1973,7 @@ fn bindgen_test_layout_sockaddr_storage() {
         concat!("Alignment of ", stringify!(sockaddr_storage))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<sockaddr_storage>())).ss_family as *const _ as usize },
+        ::std::mem::offset_of!(sockaddr_storage, ss_family),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2019,7 +1983,7 @@ fn bindgen_test_layout_sockaddr_storage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<sockaddr_storage>())).__ss_padding as *const _ as usize },
+        ::std::mem::offset_of!(sockaddr_storage, __ss_padding),
         2usize,
         concat!(
             "Offset of field: ",
@@ -2029,7 +1993,7 @@ fn bindgen_test_layout_sockaddr_storage() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<sockaddr_storage>())).__ss_align as *const _ as usize },
+        ::std::mem::offset_of!(sockaddr_storage, __ss_align),
         120usize,
         concat!(
             "Offset of field: ",
@@ -2087,7 +2051,7 @@ fn bindgen_test_layout_msghdr() {
         concat!("Alignment of ", stringify!(msghdr))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<msghdr>())).msg_name as *const _ as usize },
+        ::std::mem::offset_of!(msghdr, msg_name),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2097,7 +2061,7 @@ fn bindgen_test_layout_msghdr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<msghdr>())).msg_namelen as *const _ as usize },
+        ::std::mem::offset_of!(msghdr, msg_namelen),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2107,7 +2071,7 @@ fn bindgen_test_layout_msghdr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<msghdr>())).msg_iov as *const _ as usize },
+        ::std::mem::offset_of!(msghdr, msg_iov),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2117,7 +2081,7 @@ fn bindgen_test_layout_msghdr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<msghdr>())).msg_iovlen as *const _ as usize },
+        ::std::mem::offset_of!(msghdr, msg_iovlen),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2127,7 +2091,7 @@ fn bindgen_test_layout_msghdr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<msghdr>())).msg_control as *const _ as usize },
+        ::std::mem::offset_of!(msghdr, msg_control),
         32usize,
         concat!(
             "Offset of field: ",
@@ -2137,7 +2101,7 @@ fn bindgen_test_layout_msghdr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<msghdr>())).msg_controllen as *const _ as usize },
+        ::std::mem::offset_of!(msghdr, msg_controllen),
         40usize,
         concat!(
             "Offset of field: ",
@@ -2147,7 +2111,7 @@ fn bindgen_test_layout_msghdr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<msghdr>())).msg_flags as *const _ as usize },
+        ::std::mem::offset_of!(msghdr, msg_flags),
         48usize,
         concat!(
             "Offset of field: ",
@@ -2204,7 +2168,7 @@ fn bindgen_test_layout_linger() {
         concat!("Alignment of ", stringify!(linger))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<linger>())).l_onoff as *const _ as usize },
+        ::std::mem::offset_of!(linger, l_onoff),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2214,7 +2178,7 @@ fn bindgen_test_layout_linger() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<linger>())).l_linger as *const _ as usize },
+        ::std::mem::offset_of!(linger, l_linger),
         4usize,
         concat!(
             "Offset of field: ",
@@ -2243,7 +2207,7 @@ fn bindgen_test_layout_osockaddr() {
         concat!("Alignment of ", stringify!(osockaddr))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<osockaddr>())).sa_family as *const _ as usize },
+        ::std::mem::offset_of!(osockaddr, sa_family),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2253,7 +2217,7 @@ fn bindgen_test_layout_osockaddr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<osockaddr>())).sa_data as *const _ as usize },
+        ::std::mem::offset_of!(osockaddr, sa_data),
         2usize,
         concat!(
             "Offset of field: ",
@@ -2414,7 +2378,7 @@ fn bindgen_test_layout___mbstate_t__bindgen_ty_1() {
         concat!("Alignment of ", stringify!(__mbstate_t__bindgen_ty_1))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__mbstate_t__bindgen_ty_1>())).__wch as *const _ as usize },
+        ::std::mem::offset_of!(__mbstate_t__bindgen_ty_1, __wch),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2424,9 +2388,7 @@ fn bindgen_test_layout___mbstate_t__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<__mbstate_t__bindgen_ty_1>())).__wchb as *const _ as usize
-        },
+        ::std::mem::offset_of!(__mbstate_t__bindgen_ty_1, __wchb),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2449,7 +2411,7 @@ fn bindgen_test_layout___mbstate_t() {
         concat!("Alignment of ", stringify!(__mbstate_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__mbstate_t>())).__count as *const _ as usize },
+        ::std::mem::offset_of!(__mbstate_t, __count),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2459,7 +2421,7 @@ fn bindgen_test_layout___mbstate_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__mbstate_t>())).__value as *const _ as usize },
+        ::std::mem::offset_of!(__mbstate_t, __value),
         4usize,
         concat!(
             "Offset of field: ",
@@ -2488,7 +2450,7 @@ fn bindgen_test_layout__G_fpos_t() {
         concat!("Alignment of ", stringify!(_G_fpos_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_G_fpos_t>())).__pos as *const _ as usize },
+        ::std::mem::offset_of!(_G_fpos_t, __pos),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2498,7 +2460,7 @@ fn bindgen_test_layout__G_fpos_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_G_fpos_t>())).__state as *const _ as usize },
+        ::std::mem::offset_of!(_G_fpos_t, __state),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2528,7 +2490,7 @@ fn bindgen_test_layout__G_fpos64_t() {
         concat!("Alignment of ", stringify!(_G_fpos64_t))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_G_fpos64_t>())).__pos as *const _ as usize },
+        ::std::mem::offset_of!(_G_fpos64_t, __pos),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2538,7 +2500,7 @@ fn bindgen_test_layout__G_fpos64_t() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_G_fpos64_t>())).__state as *const _ as usize },
+        ::std::mem::offset_of!(_G_fpos64_t, __state),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2613,7 +2575,7 @@ fn bindgen_test_layout__IO_FILE() {
         concat!("Alignment of ", stringify!(_IO_FILE))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._flags as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _flags),
         0usize,
         concat!(
             "Offset of field: ",
@@ -2623,7 +2585,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_read_ptr as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_read_ptr),
         8usize,
         concat!(
             "Offset of field: ",
@@ -2633,7 +2595,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_read_end as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_read_end),
         16usize,
         concat!(
             "Offset of field: ",
@@ -2643,7 +2605,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_read_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_read_base),
         24usize,
         concat!(
             "Offset of field: ",
@@ -2653,7 +2615,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_write_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_write_base),
         32usize,
         concat!(
             "Offset of field: ",
@@ -2663,7 +2625,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_write_ptr as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_write_ptr),
         40usize,
         concat!(
             "Offset of field: ",
@@ -2673,7 +2635,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_write_end as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_write_end),
         48usize,
         concat!(
             "Offset of field: ",
@@ -2683,7 +2645,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_buf_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_buf_base),
         56usize,
         concat!(
             "Offset of field: ",
@@ -2693,7 +2655,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_buf_end as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_buf_end),
         64usize,
         concat!(
             "Offset of field: ",
@@ -2703,7 +2665,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_save_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_save_base),
         72usize,
         concat!(
             "Offset of field: ",
@@ -2713,7 +2675,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_backup_base as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_backup_base),
         80usize,
         concat!(
             "Offset of field: ",
@@ -2723,7 +2685,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._IO_save_end as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _IO_save_end),
         88usize,
         concat!(
             "Offset of field: ",
@@ -2733,7 +2695,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._markers as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _markers),
         96usize,
         concat!(
             "Offset of field: ",
@@ -2743,7 +2705,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._chain as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _chain),
         104usize,
         concat!(
             "Offset of field: ",
@@ -2753,7 +2715,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._fileno as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _fileno),
         112usize,
         concat!(
             "Offset of field: ",
@@ -2763,7 +2725,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._flags2 as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _flags2),
         116usize,
         concat!(
             "Offset of field: ",
@@ -2773,7 +2735,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._old_offset as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _old_offset),
         120usize,
         concat!(
             "Offset of field: ",
@@ -2783,7 +2745,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._cur_column as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _cur_column),
         128usize,
         concat!(
             "Offset of field: ",
@@ -2793,7 +2755,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._vtable_offset as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _vtable_offset),
         130usize,
         concat!(
             "Offset of field: ",
@@ -2803,7 +2765,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._shortbuf as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _shortbuf),
         131usize,
         concat!(
             "Offset of field: ",
@@ -2813,7 +2775,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._lock as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _lock),
         136usize,
         concat!(
             "Offset of field: ",
@@ -2823,7 +2785,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._offset as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _offset),
         144usize,
         concat!(
             "Offset of field: ",
@@ -2833,7 +2795,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._codecvt as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _codecvt),
         152usize,
         concat!(
             "Offset of field: ",
@@ -2843,7 +2805,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._wide_data as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _wide_data),
         160usize,
         concat!(
             "Offset of field: ",
@@ -2853,7 +2815,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._freeres_list as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _freeres_list),
         168usize,
         concat!(
             "Offset of field: ",
@@ -2863,7 +2825,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._freeres_buf as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _freeres_buf),
         176usize,
         concat!(
             "Offset of field: ",
@@ -2873,7 +2835,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>())).__pad5 as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, __pad5),
         184usize,
         concat!(
             "Offset of field: ",
@@ -2883,7 +2845,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._mode as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _mode),
         192usize,
         concat!(
             "Offset of field: ",
@@ -2893,7 +2855,7 @@ fn bindgen_test_layout__IO_FILE() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<_IO_FILE>()))._unused2 as *const _ as usize },
+        ::std::mem::offset_of!(_IO_FILE, _unused2),
         196usize,
         concat!(
             "Offset of field: ",
@@ -3333,7 +3295,7 @@ fn bindgen_test_layout_tm() {
         concat!("Alignment of ", stringify!(tm))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_sec as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_sec),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3343,7 +3305,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_min as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_min),
         4usize,
         concat!(
             "Offset of field: ",
@@ -3353,7 +3315,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_hour as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_hour),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3363,7 +3325,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_mday as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_mday),
         12usize,
         concat!(
             "Offset of field: ",
@@ -3373,7 +3335,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_mon as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_mon),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3383,7 +3345,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_year as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_year),
         20usize,
         concat!(
             "Offset of field: ",
@@ -3393,7 +3355,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_wday as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_wday),
         24usize,
         concat!(
             "Offset of field: ",
@@ -3403,7 +3365,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_yday as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_yday),
         28usize,
         concat!(
             "Offset of field: ",
@@ -3413,7 +3375,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_isdst as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_isdst),
         32usize,
         concat!(
             "Offset of field: ",
@@ -3423,7 +3385,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_gmtoff as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_gmtoff),
         40usize,
         concat!(
             "Offset of field: ",
@@ -3433,7 +3395,7 @@ fn bindgen_test_layout_tm() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<tm>())).tm_zone as *const _ as usize },
+        ::std::mem::offset_of!(tm, tm_zone),
         48usize,
         concat!(
             "Offset of field: ",
@@ -3462,7 +3424,7 @@ fn bindgen_test_layout_itimerspec() {
         concat!("Alignment of ", stringify!(itimerspec))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<itimerspec>())).it_interval as *const _ as usize },
+        ::std::mem::offset_of!(itimerspec, it_interval),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3472,7 +3434,7 @@ fn bindgen_test_layout_itimerspec() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<itimerspec>())).it_value as *const _ as usize },
+        ::std::mem::offset_of!(itimerspec, it_value),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3509,7 +3471,7 @@ fn bindgen_test_layout___locale_struct() {
         concat!("Alignment of ", stringify!(__locale_struct))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__locale_struct>())).__locales as *const _ as usize },
+        ::std::mem::offset_of!(__locale_struct, __locales),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3519,7 +3481,7 @@ fn bindgen_test_layout___locale_struct() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__locale_struct>())).__ctype_b as *const _ as usize },
+        ::std::mem::offset_of!(__locale_struct, __ctype_b),
         104usize,
         concat!(
             "Offset of field: ",
@@ -3529,7 +3491,7 @@ fn bindgen_test_layout___locale_struct() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__locale_struct>())).__ctype_tolower as *const _ as usize },
+        ::std::mem::offset_of!(__locale_struct, __ctype_tolower),
         112usize,
         concat!(
             "Offset of field: ",
@@ -3539,7 +3501,7 @@ fn bindgen_test_layout___locale_struct() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__locale_struct>())).__ctype_toupper as *const _ as usize },
+        ::std::mem::offset_of!(__locale_struct, __ctype_toupper),
         120usize,
         concat!(
             "Offset of field: ",
@@ -3549,7 +3511,7 @@ fn bindgen_test_layout___locale_struct() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__locale_struct>())).__names as *const _ as usize },
+        ::std::mem::offset_of!(__locale_struct, __names),
         128usize,
         concat!(
             "Offset of field: ",
@@ -3719,7 +3681,7 @@ fn bindgen_test_layout_timezone() {
         concat!("Alignment of ", stringify!(timezone))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<timezone>())).tz_minuteswest as *const _ as usize },
+        ::std::mem::offset_of!(timezone, tz_minuteswest),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3729,7 +3691,7 @@ fn bindgen_test_layout_timezone() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<timezone>())).tz_dsttime as *const _ as usize },
+        ::std::mem::offset_of!(timezone, tz_dsttime),
         4usize,
         concat!(
             "Offset of field: ",
@@ -3774,7 +3736,7 @@ fn bindgen_test_layout_itimerval() {
         concat!("Alignment of ", stringify!(itimerval))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<itimerval>())).it_interval as *const _ as usize },
+        ::std::mem::offset_of!(itimerval, it_interval),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3784,7 +3746,7 @@ fn bindgen_test_layout_itimerval() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<itimerval>())).it_value as *const _ as usize },
+        ::std::mem::offset_of!(itimerval, it_value),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3864,7 +3826,7 @@ fn bindgen_test_layout_curl_httppost() {
         concat!("Alignment of ", stringify!(curl_httppost))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).next as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, next),
         0usize,
         concat!(
             "Offset of field: ",
@@ -3874,7 +3836,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).name as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, name),
         8usize,
         concat!(
             "Offset of field: ",
@@ -3884,7 +3846,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).namelength as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, namelength),
         16usize,
         concat!(
             "Offset of field: ",
@@ -3894,7 +3856,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).contents as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, contents),
         24usize,
         concat!(
             "Offset of field: ",
@@ -3904,7 +3866,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).contentslength as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, contentslength),
         32usize,
         concat!(
             "Offset of field: ",
@@ -3914,7 +3876,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).buffer as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, buffer),
         40usize,
         concat!(
             "Offset of field: ",
@@ -3924,7 +3886,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).bufferlength as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, bufferlength),
         48usize,
         concat!(
             "Offset of field: ",
@@ -3934,7 +3896,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).contenttype as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, contenttype),
         56usize,
         concat!(
             "Offset of field: ",
@@ -3944,7 +3906,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).contentheader as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, contentheader),
         64usize,
         concat!(
             "Offset of field: ",
@@ -3954,7 +3916,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).more as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, more),
         72usize,
         concat!(
             "Offset of field: ",
@@ -3964,7 +3926,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, flags),
         80usize,
         concat!(
             "Offset of field: ",
@@ -3974,7 +3936,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).showfilename as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, showfilename),
         88usize,
         concat!(
             "Offset of field: ",
@@ -3984,7 +3946,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).userp as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, userp),
         96usize,
         concat!(
             "Offset of field: ",
@@ -3994,7 +3956,7 @@ fn bindgen_test_layout_curl_httppost() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_httppost>())).contentlen as *const _ as usize },
+        ::std::mem::offset_of!(curl_httppost, contentlen),
         104usize,
         concat!(
             "Offset of field: ",
@@ -4088,9 +4050,7 @@ fn bindgen_test_layout_curl_fileinfo__bindgen_ty_1() {
         concat!("Alignment of ", stringify!(curl_fileinfo__bindgen_ty_1))
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_fileinfo__bindgen_ty_1>())).time as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_fileinfo__bindgen_ty_1, time),
         0usize,
         concat!(
             "Offset of field: ",
@@ -4100,9 +4060,7 @@ fn bindgen_test_layout_curl_fileinfo__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_fileinfo__bindgen_ty_1>())).perm as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_fileinfo__bindgen_ty_1, perm),
         8usize,
         concat!(
             "Offset of field: ",
@@ -4112,9 +4070,7 @@ fn bindgen_test_layout_curl_fileinfo__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_fileinfo__bindgen_ty_1>())).user as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_fileinfo__bindgen_ty_1, user),
         16usize,
         concat!(
             "Offset of field: ",
@@ -4124,9 +4080,7 @@ fn bindgen_test_layout_curl_fileinfo__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_fileinfo__bindgen_ty_1>())).group as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_fileinfo__bindgen_ty_1, group),
         24usize,
         concat!(
             "Offset of field: ",
@@ -4136,9 +4090,7 @@ fn bindgen_test_layout_curl_fileinfo__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_fileinfo__bindgen_ty_1>())).target as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_fileinfo__bindgen_ty_1, target),
         32usize,
         concat!(
             "Offset of field: ",
@@ -4161,7 +4113,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         concat!("Alignment of ", stringify!(curl_fileinfo))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).filename as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, filename),
         0usize,
         concat!(
             "Offset of field: ",
@@ -4171,7 +4123,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).filetype as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, filetype),
         8usize,
         concat!(
             "Offset of field: ",
@@ -4181,7 +4133,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).time as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, time),
         16usize,
         concat!(
             "Offset of field: ",
@@ -4191,7 +4143,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).perm as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, perm),
         24usize,
         concat!(
             "Offset of field: ",
@@ -4201,7 +4153,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).uid as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, uid),
         28usize,
         concat!(
             "Offset of field: ",
@@ -4211,7 +4163,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).gid as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, gid),
         32usize,
         concat!(
             "Offset of field: ",
@@ -4221,7 +4173,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).size as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, size),
         40usize,
         concat!(
             "Offset of field: ",
@@ -4231,7 +4183,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).hardlinks as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, hardlinks),
         48usize,
         concat!(
             "Offset of field: ",
@@ -4241,7 +4193,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).strings as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, strings),
         56usize,
         concat!(
             "Offset of field: ",
@@ -4251,7 +4203,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).flags as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, flags),
         96usize,
         concat!(
             "Offset of field: ",
@@ -4261,7 +4213,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).b_data as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, b_data),
         104usize,
         concat!(
             "Offset of field: ",
@@ -4271,7 +4223,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).b_size as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, b_size),
         112usize,
         concat!(
             "Offset of field: ",
@@ -4281,7 +4233,7 @@ fn bindgen_test_layout_curl_fileinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_fileinfo>())).b_used as *const _ as usize },
+        ::std::mem::offset_of!(curl_fileinfo, b_used),
         120usize,
         concat!(
             "Offset of field: ",
@@ -4363,7 +4315,7 @@ fn bindgen_test_layout_curl_sockaddr() {
         concat!("Alignment of ", stringify!(curl_sockaddr))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_sockaddr>())).family as *const _ as usize },
+        ::std::mem::offset_of!(curl_sockaddr, family),
         0usize,
         concat!(
             "Offset of field: ",
@@ -4373,7 +4325,7 @@ fn bindgen_test_layout_curl_sockaddr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_sockaddr>())).socktype as *const _ as usize },
+        ::std::mem::offset_of!(curl_sockaddr, socktype),
         4usize,
         concat!(
             "Offset of field: ",
@@ -4383,7 +4335,7 @@ fn bindgen_test_layout_curl_sockaddr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_sockaddr>())).protocol as *const _ as usize },
+        ::std::mem::offset_of!(curl_sockaddr, protocol),
         8usize,
         concat!(
             "Offset of field: ",
@@ -4393,7 +4345,7 @@ fn bindgen_test_layout_curl_sockaddr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_sockaddr>())).addrlen as *const _ as usize },
+        ::std::mem::offset_of!(curl_sockaddr, addrlen),
         12usize,
         concat!(
             "Offset of field: ",
@@ -4403,7 +4355,7 @@ fn bindgen_test_layout_curl_sockaddr() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_sockaddr>())).addr as *const _ as usize },
+        ::std::mem::offset_of!(curl_sockaddr, addr),
         16usize,
         concat!(
             "Offset of field: ",
@@ -4621,7 +4573,7 @@ fn bindgen_test_layout_curl_khkey() {
         concat!("Alignment of ", stringify!(curl_khkey))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_khkey>())).key as *const _ as usize },
+        ::std::mem::offset_of!(curl_khkey, key),
         0usize,
         concat!(
             "Offset of field: ",
@@ -4631,7 +4583,7 @@ fn bindgen_test_layout_curl_khkey() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_khkey>())).len as *const _ as usize },
+        ::std::mem::offset_of!(curl_khkey, len),
         8usize,
         concat!(
             "Offset of field: ",
@@ -4641,7 +4593,7 @@ fn bindgen_test_layout_curl_khkey() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_khkey>())).keytype as *const _ as usize },
+        ::std::mem::offset_of!(curl_khkey, keytype),
         16usize,
         concat!(
             "Offset of field: ",
@@ -5186,7 +5138,7 @@ fn bindgen_test_layout_curl_forms() {
         concat!("Alignment of ", stringify!(curl_forms))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_forms>())).option as *const _ as usize },
+        ::std::mem::offset_of!(curl_forms, option),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5196,7 +5148,7 @@ fn bindgen_test_layout_curl_forms() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_forms>())).value as *const _ as usize },
+        ::std::mem::offset_of!(curl_forms, value),
         8usize,
         concat!(
             "Offset of field: ",
@@ -5307,7 +5259,7 @@ fn bindgen_test_layout_curl_slist() {
         concat!("Alignment of ", stringify!(curl_slist))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_slist>())).data as *const _ as usize },
+        ::std::mem::offset_of!(curl_slist, data),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5317,7 +5269,7 @@ fn bindgen_test_layout_curl_slist() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_slist>())).next as *const _ as usize },
+        ::std::mem::offset_of!(curl_slist, next),
         8usize,
         concat!(
             "Offset of field: ",
@@ -5346,7 +5298,7 @@ fn bindgen_test_layout_curl_ssl_backend() {
         concat!("Alignment of ", stringify!(curl_ssl_backend))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_ssl_backend>())).id as *const _ as usize },
+        ::std::mem::offset_of!(curl_ssl_backend, id),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5356,7 +5308,7 @@ fn bindgen_test_layout_curl_ssl_backend() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_ssl_backend>())).name as *const _ as usize },
+        ::std::mem::offset_of!(curl_ssl_backend, name),
         8usize,
         concat!(
             "Offset of field: ",
@@ -5409,7 +5361,7 @@ fn bindgen_test_layout_curl_certinfo() {
         concat!("Alignment of ", stringify!(curl_certinfo))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_certinfo>())).num_of_certs as *const _ as usize },
+        ::std::mem::offset_of!(curl_certinfo, num_of_certs),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5419,7 +5371,7 @@ fn bindgen_test_layout_curl_certinfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_certinfo>())).certinfo as *const _ as usize },
+        ::std::mem::offset_of!(curl_certinfo, certinfo),
         8usize,
         concat!(
             "Offset of field: ",
@@ -5448,7 +5400,7 @@ fn bindgen_test_layout_curl_tlssessioninfo() {
         concat!("Alignment of ", stringify!(curl_tlssessioninfo))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_tlssessioninfo>())).backend as *const _ as usize },
+        ::std::mem::offset_of!(curl_tlssessioninfo, backend),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5458,7 +5410,7 @@ fn bindgen_test_layout_curl_tlssessioninfo() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_tlssessioninfo>())).internals as *const _ as usize },
+        ::std::mem::offset_of!(curl_tlssessioninfo, internals),
         8usize,
         concat!(
             "Offset of field: ",
@@ -5656,7 +5608,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         concat!("Alignment of ", stringify!(curl_version_info_data))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_version_info_data>())).age as *const _ as usize },
+        ::std::mem::offset_of!(curl_version_info_data, age),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5666,7 +5618,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_version_info_data>())).version as *const _ as usize },
+        ::std::mem::offset_of!(curl_version_info_data, version),
         8usize,
         concat!(
             "Offset of field: ",
@@ -5676,9 +5628,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).version_num as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, version_num),
         16usize,
         concat!(
             "Offset of field: ",
@@ -5688,7 +5638,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_version_info_data>())).host as *const _ as usize },
+        ::std::mem::offset_of!(curl_version_info_data, host),
         24usize,
         concat!(
             "Offset of field: ",
@@ -5698,7 +5648,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_version_info_data>())).features as *const _ as usize },
+        ::std::mem::offset_of!(curl_version_info_data, features),
         32usize,
         concat!(
             "Offset of field: ",
@@ -5708,9 +5658,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).ssl_version as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, ssl_version),
         40usize,
         concat!(
             "Offset of field: ",
@@ -5720,9 +5668,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).ssl_version_num as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, ssl_version_num),
         48usize,
         concat!(
             "Offset of field: ",
@@ -5732,9 +5678,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).libz_version as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, libz_version),
         56usize,
         concat!(
             "Offset of field: ",
@@ -5744,9 +5688,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).protocols as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, protocols),
         64usize,
         concat!(
             "Offset of field: ",
@@ -5756,7 +5698,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_version_info_data>())).ares as *const _ as usize },
+        ::std::mem::offset_of!(curl_version_info_data, ares),
         72usize,
         concat!(
             "Offset of field: ",
@@ -5766,7 +5708,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_version_info_data>())).ares_num as *const _ as usize },
+        ::std::mem::offset_of!(curl_version_info_data, ares_num),
         80usize,
         concat!(
             "Offset of field: ",
@@ -5776,7 +5718,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_version_info_data>())).libidn as *const _ as usize },
+        ::std::mem::offset_of!(curl_version_info_data, libidn),
         88usize,
         concat!(
             "Offset of field: ",
@@ -5786,9 +5728,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).iconv_ver_num as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, iconv_ver_num),
         96usize,
         concat!(
             "Offset of field: ",
@@ -5798,9 +5738,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).libssh_version as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, libssh_version),
         104usize,
         concat!(
             "Offset of field: ",
@@ -5810,9 +5748,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).brotli_ver_num as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, brotli_ver_num),
         112usize,
         concat!(
             "Offset of field: ",
@@ -5822,9 +5758,7 @@ fn bindgen_test_layout_curl_version_info_data() {
         )
     );
     assert_eq!(
-        unsafe {
-            &(*(::std::ptr::null::<curl_version_info_data>())).brotli_version as *const _ as usize
-        },
+        ::std::mem::offset_of!(curl_version_info_data, brotli_version),
         120usize,
         concat!(
             "Offset of field: ",
@@ -5934,7 +5868,7 @@ fn bindgen_test_layout_CURLMsg__bindgen_ty_1() {
         concat!("Alignment of ", stringify!(CURLMsg__bindgen_ty_1))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<CURLMsg__bindgen_ty_1>())).whatever as *const _ as usize },
+        ::std::mem::offset_of!(CURLMsg__bindgen_ty_1, whatever),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5944,7 +5878,7 @@ fn bindgen_test_layout_CURLMsg__bindgen_ty_1() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<CURLMsg__bindgen_ty_1>())).result as *const _ as usize },
+        ::std::mem::offset_of!(CURLMsg__bindgen_ty_1, result),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5967,7 +5901,7 @@ fn bindgen_test_layout_CURLMsg() {
         concat!("Alignment of ", stringify!(CURLMsg))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<CURLMsg>())).msg as *const _ as usize },
+        ::std::mem::offset_of!(CURLMsg, msg),
         0usize,
         concat!(
             "Offset of field: ",
@@ -5977,7 +5911,7 @@ fn bindgen_test_layout_CURLMsg() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<CURLMsg>())).easy_handle as *const _ as usize },
+        ::std::mem::offset_of!(CURLMsg, easy_handle),
         8usize,
         concat!(
             "Offset of field: ",
@@ -5987,7 +5921,7 @@ fn bindgen_test_layout_CURLMsg() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<CURLMsg>())).data as *const _ as usize },
+        ::std::mem::offset_of!(CURLMsg, data),
         16usize,
         concat!(
             "Offset of field: ",
@@ -6017,7 +5951,7 @@ fn bindgen_test_layout_curl_waitfd() {
         concat!("Alignment of ", stringify!(curl_waitfd))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_waitfd>())).fd as *const _ as usize },
+        ::std::mem::offset_of!(curl_waitfd, fd),
         0usize,
         concat!(
             "Offset of field: ",
@@ -6027,7 +5961,7 @@ fn bindgen_test_layout_curl_waitfd() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_waitfd>())).events as *const _ as usize },
+        ::std::mem::offset_of!(curl_waitfd, events),
         4usize,
         concat!(
             "Offset of field: ",
@@ -6037,7 +5971,7 @@ fn bindgen_test_layout_curl_waitfd() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<curl_waitfd>())).revents as *const _ as usize },
+        ::std::mem::offset_of!(curl_waitfd, revents),
         6usize,
         concat!(
             "Offset of field: ",
@@ -6284,7 +6218,7 @@ fn bindgen_test_layout___va_list_tag() {
         concat!("Alignment of ", stringify!(__va_list_tag))
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__va_list_tag>())).gp_offset as *const _ as usize },
+        ::std::mem::offset_of!(__va_list_tag, gp_offset),
         0usize,
         concat!(
             "Offset of field: ",
@@ -6294,7 +6228,7 @@ fn bindgen_test_layout___va_list_tag() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__va_list_tag>())).fp_offset as *const _ as usize },
+        ::std::mem::offset_of!(__va_list_tag, fp_offset),
         4usize,
         concat!(
             "Offset of field: ",
@@ -6304,7 +6238,7 @@ fn bindgen_test_layout___va_list_tag() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__va_list_tag>())).overflow_arg_area as *const _ as usize },
+        ::std::mem::offset_of!(__va_list_tag, overflow_arg_area),
         8usize,
         concat!(
             "Offset of field: ",
@@ -6314,7 +6248,7 @@ fn bindgen_test_layout___va_list_tag() {
         )
     );
     assert_eq!(
-        unsafe { &(*(::std::ptr::null::<__va_list_tag>())).reg_save_area as *const _ as usize },
+        ::std::mem::offset_of!(__va_list_tag, reg_save_area),
         16usize,
         concat!(
             "Offset of field: ",
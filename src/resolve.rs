@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use libc::*;
+use openssl::rand::rand_bytes;
+use crate::Options;
+use crate::error::Error;
+use crate::raw::{
+    CURLcode::*,
+    CURL_IPRESOLVE_V4,
+    CURL_IPRESOLVE_V6,
+};
+
+/// How long resolved names are kept if `CURLOPT_DNS_CACHE_TIMEOUT` isn't set.
+pub const DEFAULT_CACHE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The per-handle DNS cache, which also holds the `CURLOPT_RESOLVE` entries.
+#[derive(Default)]
+pub struct Cache {
+    entries: HashMap<(String, u16), CacheEntry>,
+}
+
+struct CacheEntry {
+    addrs: Vec<SocketAddr>,
+    // `None` for CURLOPT_RESOLVE entries, which never expire
+    resolved: Option<Instant>,
+}
+
+impl Cache {
+    fn get(&mut self, host: &str, port: u16, timeout: Option<Duration>) -> Option<Vec<SocketAddr>> {
+        let key = (host.to_ascii_lowercase(), port);
+        let expired = match self.entries.get(&key)?.resolved {
+            Some(resolved) => timeout.is_some_and(|timeout| resolved.elapsed() >= timeout),
+            None => false,
+        };
+
+        if expired {
+            self.entries.remove(&key);
+            return None;
+        }
+
+        self.entries.get(&key).map(|entry| entry.addrs.clone())
+    }
+
+    fn insert(&mut self, host: &str, port: u16, addrs: Vec<SocketAddr>, resolved: Option<Instant>) {
+        self.entries.insert((host.to_ascii_lowercase(), port), CacheEntry { addrs, resolved });
+    }
+
+    fn remove(&mut self, host: &str, port: u16) {
+        self.entries.remove(&(host.to_ascii_lowercase(), port));
+    }
+}
+
+/// Resolves host names, honouring the DNS related options.
+#[derive(Clone)]
+pub struct Resolver {
+    cache: Arc<Mutex<Cache>>,
+    // `None` caches forever, zero disables the cache
+    cache_timeout: Option<Duration>,
+    ip_resolve: c_long,
+    shuffle: bool,
+    connect_to: Vec<String>,
+}
+
+impl Resolver {
+    /// Applies `CURLOPT_RESOLVE` to the cache, like libcurl does at the start of a transfer.
+    pub fn new(options: &Options, cache: Arc<Mutex<Cache>>) -> Result<Self, Error> {
+        {
+            let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+
+            for entry in &options.resolve {
+                apply_resolve_entry(&mut cache, entry)?;
+            }
+        }
+
+        Ok(Self {
+            cache,
+            cache_timeout: options.dns_cache_timeout,
+            ip_resolve: options.ip_resolve,
+            shuffle: options.dns_shuffle_addresses,
+            connect_to: options.connect_to.clone(),
+        })
+    }
+
+    /// Where to actually connect to for `host` and `port` according to `CURLOPT_CONNECT_TO`.
+    pub fn connect_to(&self, host: &str, port: u16) -> (String, u16) {
+        for entry in &self.connect_to {
+            if let Some(target) = match_connect_to(entry, host, port) {
+                return target;
+            }
+        }
+
+        (host.to_owned(), port)
+    }
+
+    /// Resolves `host` on a helper thread, giving up after `timeout`.
+    pub fn resolve(&self, host: &str, port: u16, timeout: Duration) -> Result<Vec<SocketAddr>, Error> {
+        let mut addrs = self.lookup(host, port, timeout)?;
+
+        addrs.retain(|addr| match self.ip_resolve as u32 {
+            CURL_IPRESOLVE_V4 => addr.is_ipv4(),
+            CURL_IPRESOLVE_V6 => addr.is_ipv6(),
+            _ => true,
+        });
+
+        if addrs.is_empty() {
+            return Err(Error::new(CURLE_COULDNT_RESOLVE_HOST, format!("Could not resolve host: {}", host)));
+        }
+
+        if self.shuffle {
+            shuffle(&mut addrs);
+        }
+
+        Ok(addrs)
+    }
+
+    fn lookup(&self, host: &str, port: u16, timeout: Duration) -> Result<Vec<SocketAddr>, Error> {
+        // Literal addresses don't need resolving
+        if let Ok(ip) = unbracket(host).parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+
+        let caching = self.cache_timeout != Some(Duration::from_secs(0));
+
+        if let Some(addrs) = self.cache.lock().unwrap_or_else(|e| e.into_inner()).get(host, port, self.cache_timeout) {
+            return Ok(addrs);
+        }
+
+        let started = Instant::now();
+        let (sender, receiver) = mpsc::channel();
+        let name = host.to_owned();
+
+        thread::spawn(move || {
+            let addrs = (name.as_str(), port).to_socket_addrs().map(Iterator::collect::<Vec<_>>);
+            sender.send(addrs).ok();
+        });
+
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(ref addrs)) if addrs.is_empty() => {},
+            Ok(Ok(addrs)) => {
+                if caching {
+                    let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+                    cache.insert(host, port, addrs.clone(), Some(Instant::now()));
+                }
+
+                return Ok(addrs);
+            },
+            Ok(Err(_)) | Err(RecvTimeoutError::Disconnected) => {},
+            Err(RecvTimeoutError::Timeout) => return Err(Error::new(
+                CURLE_OPERATION_TIMEDOUT,
+                format!("Resolving timed out after {} milliseconds", started.elapsed().as_millis()),
+            )),
+        }
+
+        Err(Error::new(CURLE_COULDNT_RESOLVE_HOST, format!("Could not resolve host: {}", host)))
+    }
+}
+
+// "[+]HOST:PORT:ADDRESS[,ADDRESS]..." adds an entry, "-HOST:PORT" removes it.
+// IPv6 hosts and addresses may be in brackets.
+fn apply_resolve_entry(cache: &mut Cache, entry: &str) -> Result<(), Error> {
+    let invalid = || Error::new(CURLE_BAD_FUNCTION_ARGUMENT, format!("Couldn't parse CURLOPT_RESOLVE entry '{}'", entry));
+
+    let (removal, fields) = match entry.strip_prefix('-') {
+        Some(removal) => (true, removal),
+        None => (false, entry.trim_start_matches('+')),
+    };
+
+    let parts = split_host_port_fields(fields);
+    let (host, port) = match parts[..] {
+        [host, port, ..] => (host, port),
+        _ => return Err(invalid()),
+    };
+    let port_number = port.parse::<u16>().map_err(|_| invalid())?;
+
+    if unbracket(host).is_empty() {
+        return Err(invalid());
+    }
+
+    if removal {
+        cache.remove(unbracket(host), port_number);
+        return Ok(());
+    }
+
+    // The addresses are everything after the port, colons of unbracketed IPv6 addresses included
+    let addrs = fields.get(host.len() + port.len() + 2..).ok_or_else(invalid)?
+        .split(',')
+        .map(|addr| unbracket(addr.trim()))
+        .filter(|addr| !addr.is_empty())
+        .map(|addr| addr.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, port_number)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+
+    if addrs.is_empty() {
+        return Err(invalid());
+    }
+
+    cache.insert(unbracket(host), port_number, addrs, None);
+
+    Ok(())
+}
+
+fn unbracket(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+// "HOST:PORT:CONNECT-TO-HOST:CONNECT-TO-PORT", where empty fields match anything or keep the original
+fn match_connect_to(entry: &str, host: &str, port: u16) -> Option<(String, u16)> {
+    let mut fields = split_host_port_fields(entry).into_iter();
+    let entry_host = fields.next()?;
+    let entry_port = fields.next()?;
+    let to_host = fields.next()?;
+    let to_port = fields.next().unwrap_or_default();
+
+    let host_matches = entry_host.is_empty() || unbracket(entry_host).eq_ignore_ascii_case(unbracket(host));
+    let port_matches = entry_port.is_empty() || entry_port.parse() == Ok(port);
+
+    if !host_matches || !port_matches {
+        return None;
+    }
+
+    let to_host = match to_host.is_empty() {
+        true => host.to_owned(),
+        false => unbracket(to_host).to_owned(),
+    };
+    let to_port = to_port.parse().unwrap_or(port);
+
+    Some((to_host, to_port))
+}
+
+// Splits at colons, except inside the brackets of IPv6 addresses
+fn split_host_port_fields(entry: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut in_brackets = false;
+
+    for (i, c) in entry.char_indices() {
+        match c {
+            '[' => in_brackets = true,
+            ']' => in_brackets = false,
+            ':' if !in_brackets => {
+                fields.push(&entry[start..i]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+
+    fields.push(&entry[start..]);
+    fields
+}
+
+fn shuffle(addrs: &mut [SocketAddr]) {
+    for i in (1..addrs.len()).rev() {
+        let mut random = [0; 4];
+        if rand_bytes(&mut random).is_err() {
+            return;
+        }
+
+        let j = u32::from_ne_bytes(random) as usize % (i + 1);
+        addrs.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(cache: &mut Cache, host: &str, port: u16) -> Option<Vec<String>> {
+        cache.get(host, port, None).map(|addrs| addrs.iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn adds_and_removes_resolve_entries() {
+        let mut cache = Cache::default();
+
+        apply_resolve_entry(&mut cache, "example.com:80:127.0.0.1, 127.0.0.2").unwrap();
+        apply_resolve_entry(&mut cache, "+Other.com:443:[::1],::2,10.0.0.1").unwrap();
+        assert_eq!(cached(&mut cache, "example.com", 80), Some(vec!["127.0.0.1:80".into(), "127.0.0.2:80".into()]));
+        assert_eq!(cached(&mut cache, "other.com", 443), Some(vec!["[::1]:443".into(), "[::2]:443".into(), "10.0.0.1:443".into()]));
+        assert_eq!(cached(&mut cache, "example.com", 443), None);
+
+        apply_resolve_entry(&mut cache, "[::1]:443:[::1]").unwrap();
+        assert_eq!(cached(&mut cache, "::1", 443), Some(vec!["[::1]:443".into()]));
+
+        apply_resolve_entry(&mut cache, "-example.com:80").unwrap();
+        apply_resolve_entry(&mut cache, "-[::1]:443").unwrap();
+        assert_eq!(cached(&mut cache, "example.com", 80), None);
+        assert_eq!(cached(&mut cache, "::1", 443), None);
+
+        for entry in &["example.com:80", "example.com:http:127.0.0.1", ":80:127.0.0.1", "example.com:80:nowhere", "example.com:80:", "-example.com"] {
+            assert_eq!(apply_resolve_entry(&mut cache, entry).err().map(|e| e.code), Some(CURLE_BAD_FUNCTION_ARGUMENT), "{}", entry);
+        }
+    }
+
+    #[test]
+    fn matches_connect_to_entries() {
+        let target = |host: &str, port: u16| Some((host.to_owned(), port));
+
+        assert_eq!(match_connect_to("example.com:443:other.com:8443", "EXAMPLE.com", 443), target("other.com", 8443));
+        assert_eq!(match_connect_to("example.com:443:other.com:8443", "example.com", 80), None);
+        assert_eq!(match_connect_to("example.com:443:other.com:8443", "other.com", 443), None);
+
+        // Empty fields match any host or port, or keep the original one
+        assert_eq!(match_connect_to("::other.com:", "example.com", 80), target("other.com", 80));
+        assert_eq!(match_connect_to(":443::8443", "example.com", 443), target("example.com", 8443));
+        assert_eq!(match_connect_to("example.com:::", "example.com", 80), target("example.com", 80));
+
+        assert_eq!(match_connect_to("[::1]:443:[::2]:8443", "[::1]", 443), target("::2", 8443));
+        assert_eq!(match_connect_to("example.com:443", "example.com", 443), None);
+    }
+}
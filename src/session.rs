@@ -0,0 +1,162 @@
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::slice;
+use libc::*;
+use openssl::ssl::SslStream;
+use openssl::x509::X509;
+use url::Url;
+use crate::{CURL, Options, connect, tls};
+use crate::connect::Stream;
+use crate::error::Error;
+use crate::raw::CURLcode::{self, *};
+use crate::util::borrow_raw::*;
+
+/// A connection kept open by `CURLOPT_CONNECT_ONLY`.
+///
+/// The application speaks its own protocol over it with `curl_easy_send` and `curl_easy_recv`.
+pub enum Session {
+    Plain(Stream),
+    Tls(SslStream<Stream>),
+}
+
+impl Session {
+    /// Connects to the host of `url`, with TLS for the secure schemes.
+    pub fn open(url: &Url, options: &Options, settings: &connect::Settings) -> Result<Self, Error> {
+        let host = url.host_str().unwrap_or_default().trim_start_matches('[').trim_end_matches(']');
+        let port = url.port_or_known_default().ok_or_else(|| Error::new(
+            CURLE_UNSUPPORTED_PROTOCOL,
+            format!("Protocol \"{}\" not supported", url.scheme()),
+        ))?;
+
+        let stream = connect::open(host, port, settings)?;
+
+        let session = match url.scheme() {
+            "https" | "wss" | "ftps" | "imaps" | "pop3s" | "smtps" | "ldaps" => Session::Tls(tls::handshake(host, stream, options, false)?),
+            _ => Session::Plain(stream),
+        };
+
+        Ok(session)
+    }
+
+    fn stream(&self) -> &Stream {
+        match self {
+            Session::Plain(stream) => stream,
+            Session::Tls(stream) => stream.get_ref(),
+        }
+    }
+
+    pub fn addrs(&self) -> Option<(SocketAddr, SocketAddr)> {
+        self.stream().addrs()
+    }
+
+    pub fn peer_certificates(&self) -> Vec<X509> {
+        match self {
+            Session::Plain(_) => Vec::new(),
+            Session::Tls(stream) => tls::peer_chain(stream.ssl()),
+        }
+    }
+
+    /// Switches to the non-blocking mode `curl_easy_send` and `curl_easy_recv` work in.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.stream().set_nonblocking(nonblocking)
+    }
+}
+
+impl AsRawFd for Session {
+    fn as_raw_fd(&self) -> RawFd {
+        self.stream().as_raw_fd()
+    }
+}
+
+impl Read for Session {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Session::Plain(stream) => stream.read(buf),
+            Session::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Session {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Session::Plain(stream) => stream.write(buf),
+            Session::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Session::Plain(stream) => stream.flush(),
+            Session::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn curl_easy_send(
+    curl: *mut CURL,
+    buffer: *const c_void,
+    buflen: size_t,
+    n: *mut size_t,
+) -> CURLcode::Type {
+    curl.borrow_raw_mut(|curl| {
+        if n.is_null() || (buffer.is_null() && buflen > 0) {
+            return CURLE_BAD_FUNCTION_ARGUMENT;
+        }
+
+        *n = 0;
+
+        let buffer = match buflen {
+            0 => &[][..],
+            _ => slice::from_raw_parts(buffer as *const u8, buflen),
+        };
+
+        let result = match &mut curl.session {
+            Some(session) => session.write(buffer),
+            None => return curl.error(CURLE_UNSUPPORTED_PROTOCOL, "CONNECT_ONLY is required"),
+        };
+
+        match result {
+            Ok(len) => { *n = len; CURLE_OK },
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => CURLE_AGAIN,
+            Err(e) => curl.error(CURLE_SEND_ERROR, e.to_string()),
+        }
+    })
+    .unwrap_or(CURLE_BAD_FUNCTION_ARGUMENT)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn curl_easy_recv(
+    curl: *mut CURL,
+    buffer: *mut c_void,
+    buflen: size_t,
+    n: *mut size_t,
+) -> CURLcode::Type {
+    curl.borrow_raw_mut(|curl| {
+        if n.is_null() || (buffer.is_null() && buflen > 0) {
+            return CURLE_BAD_FUNCTION_ARGUMENT;
+        }
+
+        *n = 0;
+
+        let buffer = match buflen {
+            0 => &mut [][..],
+            _ => slice::from_raw_parts_mut(buffer as *mut u8, buflen),
+        };
+
+        let result = match &mut curl.session {
+            Some(session) => session.read(buffer),
+            None => return curl.error(CURLE_UNSUPPORTED_PROTOCOL, "CONNECT_ONLY is required"),
+        };
+
+        // Like libcurl, a closed connection is reported as zero bytes received
+        match result {
+            Ok(len) => { *n = len; CURLE_OK },
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => CURLE_AGAIN,
+            Err(e) => curl.error(CURLE_RECV_ERROR, e.to_string()),
+        }
+    })
+    .unwrap_or(CURLE_BAD_FUNCTION_ARGUMENT)
+}
@@ -0,0 +1,335 @@
+use chrono::{DateTime, Utc};
+use openssl::hash::{hash, MessageDigest};
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use hyper::Method;
+use url::Url;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use percent_encoding::percent_decode;
+use crate::Options;
+use crate::auth::Credentials;
+use crate::error::Error;
+use crate::util::hex;
+use crate::raw::CURLcode::*;
+use crate::rawx::CURLAUTH_AWS_SIGV4;
+
+/// The parsed `CURLOPT_AWS_SIGV4` value: `provider1[:provider2[:region[:service]]]`.
+pub struct Spec {
+    provider1: String,
+    provider2: String,
+    region: Option<String>,
+    service: Option<String>,
+}
+
+impl Spec {
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let mut parts = spec.splitn(4, ':').map(str::to_owned);
+        let provider1 = parts.next().filter(|provider| !provider.is_empty())
+            .ok_or_else(|| Error::new(CURLE_BAD_FUNCTION_ARGUMENT, "AWS_SIGV4: missing provider"))?;
+        let provider2 = parts.next().unwrap_or_else(|| provider1.clone());
+
+        Ok(Self {
+            provider1,
+            provider2,
+            region: parts.next(),
+            service: parts.next(),
+        })
+    }
+}
+
+/// Everything needed to sign one request.
+pub struct Request<'a> {
+    pub method: &'a Method,
+    pub url: &'a Url,
+    pub content_type: Option<&'a str>,
+    pub payload: &'a [u8],
+}
+
+/// Signs the request if `CURLOPT_HTTPAUTH` asks for it, with no headers to add otherwise.
+pub fn sign_if_enabled(options: &Options, credentials: Option<&Credentials>, request: &Request) -> Result<HeaderMap, Error> {
+    if options.http_auth & CURLAUTH_AWS_SIGV4 == 0 {
+        return Ok(HeaderMap::new());
+    }
+
+    match (&options.aws_sigv4, credentials) {
+        (Some(spec), Some(credentials)) => sign(spec, credentials, request, Utc::now()),
+        _ => Ok(HeaderMap::new()),
+    }
+}
+
+/// Computes the headers that sign the request, including the Authorization header.
+pub fn sign(spec: &Spec, credentials: &Credentials, request: &Request, now: DateTime<Utc>) -> Result<HeaderMap, Error> {
+    let host = request.url.host_str()
+        .ok_or_else(|| Error::new(CURLE_URL_MALFORMAT, "AWS_SIGV4: URL has no host"))?;
+    let host = match request.url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    };
+
+    // Like libcurl, fall back to deriving service and region from "service.region.example.com"
+    let mut labels = host.split('.');
+    let service = spec.service.clone()
+        .or_else(|| labels.next().map(str::to_owned))
+        .ok_or_else(|| Error::new(CURLE_URL_MALFORMAT, "AWS_SIGV4: unable to determine service"))?;
+    let region = spec.region.clone()
+        .or_else(|| labels.next().map(str::to_owned))
+        .ok_or_else(|| Error::new(CURLE_URL_MALFORMAT, "AWS_SIGV4: unable to determine region"))?;
+
+    let provider1_upper = spec.provider1.to_ascii_uppercase();
+    let provider1_lower = spec.provider1.to_ascii_lowercase();
+    let provider2_lower = spec.provider2.to_ascii_lowercase();
+    let provider2_title = title_case(&spec.provider2);
+
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    let payload_hash = sha256_hex(request.payload)?;
+
+    let date_header = format!("x-{}-date", provider2_lower);
+    let content_sha256_header = format!("x-{}-content-sha256", provider2_lower);
+
+    let mut signed = vec![
+        ("host".to_owned(), host.clone()),
+        (date_header.clone(), timestamp.clone()),
+    ];
+
+    if let Some(content_type) = request.content_type {
+        signed.push(("content-type".to_owned(), content_type.trim().to_owned()));
+    }
+
+    // S3 requires the payload hash to be sent along
+    let send_content_sha256 = service == "s3";
+    if send_content_sha256 {
+        signed.push((content_sha256_header.clone(), payload_hash.clone()));
+    }
+
+    signed.sort();
+
+    let canonical_headers: String = signed.iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value))
+        .collect();
+    let signed_headers = signed.iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        request.method,
+        canonical_path(request.url),
+        canonical_query(request.url),
+        canonical_headers,
+        signed_headers,
+        payload_hash,
+    );
+
+    let algorithm = format!("{}4-HMAC-SHA256", provider1_upper);
+    let scope = format!("{}/{}/{}/{}4_request", date, region, service, provider1_lower);
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        algorithm,
+        timestamp,
+        scope,
+        sha256_hex(canonical_request.as_bytes())?,
+    );
+
+    let secret = format!("{}4{}", provider1_upper, credentials.password);
+    let key = hmac(secret.as_bytes(), date.as_bytes())?;
+    let key = hmac(&key, region.as_bytes())?;
+    let key = hmac(&key, service.as_bytes())?;
+    let key = hmac(&key, format!("{}4_request", provider1_lower).as_bytes())?;
+    let signature = hex::encode(&hmac(&key, string_to_sign.as_bytes())?);
+
+    let authorization = format!(
+        "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+        algorithm, credentials.user, scope, signed_headers, signature,
+    );
+
+    let mut headers = HeaderMap::new();
+    insert(&mut headers, &format!("X-{}-Date", provider2_title), &timestamp)?;
+    if send_content_sha256 {
+        insert(&mut headers, &content_sha256_header, &payload_hash)?;
+    }
+    headers.insert(AUTHORIZATION, header_value(&authorization)?);
+
+    Ok(headers)
+}
+
+// Every path segment is re-encoded with the AWS rules
+fn canonical_path(url: &Url) -> String {
+    url.path().split('/')
+        .map(|segment| aws_encode(&percent_decode(segment.as_bytes()).collect::<Vec<u8>>()))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Query parameters are sorted and re-encoded with the AWS rules
+fn canonical_query(url: &Url) -> String {
+    let query = match url.query() {
+        Some(query) => query,
+        None => return String::new(),
+    };
+
+    let mut params = query.split('&')
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let mut param = param.splitn(2, '=');
+            let name = aws_encode(&decode_query(param.next().unwrap_or_default()));
+            let value = aws_encode(&decode_query(param.next().unwrap_or_default()));
+            (name, value)
+        })
+        .collect::<Vec<_>>();
+
+    params.sort();
+
+    params.iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn decode_query(component: &str) -> Vec<u8> {
+    percent_decode(component.replace('+', " ").as_bytes()).collect()
+}
+
+// Only unreserved characters are left as they are
+fn aws_encode(decoded: &[u8]) -> String {
+    decoded.iter().map(|&byte| match byte {
+        b'A' ..= b'Z' | b'a' ..= b'z' | b'0' ..= b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte),
+    })
+    .collect()
+}
+
+fn title_case(name: &str) -> String {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => String::new(),
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> Result<String, Error> {
+    hash(MessageDigest::sha256(), data)
+        .map(|digest| hex::encode(&digest))
+        .map_err(|e| Error::new(CURLE_FAILED_INIT, e.to_string()))
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let key = PKey::hmac(key)
+        .map_err(|e| Error::new(CURLE_FAILED_INIT, e.to_string()))?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)
+        .map_err(|e| Error::new(CURLE_FAILED_INIT, e.to_string()))?;
+
+    signer.update(data)
+        .and_then(|_| signer.sign_to_vec())
+        .map_err(|e| Error::new(CURLE_FAILED_INIT, e.to_string()))
+}
+
+fn insert(headers: &mut HeaderMap, name: &str, value: &str) -> Result<(), Error> {
+    let name = HeaderName::from_bytes(name.as_bytes())
+        .map_err(|e| Error::new(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()))?;
+    headers.insert(name, header_value(value)?);
+    Ok(())
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, Error> {
+    HeaderValue::from_str(value)
+        .map_err(|e| Error::new(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Requests from the AWS Signature Version 4 test suite, with their expected signatures
+    fn signature(method: Method, url: &str) -> String {
+        let spec = Spec::parse("aws:amz:us-east-1:service").unwrap();
+        let credentials = Credentials {
+            user: "AKIDEXAMPLE".to_owned(),
+            password: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_owned(),
+        };
+        let url = Url::parse(url).unwrap();
+        let request = Request { method: &method, url: &url, content_type: None, payload: b"" };
+        let now = "2015-08-30T12:36:00Z".parse().unwrap();
+
+        let headers = sign(&spec, &credentials, &request, now).unwrap();
+        assert_eq!(headers["x-amz-date"], "20150830T123600Z");
+
+        let authorization = headers[AUTHORIZATION].to_str().unwrap();
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, Signature=",
+        ));
+
+        authorization.rsplit('=').next().unwrap().to_owned()
+    }
+
+    #[test]
+    fn get_vanilla() {
+        assert_eq!(
+            signature(Method::GET, "https://example.amazonaws.com/"),
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31",
+        );
+    }
+
+    #[test]
+    fn get_vanilla_empty_query_key() {
+        assert_eq!(
+            signature(Method::GET, "https://example.amazonaws.com/?Param1=value1"),
+            "a67d582fa61cc504c4bae71f336f98b97f1ea3c7a6bfe1b6e45aec72011b9aeb",
+        );
+    }
+
+    #[test]
+    fn get_vanilla_query_order_key() {
+        assert_eq!(
+            signature(Method::GET, "https://example.amazonaws.com/?Param2=value2&Param1=value1"),
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500",
+        );
+    }
+
+    #[test]
+    fn get_vanilla_utf8_query() {
+        assert_eq!(
+            signature(Method::GET, "https://example.amazonaws.com/?ሴ=bar"),
+            "2cdec8eed098649ff3a119c94853b13c643bcf08f8b0a1d91e12c9027818dd04",
+        );
+    }
+
+    #[test]
+    fn get_space() {
+        assert_eq!(
+            signature(Method::GET, "https://example.amazonaws.com/example space/"),
+            "652487583200325589f1fba4c7e578f72c47cb61beeca81406b39ddec1366741",
+        );
+    }
+
+    #[test]
+    fn get_utf8() {
+        assert_eq!(
+            signature(Method::GET, "https://example.amazonaws.com/ሴ"),
+            "8318018e0b0f223aa2bbf98705b62bb787dc9c0e678f255a891fd03141be5d85",
+        );
+    }
+
+    #[test]
+    fn get_unreserved() {
+        assert_eq!(
+            signature(
+                Method::GET,
+                "https://example.amazonaws.com/-._~0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz",
+            ),
+            "07ef7494c76fa4850883e2b006601f940f8a34d404d0cfa977f52a65bbf5f24f",
+        );
+    }
+
+    #[test]
+    fn post_vanilla() {
+        assert_eq!(
+            signature(Method::POST, "https://example.amazonaws.com/"),
+            "5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b",
+        );
+    }
+}
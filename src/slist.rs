@@ -18,6 +18,10 @@ impl curl_slist {
         self.elements.push(value);
     }
 
+    pub fn elements(&self) -> &[String] {
+        &self.elements
+    }
+
     pub fn into_raw(self: Box<Self>) -> *mut Self {
         Box::into_raw(self)
     }
@@ -27,12 +31,12 @@ impl curl_slist {
             return;
         }
 
-        Box::from_raw(this);
+        drop(Box::from_raw(this));
     }
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_slist_append(
+pub unsafe extern "C" fn curl_slist_append(
     this: *mut curl_slist,
     value: *const c_char
 ) -> *mut curl_slist
@@ -55,6 +59,6 @@ pub unsafe extern fn curl_slist_append(
 }
 
 #[no_mangle]
-pub unsafe extern fn curl_slist_free_all(this: *mut curl_slist) {
+pub unsafe extern "C" fn curl_slist_free_all(this: *mut curl_slist) {
     curl_slist::from_raw(this);
 }
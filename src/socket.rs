@@ -0,0 +1,463 @@
+use std::error;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem::{self, ManuallyDrop};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, TcpStream, ToSocketAddrs};
+use std::ops::Deref;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
+use std::ptr::null_mut;
+use std::time::Duration;
+use libc::*;
+use crate::Options;
+use crate::raw::{
+    curl_sockaddr,
+    curl_socket_t,
+    curlsocktype::{self, CURLSOCKTYPE_IPCXN},
+    CURL_SOCKET_BAD,
+    CURL_SOCKOPT_ALREADY_CONNECTED,
+    CURL_SOCKOPT_OK,
+};
+use crate::rawx::CURL_PREREQFUNC_OK;
+
+pub type OpenSocketFunction = unsafe extern "C" fn(
+    clientp: *mut c_void,
+    purpose: curlsocktype::Type,
+    address: *mut curl_sockaddr,
+) -> curl_socket_t;
+
+pub type CloseSocketFunction = unsafe extern "C" fn(
+    clientp: *mut c_void,
+    item: curl_socket_t,
+) -> c_int;
+
+pub type SockoptFunction = unsafe extern "C" fn(
+    clientp: *mut c_void,
+    curlfd: curl_socket_t,
+    purpose: curlsocktype::Type,
+) -> c_int;
+
+pub type PrereqFunction = unsafe extern "C" fn(
+    clientp: *mut c_void,
+    conn_primary_ip: *mut c_char,
+    conn_local_ip: *mut c_char,
+    conn_primary_port: c_int,
+    conn_local_port: c_int,
+) -> c_int;
+
+/// Options applied to every TCP socket before it connects.
+#[derive(Clone)]
+pub struct Tuning {
+    // The address to bind to for IPv4 and IPv6 connections
+    pub local_v4: Option<IpAddr>,
+    pub local_v6: Option<IpAddr>,
+    pub local_port: u16,
+    pub local_port_range: u16,
+    pub nodelay: bool,
+    pub keepalive: Option<(Duration, Duration)>,
+}
+
+/// The socket callbacks of a handle.
+///
+/// Like the handle itself they stay on the thread that performs the transfer,
+/// which is the only one calling them.
+#[derive(Clone)]
+pub struct Callbacks {
+    open_socket: Option<(OpenSocketFunction, *mut c_void)>,
+    close_socket: Option<(CloseSocketFunction, *mut c_void)>,
+    sockopt: Option<(SockoptFunction, *mut c_void)>,
+    prereq: Option<(PrereqFunction, *mut c_void)>,
+}
+
+impl Callbacks {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            open_socket: options.open_socket_function.map(|f| (f, options.open_socket_data)),
+            close_socket: options.close_socket_function.map(|f| (f, options.close_socket_data)),
+            sockopt: options.sockopt_function.map(|f| (f, options.sockopt_data)),
+            prereq: options.prereq_function.map(|f| (f, options.prereq_data)),
+        }
+    }
+
+    /// Asks `CURLOPT_PREREQFUNCTION` whether a request may be sent over a new connection.
+    pub fn allows_request(&self, addrs: Option<(SocketAddr, SocketAddr)>) -> bool {
+        let (function, data) = match self.prereq {
+            Some(prereq) => prereq,
+            None => return true,
+        };
+
+        let ip = |addr: Option<SocketAddr>| addr.map(|addr| addr.ip().to_string()).unwrap_or_default();
+        let port = |addr: Option<SocketAddr>| addr.map_or(0, |addr| addr.port() as c_int);
+        let local = addrs.map(|(local, _)| local);
+        let peer = addrs.map(|(_, peer)| peer);
+        let primary_ip = CString::new(ip(peer)).unwrap_or_default();
+        let local_ip = CString::new(ip(local)).unwrap_or_default();
+
+        let result = unsafe {
+            function(data, primary_ip.as_ptr() as *mut c_char, local_ip.as_ptr() as *mut c_char, port(peer), port(local))
+        };
+
+        result == CURL_PREREQFUNC_OK
+    }
+
+    // Opens the socket through `CURLOPT_OPENSOCKETFUNCTION`, which may change the address
+    fn open(&self, addr: &SocketAddr) -> io::Result<(curl_socket_t, SocketAddr)> {
+        let (function, data) = match self.open_socket {
+            Some(open_socket) => open_socket,
+            None => return open(addr).map(|fd| (fd, *addr)),
+        };
+
+        let (storage, len) = raw_addr(addr);
+        let mut address = SockaddrEx {
+            family: c_int::from(storage.ss_family),
+            socktype: SOCK_STREAM,
+            protocol: IPPROTO_TCP,
+            addrlen: len,
+            addr: storage,
+        };
+
+        let fd = unsafe { function(data, CURLSOCKTYPE_IPCXN, &mut address as *mut SockaddrEx as *mut curl_sockaddr) };
+
+        if fd == CURL_SOCKET_BAD {
+            return Err(io::Error::other("Could not open socket through the callback"));
+        }
+
+        Ok((fd, socket_addr(&address.addr).unwrap_or(*addr)))
+    }
+
+    // Returns whether the callback already connected the socket
+    fn set_options(&self, fd: curl_socket_t) -> io::Result<bool> {
+        let (function, data) = match self.sockopt {
+            Some(sockopt) => sockopt,
+            None => return Ok(false),
+        };
+
+        let result = unsafe { function(data, fd, CURLSOCKTYPE_IPCXN) };
+
+        match result as u32 {
+            CURL_SOCKOPT_OK => Ok(false),
+            CURL_SOCKOPT_ALREADY_CONNECTED => Ok(true),
+            _ => Err(io::Error::other(Aborted("setsockopt callback returned error"))),
+        }
+    }
+
+    fn close(&self, stream: TcpStream) {
+        let (function, data) = match self.close_socket {
+            Some(close_socket) => close_socket,
+            None => return,
+        };
+
+        let fd = stream.into_raw_fd();
+        unsafe { function(data, fd) };
+    }
+}
+
+// What libcurl actually passes as `curl_sockaddr`, with room for IPv6 addresses
+#[repr(C)]
+struct SockaddrEx {
+    family: c_int,
+    socktype: c_int,
+    protocol: c_int,
+    addrlen: c_uint,
+    addr: sockaddr_storage,
+}
+
+/// A connection failure that has to end the transfer instead of trying the next address.
+#[derive(Debug)]
+struct Aborted(&'static str);
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl error::Error for Aborted {}
+
+pub fn is_aborted(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|e| e.is::<Aborted>())
+}
+
+/// A TCP socket, closed through `CURLOPT_CLOSESOCKETFUNCTION` if one is set.
+pub struct Socket {
+    stream: ManuallyDrop<TcpStream>,
+    callbacks: Callbacks,
+}
+
+impl Deref for Socket {
+    type Target = TcpStream;
+
+    fn deref(&self) -> &TcpStream {
+        &self.stream
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self.stream).read(buf)
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self.stream).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.stream).flush()
+    }
+}
+
+impl fmt::Debug for Socket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Socket").field(&*self.stream).finish()
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let stream = unsafe { ManuallyDrop::take(&mut self.stream) };
+
+        match self.callbacks.close_socket {
+            Some(_) => self.callbacks.close(stream),
+            None => drop(stream),
+        }
+    }
+}
+
+/// Starts connecting a new socket to `addr`, returning whether it is connected already.
+///
+/// The steps and callbacks follow libcurl: open the socket, tune it,
+/// call `CURLOPT_SOCKOPTFUNCTION`, bind the local end and connect.
+/// A pending connection is completed by `finish`.
+pub fn connect(addr: &SocketAddr, tuning: &Tuning, callbacks: &Callbacks) -> io::Result<(Socket, bool)> {
+    let (fd, addr) = callbacks.open(addr)?;
+
+    // From here on the socket owns the fd and closes it on errors
+    let socket = Socket {
+        stream: ManuallyDrop::new(unsafe { TcpStream::from_raw_fd(fd) }),
+        callbacks: callbacks.clone(),
+    };
+
+    tune(&socket, tuning)?;
+
+    if callbacks.set_options(fd)? {
+        return Ok((socket, true));
+    }
+
+    bind_local(&socket, &addr, tuning)?;
+    let connected = connect_socket(&socket, &addr)?;
+
+    Ok((socket, connected))
+}
+
+/// Completes a connection once its socket turned writable.
+pub fn finish(socket: &Socket) -> io::Result<()> {
+    if let Some(e) = socket.take_error()? {
+        return Err(e);
+    }
+
+    socket.set_nonblocking(false)
+}
+
+fn open(addr: &SocketAddr) -> io::Result<curl_socket_t> {
+    let family = match addr {
+        SocketAddr::V4(_) => AF_INET,
+        SocketAddr::V6(_) => AF_INET6,
+    };
+
+    match unsafe { socket(family, SOCK_STREAM, IPPROTO_TCP) } {
+        fd if fd < 0 => Err(io::Error::last_os_error()),
+        fd => Ok(fd),
+    }
+}
+
+fn tune(stream: &TcpStream, tuning: &Tuning) -> io::Result<()> {
+    stream.set_nodelay(tuning.nodelay)?;
+
+    if let Some((idle, interval)) = tuning.keepalive {
+        set_int_option(stream, SOL_SOCKET, SO_KEEPALIVE, 1)?;
+        set_int_option(stream, IPPROTO_TCP, TCP_KEEPIDLE, idle.as_secs() as c_int)?;
+        set_int_option(stream, IPPROTO_TCP, TCP_KEEPINTVL, interval.as_secs() as c_int)?;
+    }
+
+    Ok(())
+}
+
+fn bind_local(stream: &TcpStream, addr: &SocketAddr, tuning: &Tuning) -> io::Result<()> {
+    let local_ip = match addr {
+        SocketAddr::V4(_) => tuning.local_v4,
+        SocketAddr::V6(_) => tuning.local_v6,
+    };
+
+    if local_ip.is_none() && tuning.local_port == 0 {
+        return Ok(());
+    }
+
+    let ip = local_ip.unwrap_or_else(|| match addr {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    });
+
+    bind(stream, ip, tuning.local_port, tuning.local_port_range)
+}
+
+// Connects without blocking, returning whether the connection is already established
+fn connect_socket(stream: &TcpStream, addr: &SocketAddr) -> io::Result<bool> {
+    let (raw_addr, len) = raw_addr(addr);
+
+    stream.set_nonblocking(true)?;
+
+    if unsafe { libc::connect(stream.as_raw_fd(), &raw_addr as *const _ as *const sockaddr, len) } == 0 {
+        stream.set_nonblocking(false)?;
+        return Ok(true);
+    }
+
+    match io::Error::last_os_error() {
+        e if e.raw_os_error() == Some(EINPROGRESS) => Ok(false),
+        e => Err(e),
+    }
+}
+
+/// The C representation of an address.
+pub fn raw_addr(addr: &SocketAddr) -> (sockaddr_storage, socklen_t) {
+    unsafe {
+        let mut storage: sockaddr_storage = mem::zeroed();
+
+        let len = match addr {
+            SocketAddr::V4(addr) => {
+                let raw = &mut *(&mut storage as *mut sockaddr_storage as *mut sockaddr_in);
+                raw.sin_family = AF_INET as sa_family_t;
+                raw.sin_port = addr.port().to_be();
+                raw.sin_addr = in_addr { s_addr: u32::from_ne_bytes(addr.ip().octets()) };
+                mem::size_of::<sockaddr_in>()
+            },
+            SocketAddr::V6(addr) => {
+                let raw = &mut *(&mut storage as *mut sockaddr_storage as *mut sockaddr_in6);
+                raw.sin6_family = AF_INET6 as sa_family_t;
+                raw.sin6_port = addr.port().to_be();
+                raw.sin6_flowinfo = addr.flowinfo();
+                raw.sin6_addr.s6_addr = addr.ip().octets();
+                raw.sin6_scope_id = addr.scope_id();
+                mem::size_of::<sockaddr_in6>()
+            },
+        };
+
+        (storage, len as socklen_t)
+    }
+}
+
+pub fn socket_addr(storage: &sockaddr_storage) -> Option<SocketAddr> {
+    unsafe {
+        match c_int::from(storage.ss_family) {
+            AF_INET => {
+                let raw = &*(storage as *const sockaddr_storage as *const sockaddr_in);
+                let ip = Ipv4Addr::from(raw.sin_addr.s_addr.to_ne_bytes());
+                Some(SocketAddrV4::new(ip, u16::from_be(raw.sin_port)).into())
+            },
+            AF_INET6 => {
+                let raw = &*(storage as *const sockaddr_storage as *const sockaddr_in6);
+                let ip = Ipv6Addr::from(raw.sin6_addr.s6_addr);
+                Some(SocketAddrV6::new(ip, u16::from_be(raw.sin6_port), raw.sin6_flowinfo, raw.sin6_scope_id).into())
+            },
+            _ => None,
+        }
+    }
+}
+
+/// The local address `CURLOPT_INTERFACE` asks to bind to for the given address family.
+///
+/// Like libcurl, "if!" only accepts interface names, "host!" only host names or addresses,
+/// and anything else is tried as an interface first.
+pub fn interface_addr(interface: &str, ipv6: bool) -> Option<IpAddr> {
+    if let Some(name) = interface.strip_prefix("if!") {
+        return interface_ip(name, ipv6);
+    }
+
+    if let Some(host) = interface.strip_prefix("host!") {
+        return host_ip(host, ipv6);
+    }
+
+    interface_ip(interface, ipv6).or_else(|| host_ip(interface, ipv6))
+}
+
+fn interface_ip(name: &str, ipv6: bool) -> Option<IpAddr> {
+    let mut found = None;
+
+    unsafe {
+        let mut addrs = null_mut();
+        if getifaddrs(&mut addrs) != 0 {
+            return None;
+        }
+
+        let mut current = addrs;
+
+        while let Some(ifaddr) = current.as_ref() {
+            current = ifaddr.ifa_next;
+
+            if ifaddr.ifa_addr.is_null() || CStr::from_ptr(ifaddr.ifa_name).to_bytes() != name.as_bytes() {
+                continue;
+            }
+
+            let ip = match c_int::from((*ifaddr.ifa_addr).sa_family) {
+                AF_INET if !ipv6 => {
+                    let raw = &*(ifaddr.ifa_addr as *const sockaddr_in);
+                    IpAddr::from(raw.sin_addr.s_addr.to_ne_bytes())
+                },
+                AF_INET6 if ipv6 => {
+                    let raw = &*(ifaddr.ifa_addr as *const sockaddr_in6);
+                    IpAddr::from(raw.sin6_addr.s6_addr)
+                },
+                _ => continue,
+            };
+
+            found = Some(ip);
+            break;
+        }
+
+        freeifaddrs(addrs);
+    }
+
+    found
+}
+
+fn host_ip(host: &str, ipv6: bool) -> Option<IpAddr> {
+    (host, 0).to_socket_addrs().ok()?
+        .map(|addr| addr.ip())
+        .find(|ip| ip.is_ipv6() == ipv6)
+}
+
+// Tries every port of the range until one is free
+fn bind(stream: &TcpStream, ip: IpAddr, port: u16, range: u16) -> io::Result<()> {
+    let ports = port as u32 .. port as u32 + range.max(1) as u32;
+    let mut last_error = None;
+
+    for port in ports.filter(|&port| port <= u16::MAX as u32) {
+        let (raw_addr, len) = raw_addr(&SocketAddr::new(ip, port as u16));
+
+        if unsafe { libc::bind(stream.as_raw_fd(), &raw_addr as *const _ as *const sockaddr, len) } == 0 {
+            return Ok(());
+        }
+
+        last_error = Some(io::Error::last_os_error());
+    }
+
+    Err(last_error.unwrap_or_else(|| io::Error::new(io::ErrorKind::AddrNotAvailable, "no local port available")))
+}
+
+fn set_int_option(stream: &TcpStream, level: c_int, name: c_int, value: c_int) -> io::Result<()> {
+    let ret = unsafe {
+        setsockopt(
+            stream.as_raw_fd(),
+            level,
+            name,
+            &value as *const c_int as *const c_void,
+            mem::size_of::<c_int>() as socklen_t,
+        )
+    };
+
+    match ret {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
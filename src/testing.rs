@@ -0,0 +1,379 @@
+//! Local servers and certificates for the tests that run whole transfers.
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::ptr::null_mut;
+use std::slice;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use futures::{future, Future};
+use hyper::{Body, Request, Response};
+use hyper::service::service_fn_ok;
+use libc::*;
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslMethod, SslVerifyMode};
+use openssl::x509::{X509, X509Name};
+use openssl::x509::extension::{BasicConstraints, SubjectAlternativeName};
+use tokio::runtime::current_thread::Runtime;
+use crate::CURL;
+use crate::raw::CURLcode;
+use crate::tls::Source;
+
+static CA: OnceLock<(PKey<Private>, X509)> = OnceLock::new();
+
+/// The test CA, created on first use. Transfers trust it through `CURLOPT_CAINFO_BLOB`.
+fn ca() -> &'static (PKey<Private>, X509) {
+    CA.get_or_init(|| {
+        let key = generate_key();
+        let cert = build_cert("recurl test CA", &key, None, None);
+
+        (key, cert)
+    })
+}
+
+/// The test CA as PEM.
+pub fn ca_pem() -> Vec<u8> {
+    ca().1.to_pem().unwrap()
+}
+
+/// A directory of its own under the system temp dir, removed with everything in it when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let name = format!("recurl-tests-{}-{}", process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+        let path = env::temp_dir().join(name);
+        fs::create_dir_all(&path).unwrap();
+
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// The path of `name` in the directory, as a string for the options.
+    pub fn file(&self, name: &str) -> String {
+        self.0.join(name).to_str().unwrap().to_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+fn generate_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+fn build_cert(name: &str, key: &PKey<Private>, issuer: Option<(&PKey<Private>, &X509)>, alt_name: Option<&str>) -> X509 {
+    let mut subject = X509Name::builder().unwrap();
+    subject.append_entry_by_nid(Nid::COMMONNAME, name).unwrap();
+    let subject = subject.build();
+
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+
+    let mut cert = X509::builder().unwrap();
+    cert.set_version(2).unwrap();
+    cert.set_serial_number(&serial.to_asn1_integer().unwrap()).unwrap();
+    cert.set_subject_name(&subject).unwrap();
+    cert.set_pubkey(key).unwrap();
+    cert.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+    cert.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+
+    let (signer, issuer_name) = match issuer {
+        Some((issuer_key, issuer_cert)) => (issuer_key, issuer_cert.subject_name()),
+        None => {
+            cert.append_extension(BasicConstraints::new().critical().ca().build().unwrap()).unwrap();
+            (key, subject.as_ref())
+        },
+    };
+    cert.set_issuer_name(issuer_name).unwrap();
+
+    if let Some(alt_name) = alt_name {
+        let context = cert.x509v3_context(issuer.map(|(_, issuer_cert)| issuer_cert.as_ref()), None);
+        let alt_name = SubjectAlternativeName::new().ip(alt_name).build(&context).unwrap();
+        cert.append_extension(alt_name).unwrap();
+    }
+
+    cert.sign(signer, MessageDigest::sha256()).unwrap();
+    cert.build()
+}
+
+/// A fresh key and a certificate for it, issued by the test CA for 127.0.0.1.
+pub fn issue(name: &str) -> (PKey<Private>, X509) {
+    let (ca_key, ca_cert) = ca();
+    let key = generate_key();
+    let cert = build_cert(name, &key, Some((ca_key, ca_cert)), Some("127.0.0.1"));
+
+    (key, cert)
+}
+
+/// An acceptor with a freshly issued server certificate.
+pub fn acceptor() -> (SslAcceptorBuilder, X509) {
+    let (key, cert) = issue("127.0.0.1");
+    let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+    acceptor.set_private_key(&key).unwrap();
+    acceptor.set_certificate(&cert).unwrap();
+
+    (acceptor, cert)
+}
+
+/// Makes the server ask for a client certificate issued by the test CA and refuse connections without one.
+pub fn require_client_cert(acceptor: &mut SslAcceptorBuilder) {
+    let (_, ca_cert) = ca();
+    acceptor.cert_store_mut().add_cert(ca_cert.clone()).unwrap();
+    acceptor.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+}
+
+type Respond = dyn Fn(&[u8]) -> Vec<u8> + Send + Sync;
+
+/// Answers every request on 127.0.0.1 with what `respond` makes of it, then closes the connection.
+pub struct Server {
+    addr: SocketAddr,
+    scheme: &'static str,
+    requests: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl Server {
+    pub fn http(respond: impl Fn(&[u8]) -> Vec<u8> + Send + Sync + 'static) -> Self {
+        Self::start("http", None, Arc::new(respond))
+    }
+
+    pub fn https(acceptor: SslAcceptor, respond: impl Fn(&[u8]) -> Vec<u8> + Send + Sync + 'static) -> Self {
+        Self::start("https", Some(acceptor), Arc::new(respond))
+    }
+
+    /// Answers on the Unix socket at `path`, with the URLs still pointing to 127.0.0.1.
+    pub fn unix(path: &Path, respond: impl Fn(&[u8]) -> Vec<u8> + Send + Sync + 'static) -> Self {
+        let listener = UnixListener::bind(path).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                answer(&mut stream, &respond, &recorded).ok();
+            }
+        });
+
+        Self {
+            addr: SocketAddr::from(([127, 0, 0, 1], 80)),
+            scheme: "http",
+            requests,
+        }
+    }
+
+    /// Waits `delay` after every request, sends `sent` and then nothing until the client gives up.
+    pub fn stalling(delay: Duration, sent: &'static [u8]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let recorded = recorded.clone();
+
+                thread::spawn(move || {
+                    if let Ok(request) = read_request(&mut stream) {
+                        recorded.lock().unwrap().push(request);
+                    }
+
+                    thread::sleep(delay);
+                    stream.write_all(sent).ok();
+                    io::copy(&mut stream, &mut io::sink()).ok();
+                });
+            }
+        });
+
+        Self {
+            addr,
+            scheme: "http",
+            requests,
+        }
+    }
+
+    /// Speaks HTTP/2 without TLS, with prior knowledge, answering every request with `body`.
+    ///
+    /// The requests are recorded as their method, path and version.
+    pub fn h2c(body: &'static str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        thread::spawn(move || {
+            let server = future::lazy(move || {
+                hyper::Server::from_tcp(listener).unwrap()
+                    .http2_only(true)
+                    .serve(move || {
+                        let recorded = recorded.clone();
+                        service_fn_ok(move |request: Request<Body>| {
+                            let line = format!("{} {} {:?}", request.method(), request.uri(), request.version());
+                            recorded.lock().unwrap().push(line.into_bytes());
+                            Response::new(Body::from(body))
+                        })
+                    })
+                    .map_err(|_| ())
+            });
+
+            Runtime::new().unwrap().block_on(server).ok();
+        });
+
+        Self {
+            addr,
+            scheme: "http",
+            requests,
+        }
+    }
+
+    fn start(scheme: &'static str, acceptor: Option<SslAcceptor>, respond: Arc<Respond>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        // The thread is left blocked in accept once the test is done
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let acceptor = acceptor.clone();
+                let respond = respond.clone();
+                let recorded = recorded.clone();
+
+                thread::spawn(move || {
+                    let result = match acceptor {
+                        Some(acceptor) => match acceptor.accept(stream) {
+                            Ok(mut stream) => {
+                                let result = answer(&mut stream, &*respond, &recorded);
+                                stream.shutdown().ok();
+                                result
+                            },
+                            Err(_) => Ok(()),
+                        },
+                        None => answer(&mut &stream, &*respond, &recorded),
+                    };
+                    result.ok();
+                });
+            }
+        });
+
+        Self {
+            addr,
+            scheme,
+            requests,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}://{}{}", self.scheme, self.addr, path)
+    }
+
+    /// A handle for `path` on the server that trusts the test CA.
+    pub fn handle(&self, path: &str) -> Box<CURL> {
+        let mut curl = CURL::init();
+        curl.options.url = Some(self.url(path));
+        curl.options.ca_info = Some(Source::Blob(ca_pem()));
+        curl
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// The requests received so far, head and body.
+    pub fn requests(&self) -> Vec<Vec<u8>> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn answer(stream: &mut (impl Read + Write), respond: &Respond, recorded: &Mutex<Vec<Vec<u8>>>) -> io::Result<()> {
+    let request = read_request(stream)?;
+    let response = respond(&request);
+    recorded.lock().unwrap().push(request);
+    stream.write_all(&response)?;
+    stream.flush()
+}
+
+fn read_request(stream: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut byte = [0];
+
+    while !request.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        request.push(byte[0]);
+    }
+
+    let head = String::from_utf8_lossy(&request).to_ascii_lowercase();
+    let length = head.lines()
+        .filter_map(|line| line.strip_prefix("content-length:"))
+        .find_map(|length| length.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body)?;
+    request.extend(body);
+
+    Ok(request)
+}
+
+/// A complete response with `body`, which closes the connection.
+pub fn response(status: &str, headers: &[&str], body: &str) -> Vec<u8> {
+    let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\nContent-Length: {}\r\n", status, body.len());
+
+    for header in headers {
+        response.push_str(header);
+        response.push_str("\r\n");
+    }
+
+    response.push_str("\r\n");
+    response.push_str(body);
+    response.into_bytes()
+}
+
+/// The request line of `request`, without the line break.
+pub fn request_line(request: &[u8]) -> String {
+    let request = String::from_utf8_lossy(request);
+    request.lines().next().unwrap_or_default().to_owned()
+}
+
+unsafe extern "C" fn collect(ptr: *const c_char, size: size_t, nitems: size_t, userdata: *mut c_void) -> size_t {
+    let body = &mut *(userdata as *mut Vec<u8>);
+    body.extend_from_slice(slice::from_raw_parts(ptr as *const u8, size * nitems));
+    size * nitems
+}
+
+/// Runs the transfer, collecting the body instead of writing it to stdout.
+pub fn perform(curl: &mut CURL) -> (CURLcode::Type, Vec<u8>) {
+    let mut body = Vec::new();
+    curl.options.write_function = collect;
+    curl.options.write_data = &mut body as *mut Vec<u8> as *mut c_void;
+
+    let code = curl.perform();
+    curl.options.write_data = null_mut();
+
+    (code, body)
+}
@@ -0,0 +1,219 @@
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+use crate::Options;
+
+// Length of the window the current transfer speed is averaged over
+const SPEED_WINDOW: Duration = Duration::from_secs(5);
+
+// The values libcurl uses when these options are unset or set to 0
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(300);
+pub const DEFAULT_HAPPY_EYEBALLS_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// The deadline of the whole transfer set by `CURLOPT_TIMEOUT(_MS)`.
+#[derive(Copy, Clone)]
+pub struct Deadline {
+    started: Instant,
+    timeout: Option<Duration>,
+}
+
+impl Deadline {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            started: Instant::now(),
+            timeout,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// The time left until the deadline, if there is one.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.timeout.map(|timeout| timeout.checked_sub(self.elapsed()).unwrap_or_default())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.timeout.is_some_and(|timeout| self.elapsed() >= timeout)
+    }
+
+    /// The time left for connecting, which includes resolving the name.
+    ///
+    /// Like libcurl, this is the connect timeout capped by what's left of the whole transfer.
+    pub fn connect_timeout(&self, connect_timeout: Duration) -> Duration {
+        match self.remaining() {
+            Some(remaining) => connect_timeout.min(remaining),
+            None => connect_timeout,
+        }
+    }
+
+    pub fn message(&self, received: u64, expected: Option<u64>) -> String {
+        let elapsed = self.elapsed().as_millis();
+
+        match expected {
+            Some(expected) => format!(
+                "Operation timed out after {} milliseconds with {} out of {} bytes received",
+                elapsed, received, expected,
+            ),
+            None => format!(
+                "Operation timed out after {} milliseconds with {} bytes received",
+                elapsed, received,
+            ),
+        }
+    }
+}
+
+/// How long a single read or write may block.
+///
+/// That's what is left of the transfer, and with a low speed limit at most
+/// `CURLOPT_LOW_SPEED_TIME`, so a stalled connection is noticed in time.
+#[derive(Copy, Clone)]
+pub struct OperationTimeout {
+    deadline: Deadline,
+    low_speed_time: Option<Duration>,
+}
+
+impl OperationTimeout {
+    pub fn new(options: &Options, deadline: Deadline) -> Self {
+        Self {
+            deadline,
+            low_speed_time: (options.low_speed_limit > 0).then_some(options.low_speed_time),
+        }
+    }
+
+    pub fn get(&self) -> Option<Duration> {
+        let remaining = self.deadline.remaining();
+
+        match (remaining, self.low_speed_time) {
+            (Some(remaining), Some(low_speed_time)) => Some(min(remaining, low_speed_time)),
+            (remaining, low_speed_time) => remaining.or(low_speed_time),
+        }
+    }
+}
+
+/// Aborts transfers that stay below `CURLOPT_LOW_SPEED_LIMIT`
+/// for longer than `CURLOPT_LOW_SPEED_TIME`.
+pub struct SpeedCheck {
+    limit: u64,
+    time: Duration,
+    samples: VecDeque<(Instant, u64)>,
+    total: u64,
+    slow_since: Option<Instant>,
+}
+
+impl SpeedCheck {
+    pub fn new(options: &Options) -> Self {
+        let mut samples = VecDeque::new();
+        samples.push_back((Instant::now(), 0));
+
+        Self {
+            limit: options.low_speed_limit,
+            time: options.low_speed_time,
+            samples,
+            total: 0,
+            slow_since: None,
+        }
+    }
+
+    fn update(&mut self, bytes: u64) -> Result<(), String> {
+        if self.limit == 0 || self.time == Duration::from_secs(0) {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        self.total += bytes;
+        self.samples.push_back((now, self.total));
+
+        while self.samples.len() > 2 && now.duration_since(self.samples[1].0) >= SPEED_WINDOW {
+            self.samples.pop_front();
+        }
+
+        let (oldest_time, oldest_total) = self.samples[0];
+        let window = now.duration_since(oldest_time);
+
+        // Too early to tell
+        if window < Duration::from_secs(1) {
+            return Ok(());
+        }
+
+        let speed = (self.total - oldest_total) as f64 / window.as_secs_f64();
+
+        if speed >= self.limit as f64 {
+            self.slow_since = None;
+            return Ok(());
+        }
+
+        let slow_since = *self.slow_since.get_or_insert(oldest_time);
+
+        if now.duration_since(slow_since) >= self.time {
+            return Err(format!(
+                "Operation too slow. Less than {} bytes/sec transferred the last {} seconds",
+                self.limit,
+                self.time.as_secs(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Enforces the deadline and the low speed limit while the body is read.
+pub struct Reader<R> {
+    inner: R,
+    deadline: Deadline,
+    speed_check: SpeedCheck,
+    received: u64,
+    expected: Option<u64>,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(inner: R, deadline: Deadline, speed_check: SpeedCheck, expected: Option<u64>) -> Self {
+        Self {
+            inner,
+            deadline,
+            speed_check,
+            received: 0,
+            expected,
+        }
+    }
+}
+
+impl<R: Read> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.deadline.is_expired() {
+            return Err(timed_out(self.deadline.message(self.received, self.expected)));
+        }
+
+        let len = match self.inner.read(buf) {
+            Ok(len) => len,
+            Err(ref e) if is_timeout(e) && self.deadline.is_expired() => {
+                return Err(timed_out(self.deadline.message(self.received, self.expected)));
+            },
+            Err(ref e) if is_timeout(e) => {
+                let message = self.speed_check.update(0)
+                    .err()
+                    .unwrap_or_else(|| format!("Operation timed out after {} milliseconds", self.deadline.elapsed().as_millis()));
+                return Err(timed_out(message));
+            },
+            Err(e) => return Err(e),
+        };
+
+        self.received += len as u64;
+
+        if let Err(message) = self.speed_check.update(len as u64) {
+            return Err(timed_out(message));
+        }
+
+        Ok(len)
+    }
+}
+
+fn timed_out(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, message)
+}
+
+pub fn is_timeout(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::TimedOut || e.kind() == io::ErrorKind::WouldBlock
+}
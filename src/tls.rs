@@ -0,0 +1,402 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{PKey, Private};
+use openssl::sha::sha256;
+use openssl::ssl::{HandshakeError, SslConnector, SslFiletype, SslMethod, SslOptions, SslRef, SslStream, SslVersion};
+use openssl::x509::{X509, X509VerifyResult};
+use openssl::x509::store::{X509Lookup, X509Store, X509StoreBuilder};
+use openssl::x509::verify::X509VerifyFlags;
+use libc::*;
+use crate::Options;
+use crate::error::Error;
+use crate::raw::{
+    CURLSSLOPT_ALLOW_BEAST,
+    CURLcode::*,
+    _bindgen_ty_6::*,
+    _bindgen_ty_7::*,
+};
+use crate::rawx::{CURLSSLOPT_NATIVE_CA, CURLSSLOPT_NO_PARTIALCHAIN};
+
+/// Where a certificate or key is loaded from.
+pub enum Source {
+    File(String),
+    Blob(Vec<u8>),
+}
+
+impl Source {
+    fn read(&self) -> io::Result<Cow<'_, [u8]>> {
+        match self {
+            Source::File(path) => fs::read(path).map(Cow::Owned),
+            Source::Blob(blob) => Ok(Cow::Borrowed(blob)),
+        }
+    }
+
+    fn describe(&self) -> &str {
+        match self {
+            Source::File(path) => path,
+            Source::Blob(_) => "(memory blob)",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum FileType {
+    Pem,
+    Der,
+    P12,
+}
+
+impl FileType {
+    fn parse(name: Option<&str>) -> Result<Self, Error> {
+        let name = match name {
+            Some(name) => name,
+            None => return Ok(FileType::Pem),
+        };
+
+        if name.eq_ignore_ascii_case("PEM") {
+            Ok(FileType::Pem)
+        } else if name.eq_ignore_ascii_case("DER") {
+            Ok(FileType::Der)
+        } else if name.eq_ignore_ascii_case("P12") {
+            Ok(FileType::P12)
+        } else if name.eq_ignore_ascii_case("ENG") {
+            Err(Error::new(CURLE_NOT_BUILT_IN, "crypto engines are not supported"))
+        } else {
+            Err(Error::new(CURLE_SSL_CERTPROBLEM, format!("not supported file type '{}'", name)))
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub enum TlsVersion {
+    Tls10,
+    Tls11,
+    Tls12,
+    Tls13,
+}
+
+impl TlsVersion {
+    fn to_openssl(self) -> SslVersion {
+        match self {
+            TlsVersion::Tls10 => SslVersion::TLS1,
+            TlsVersion::Tls11 => SslVersion::TLS1_1,
+            TlsVersion::Tls12 => SslVersion::TLS1_2,
+            TlsVersion::Tls13 => SslVersion::TLS1_3,
+        }
+    }
+}
+
+/// Parses `CURLOPT_SSLVERSION` into the minimum and maximum version.
+///
+/// Both are enforced by OpenSSL, `None` leaves its default in place.
+#[allow(non_upper_case_globals)]
+pub fn ssl_version(value: c_long) -> Result<(Option<TlsVersion>, Option<TlsVersion>), Error> {
+    let value = value as c_uint;
+
+    let min = match value & 0xffff {
+        CURL_SSLVERSION_DEFAULT => None,
+        CURL_SSLVERSION_TLSv1 | CURL_SSLVERSION_TLSv1_0 => Some(TlsVersion::Tls10),
+        CURL_SSLVERSION_TLSv1_1 => Some(TlsVersion::Tls11),
+        CURL_SSLVERSION_TLSv1_2 => Some(TlsVersion::Tls12),
+        CURL_SSLVERSION_TLSv1_3 => Some(TlsVersion::Tls13),
+        CURL_SSLVERSION_SSLv2 | CURL_SSLVERSION_SSLv3 => return Err(Error::new(CURLE_NOT_BUILT_IN, "SSLv2 and SSLv3 are not supported")),
+        _ => return Err(Error::new(CURLE_BAD_FUNCTION_ARGUMENT, format!("unknown SSL version ({})", value & 0xffff))),
+    };
+
+    let max = match value & 0xffff_0000 {
+        CURL_SSLVERSION_MAX_NONE | CURL_SSLVERSION_MAX_DEFAULT => None,
+        CURL_SSLVERSION_MAX_TLSv1_0 => Some(TlsVersion::Tls10),
+        CURL_SSLVERSION_MAX_TLSv1_1 => Some(TlsVersion::Tls11),
+        CURL_SSLVERSION_MAX_TLSv1_2 => Some(TlsVersion::Tls12),
+        CURL_SSLVERSION_MAX_TLSv1_3 => Some(TlsVersion::Tls13),
+        max => return Err(Error::new(CURLE_BAD_FUNCTION_ARGUMENT, format!("unknown maximum SSL version ({})", max))),
+    };
+
+    if let (Some(min), Some(max)) = (min, max) {
+        if max < min {
+            return Err(Error::new(CURLE_BAD_FUNCTION_ARGUMENT, "the maximum SSL version is below the minimum"));
+        }
+    }
+
+    Ok((min, max))
+}
+
+// The private key, the client certificate and its chain
+type Identity = (PKey<Private>, X509, Vec<X509>);
+
+// Loads the client certificate, its chain and the private key
+fn load_identity(options: &Options) -> Result<Option<Identity>, Error> {
+    let cert = match &options.ssl_cert {
+        Some(cert) => cert,
+        None => return Ok(None),
+    };
+
+    let passwd = options.key_passwd.as_deref().unwrap_or("");
+    let cert_type = FileType::parse(options.ssl_cert_type.as_deref())?;
+    let cert_bytes = cert.read().map_err(|e| Error::new(
+        CURLE_SSL_CERTPROBLEM,
+        format!("could not load client certificate {}: {}", cert.describe(), e),
+    ))?;
+
+    let (key, leaf, certs) = if cert_type == FileType::P12 {
+        let pkcs12 = Pkcs12::from_der(&cert_bytes)
+            .and_then(|pkcs12| pkcs12.parse2(passwd))
+            .map_err(|e| Error::new(
+                CURLE_SSL_CERTPROBLEM,
+                format!("could not parse PKCS12 file {}: {}", cert.describe(), e),
+            ))?;
+        let chain = pkcs12.ca
+            .map(|chain| chain.into_iter().collect())
+            .unwrap_or_default();
+
+        match (pkcs12.pkey, pkcs12.cert) {
+            (Some(key), Some(leaf)) => (key, leaf, chain),
+            _ => return Err(Error::new(
+                CURLE_SSL_CERTPROBLEM,
+                format!("PKCS12 file {} holds no client certificate and key", cert.describe()),
+            )),
+        }
+    } else {
+        let mut certs = load_certs(&cert_bytes, cert_type).map_err(|e| Error::new(
+            CURLE_SSL_CERTPROBLEM,
+            format!("could not load client certificate {}: {}", cert.describe(), e),
+        ))?;
+        let leaf = certs.remove(0);
+
+        // Like libcurl, look for the key in the certificate file if no key was given
+        let key_source = options.ssl_key.as_ref().unwrap_or(cert);
+        let key_type = match &options.ssl_key_type {
+            Some(key_type) => FileType::parse(Some(key_type))?,
+            None if options.ssl_key.is_none() => cert_type,
+            None => FileType::Pem,
+        };
+        let key_bytes = key_source.read().map_err(|e| Error::new(
+            CURLE_SSL_CERTPROBLEM,
+            format!("could not load private key {}: {}", key_source.describe(), e),
+        ))?;
+        let key = load_key(&key_bytes, key_type, passwd).map_err(|e| Error::new(
+            CURLE_SSL_CERTPROBLEM,
+            format!("unable to set private key file {}: {}", key_source.describe(), e),
+        ))?;
+
+        (key, leaf, certs)
+    };
+
+    let key_matches = leaf.public_key()
+        .map(|public_key| public_key.public_eq(&key))
+        .unwrap_or(false);
+
+    if !key_matches {
+        return Err(Error::new(CURLE_SSL_CERTPROBLEM, "private key does not match the client certificate"));
+    }
+
+    Ok(Some((key, leaf, certs)))
+}
+
+fn load_certs(bytes: &[u8], file_type: FileType) -> Result<Vec<X509>, String> {
+    let certs = match file_type {
+        FileType::Pem => X509::stack_from_pem(bytes).map_err(|e| e.to_string())?,
+        FileType::Der => vec![X509::from_der(bytes).map_err(|e| e.to_string())?],
+        FileType::P12 => unreachable!("PKCS12 files are handled separately"),
+    };
+
+    if certs.is_empty() {
+        return Err("no certificate found".into());
+    }
+
+    Ok(certs)
+}
+
+fn load_key(bytes: &[u8], file_type: FileType, passwd: &str) -> Result<PKey<Private>, String> {
+    let key = match file_type {
+        FileType::Pem => PKey::private_key_from_pem_passphrase(bytes, passwd.as_bytes()),
+        FileType::Der if passwd.is_empty() => PKey::private_key_from_der(bytes),
+        // Like with PEM, the password is only needed if the key turns out to be encrypted
+        FileType::Der => PKey::private_key_from_pkcs8_passphrase(bytes, passwd.as_bytes())
+            .or_else(|_| PKey::private_key_from_der(bytes)),
+        FileType::P12 => return Err("PKCS12 is not a supported key type".into()),
+    };
+
+    key.map_err(|e| e.to_string())
+}
+
+fn openssl_error(e: openssl::error::ErrorStack) -> Error {
+    Error::new(CURLE_SSL_CERTPROBLEM, e.to_string())
+}
+
+// The certificates of `CURLOPT_CAINFO` and `CURLOPT_CAPATH`, which replace the system store
+fn trust_store(options: &Options) -> Result<Option<X509Store>, Error> {
+    if options.ca_info.is_none() && options.ca_path.is_none() {
+        return Ok(None);
+    }
+
+    let bad_file = |what: &str, e: &dyn fmt::Display| Error::new(
+        CURLE_SSL_CACERT_BADFILE,
+        format!("error setting certificate {}: {}", what, e),
+    );
+    let mut store = X509StoreBuilder::new().map_err(|e| bad_file("store", &e))?;
+
+    // Without CURLSSLOPT_NATIVE_CA, the system store is replaced rather than added to
+    if options.ssl_options & CURLSSLOPT_NATIVE_CA != 0 {
+        store.set_default_paths().map_err(|e| bad_file("store", &e))?;
+    }
+
+    if let Some(ca_info) = &options.ca_info {
+        let certs = ca_info.read()
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| load_certs(&bytes, FileType::Pem))
+            .map_err(|e| bad_file(&format!("file {}", ca_info.describe()), &e))?;
+
+        for cert in certs {
+            store.add_cert(cert).map_err(|e| bad_file(&format!("file {}", ca_info.describe()), &e))?;
+        }
+    }
+
+    if let Some(ca_path) = &options.ca_path {
+        store.add_lookup(X509Lookup::hash_dir())
+            .and_then(|lookup| lookup.add_dir(ca_path, SslFiletype::PEM))
+            .map_err(|e| bad_file(&format!("path {}", ca_path), &e))?;
+    }
+
+    Ok(Some(store.build()))
+}
+
+/// Performs a verified TLS handshake with `host` over `stream`.
+///
+/// ALPN offers HTTP/1.1, and with `http2` HTTP/2 first, unless `CURLOPT_SSL_ENABLE_ALPN` is off.
+pub fn handshake<S: Read + Write + fmt::Debug>(host: &str, stream: S, options: &Options, http2: bool) -> Result<SslStream<S>, Error> {
+    let mut connector = SslConnector::builder(SslMethod::tls())
+        .map_err(|e| Error::new(CURLE_SSL_CONNECT_ERROR, e.to_string()))?;
+
+    connector.set_min_proto_version(options.ssl_version_min.map(TlsVersion::to_openssl)).map_err(openssl_error)?;
+    connector.set_max_proto_version(options.ssl_version_max.map(TlsVersion::to_openssl)).map_err(openssl_error)?;
+
+    if let Some(ciphers) = &options.ssl_cipher_list {
+        connector.set_cipher_list(ciphers).map_err(|e| Error::new(
+            CURLE_SSL_CIPHER,
+            format!("failed setting cipher list: {}: {}", ciphers, e),
+        ))?;
+    }
+
+    if let Some(ciphers) = &options.tls13_ciphers {
+        connector.set_ciphersuites(ciphers).map_err(|e| Error::new(
+            CURLE_SSL_CIPHER,
+            format!("failed setting TLS 1.3 cipher suite: {}: {}", ciphers, e),
+        ))?;
+    }
+
+    if let Some(store) = trust_store(options)? {
+        connector.set_cert_store(store);
+    }
+
+    // Like libcurl, trust anchors don't have to be self-signed unless CURLSSLOPT_NO_PARTIALCHAIN says so
+    if options.ssl_options & CURLSSLOPT_NO_PARTIALCHAIN == 0 {
+        connector.verify_param_mut().set_flags(X509VerifyFlags::PARTIAL_CHAIN).map_err(openssl_error)?;
+    }
+
+    // OpenSSL only leaves out the empty fragments that protect against BEAST when asked to
+    if options.ssl_options & CURLSSLOPT_ALLOW_BEAST as c_long != 0 {
+        connector.set_options(SslOptions::DONT_INSERT_EMPTY_FRAGMENTS);
+    }
+
+    if options.ssl_enable_alpn {
+        let protocols: &[u8] = match http2 {
+            true => b"\x02h2\x08http/1.1",
+            false => b"\x08http/1.1",
+        };
+        connector.set_alpn_protos(protocols).map_err(openssl_error)?;
+    }
+
+    if let Some((key, leaf, certs)) = load_identity(options)? {
+        connector.set_private_key(&key).map_err(openssl_error)?;
+        connector.set_certificate(&leaf).map_err(openssl_error)?;
+        for cert in certs {
+            connector.add_extra_chain_cert(cert).map_err(openssl_error)?;
+        }
+    }
+
+    connector.build()
+        .connect(host, stream)
+        .map_err(|e| match e {
+            HandshakeError::Failure(ref stream) if stream.ssl().verify_result() != X509VerifyResult::OK => Error::new(
+                CURLE_PEER_FAILED_VERIFICATION,
+                format!("SSL certificate problem: {}", stream.ssl().verify_result()),
+            ),
+            // The connection blocks until it times out, so that's all that can make it stop halfway
+            HandshakeError::WouldBlock(_) => Error::new(CURLE_OPERATION_TIMEDOUT, "SSL connection timeout"),
+            e => Error::new(CURLE_SSL_CONNECT_ERROR, e.to_string()),
+        })
+}
+
+/// The certificates the server sent, starting with its own.
+pub fn peer_chain(ssl: &SslRef) -> Vec<X509> {
+    ssl.peer_cert_chain()
+        .map(|chain| chain.iter().map(ToOwned::to_owned).collect())
+        .unwrap_or_default()
+}
+
+/// Whether ALPN settled on HTTP/2.
+pub fn is_http2(ssl: &SslRef) -> bool {
+    ssl.selected_alpn_protocol() == Some(b"h2")
+}
+
+/// Checks the server key against `CURLOPT_PINNEDPUBLICKEY`.
+pub fn check_pinned_public_key(chain: &[X509], pin: &str) -> Result<(), Error> {
+    let spki = chain.first()
+        .ok_or_else(|| Error::new(CURLE_SSL_PINNEDPUBKEYNOTMATCH, "server did not send a certificate"))?
+        .public_key()
+        .and_then(|key| key.public_key_to_der())
+        .map_err(|e| Error::new(CURLE_SSL_PINNEDPUBKEYNOTMATCH, e.to_string()))?;
+
+    if pin_matches(pin, &spki)? {
+        return Ok(());
+    }
+
+    Err(Error::new(CURLE_SSL_PINNEDPUBKEYNOTMATCH, "SSL: public key does not match pinned public key"))
+}
+
+fn pin_matches(pin: &str, spki: &[u8]) -> Result<bool, Error> {
+    if pin.starts_with("sha256//") {
+        let hash = base64::encode(&sha256(spki));
+
+        let matches = pin.split(';')
+            .map(|pin| pin.trim().trim_start_matches("sha256//"))
+            .any(|pin| pin == hash);
+
+        return Ok(matches);
+    }
+
+    let pinned = fs::read(pin).map_err(|e| Error::new(
+        CURLE_SSL_PINNEDPUBKEYNOTMATCH,
+        format!("could not load pinned public key {}: {}", pin, e),
+    ))?;
+
+    if !pinned.starts_with(b"-----BEGIN PUBLIC KEY-----") {
+        return Ok(pinned == spki);
+    }
+
+    let pinned = PKey::public_key_from_pem(&pinned)
+        .and_then(|key| key.public_key_to_der())
+        .map_err(|e| Error::new(CURLE_SSL_PINNEDPUBKEYNOTMATCH, e.to_string()))?;
+
+    Ok(pinned == spki)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_minimum_and_maximum_versions() {
+        let value = (CURL_SSLVERSION_TLSv1_2 | CURL_SSLVERSION_MAX_TLSv1_3) as c_long;
+        assert_eq!(ssl_version(value).unwrap(), (Some(TlsVersion::Tls12), Some(TlsVersion::Tls13)));
+        assert_eq!(ssl_version(CURL_SSLVERSION_TLSv1_3 as c_long).unwrap(), (Some(TlsVersion::Tls13), None));
+
+        let inverted = (CURL_SSLVERSION_TLSv1_3 | CURL_SSLVERSION_MAX_TLSv1_2) as c_long;
+        assert_eq!(ssl_version(inverted).unwrap_err().code, CURLE_BAD_FUNCTION_ARGUMENT);
+        assert_eq!(ssl_version(CURL_SSLVERSION_SSLv3 as c_long).unwrap_err().code, CURLE_NOT_BUILT_IN);
+    }
+}
@@ -0,0 +1,200 @@
+use std::io::{self, Read, Write};
+use libc::*;
+use hyper::{Body, Request, Response};
+use hyper::header::{HeaderName, HeaderValue, CONTENT_LENGTH, HOST};
+use crate::CURL;
+use crate::raw::curl_infotype::{self, *};
+use crate::wire::{Event, Wire};
+
+pub type DebugFunction = unsafe extern "C" fn(
+    handle: *mut CURL,
+    kind: curl_infotype::Type,
+    data: *mut c_char,
+    size: size_t,
+    userptr: *mut c_void,
+) -> c_int;
+
+/// Emits the `CURLOPT_VERBOSE` trace of a transfer.
+///
+/// Without a `CURLOPT_DEBUGFUNCTION`, text and headers are written
+/// to `CURLOPT_STDERR` in the same format libcurl uses.
+#[derive(Copy, Clone)]
+pub struct Tracer {
+    handle: *mut CURL,
+    verbose: bool,
+    debug_function: Option<DebugFunction>,
+    debug_data: *mut c_void,
+    stderr: *mut FILE,
+}
+
+impl Tracer {
+    pub fn new(curl: &mut CURL) -> Self {
+        Self {
+            verbose: curl.options.verbose,
+            debug_function: curl.options.debug_function,
+            debug_data: curl.options.debug_data,
+            stderr: curl.options.stderr,
+            handle: curl,
+        }
+    }
+
+    pub fn text(&self, text: &str) {
+        self.emit(CURLINFO_TEXT, format!("{}\n", text).as_bytes());
+    }
+
+    pub fn header_out(&self, headers: &[u8]) {
+        self.emit(CURLINFO_HEADER_OUT, headers);
+    }
+
+    pub fn header_in(&self, line: &[u8]) {
+        self.emit(CURLINFO_HEADER_IN, line);
+    }
+
+    /// Traces what went over the connection, as seen by the transport.
+    pub fn wire(&self, events: Vec<Event>) {
+        for event in events {
+            match event {
+                Event::SslDataIn(data) => self.emit(CURLINFO_SSL_DATA_IN, &data),
+                Event::SslDataOut(data) => self.emit(CURLINFO_SSL_DATA_OUT, &data),
+                Event::HeaderOut(head) => self.header_out(&head),
+                Event::DataOut(data) => self.data_out(&data),
+                // Like libcurl, incoming headers are passed on one line at a time
+                Event::Head(lines) | Event::Trailers(lines) => {
+                    for line in lines.split_inclusive(|&byte| byte == b'\n') {
+                        self.header_in(line);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Traces the request line and headers of an outgoing HTTP/2 request, followed by its body.
+    ///
+    /// The transport passes HTTP/2 frames on without decoding them, so the request is reconstructed.
+    pub fn request(&self, request: &Request<Body>, body: Option<&[u8]>) {
+        if !self.verbose {
+            return;
+        }
+
+        let uri = request.uri();
+        let target = uri.path_and_query().map_or("/", |target| target.as_str());
+        let mut head = Vec::new();
+
+        write!(head, "{} {} HTTP/2\r\n", request.method(), target).ok();
+
+        if let Some(authority) = uri.authority_part().filter(|_| !request.headers().contains_key(HOST)) {
+            write!(head, "Host: {}\r\n", authority).ok();
+        }
+
+        if let Some(body) = body.filter(|_| !request.headers().contains_key(CONTENT_LENGTH)) {
+            write!(head, "Content-Length: {}\r\n", body.len()).ok();
+        }
+
+        for (name, value) in request.headers() {
+            write_header(&mut head, name, value);
+        }
+        write!(head, "\r\n").ok();
+
+        self.header_out(&head);
+
+        if let Some(body) = body {
+            self.data_out(body);
+        }
+    }
+
+    /// Traces the status line and headers of an HTTP/2 response, one line at a time.
+    pub fn response(&self, response: &Response<Body>) {
+        if !self.verbose {
+            return;
+        }
+
+        let status = response.status();
+        let status_line = format!(
+            "{:?} {} {}\r\n",
+            response.version(),
+            status.as_str(),
+            status.canonical_reason().unwrap_or_default(),
+        );
+        self.header_in(status_line.as_bytes());
+
+        for (name, value) in response.headers() {
+            let mut line = Vec::new();
+            write_header(&mut line, name, value);
+            self.header_in(&line);
+        }
+
+        self.header_in(b"\r\n");
+    }
+
+    pub fn data_out(&self, data: &[u8]) {
+        self.emit(CURLINFO_DATA_OUT, data);
+    }
+
+    pub fn data_in(&self, data: &[u8]) {
+        self.emit(CURLINFO_DATA_IN, data);
+    }
+
+    fn emit(&self, kind: curl_infotype::Type, data: &[u8]) {
+        if !self.verbose || data.is_empty() {
+            return;
+        }
+
+        if let Some(debug_function) = self.debug_function {
+            unsafe {
+                debug_function(self.handle, kind, data.as_ptr() as *mut c_char, data.len(), self.debug_data);
+            }
+            return;
+        }
+
+        let prefix: &[u8] = match kind {
+            CURLINFO_TEXT => b"* ",
+            CURLINFO_HEADER_OUT => b"> ",
+            CURLINFO_HEADER_IN => b"< ",
+            _ => return,
+        };
+
+        // Outgoing headers arrive as one block, so every line gets the prefix
+        let mut rest = data;
+
+        while !rest.is_empty() {
+            let end = rest.iter().position(|&byte| byte == b'\n').map_or(rest.len(), |i| i + 1);
+            let (line, after) = rest.split_at(end);
+            let mut output = prefix.to_vec();
+            output.extend_from_slice(line);
+
+            unsafe {
+                fwrite(output.as_ptr() as *const c_void, 1, output.len(), self.stderr);
+            }
+
+            rest = after;
+        }
+    }
+}
+
+fn write_header(output: &mut Vec<u8>, name: &HeaderName, value: &HeaderValue) {
+    write!(output, "{}: ", name).ok();
+    output.extend_from_slice(value.as_bytes());
+    write!(output, "\r\n").ok();
+}
+
+/// Traces the incoming body as `CURLINFO_DATA_IN`, after what the transport saw while it came in.
+pub struct Reader<'a, R> {
+    inner: R,
+    tracer: Tracer,
+    wire: &'a Wire,
+}
+
+impl<'a, R: Read> Reader<'a, R> {
+    pub fn new(inner: R, tracer: Tracer, wire: &'a Wire) -> Self {
+        Self { inner, tracer, wire }
+    }
+}
+
+impl<R: Read> Read for Reader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.tracer.wire(self.wire.drain());
+        self.tracer.data_in(&buf[..len]);
+        Ok(len)
+    }
+}
@@ -0,0 +1,380 @@
+use std::cmp::min;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+use futures::{future, Async, Future, Poll, Stream};
+use hyper::{Body, Chunk, Request, Response};
+use hyper::client::conn::{Builder, SendRequest};
+use mio::unix::EventedFd;
+use openssl::ssl::SslStream;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::reactor::Registration;
+use tokio::runtime::current_thread::Runtime;
+use tokio::timer::Timeout;
+use crate::connect::Duplicate;
+use crate::timeout::OperationTimeout;
+use crate::wire::{Event, Recorder, ResponseParser, Sink};
+
+/// Sends the request of one hop over the connection that was opened for it.
+///
+/// hyper speaks HTTP over the connection, on a runtime of its own
+/// that only makes progress while the transfer waits for it,
+/// so everything happens on the calling thread.
+pub struct Transport {
+    runtime: Runtime,
+    sender: SendRequest<Body>,
+}
+
+impl Transport {
+    /// Starts HTTP/1, or with `http2` HTTP/2, over `upstream`, reporting what goes over it to `sink`.
+    pub fn start(upstream: Upstream, http2: bool, head_request: bool, sink: Sink) -> io::Result<Self> {
+        let mut runtime = Runtime::new()?;
+
+        // HTTP/2 frames are passed on as they are, so only HTTP/1 messages are followed
+        let http1 = match http2 {
+            true => None,
+            false => Some(Http1 {
+                head: Some(Vec::new()),
+                parser: ResponseParser::new(head_request),
+                received: Vec::new(),
+            }),
+        };
+        let mut connection = Connection { upstream, sink, http1 };
+
+        // The connection has to be registered with the reactor of the runtime, so this happens on it
+        let handshake = future::lazy(move || {
+                connection.upstream.io_mut().register()?;
+                Ok(connection)
+            })
+            .and_then(|connection| Builder::new()
+                .http2_only(http2)
+                .handshake(connection)
+                .map_err(io_error));
+
+        let (sender, connection) = runtime.block_on(handshake)?;
+
+        // Its errors surface through the response and its body
+        runtime.spawn(connection.map_err(|_| ()));
+
+        Ok(Self { runtime, sender })
+    }
+
+    /// Sends `request` and waits for the head of the response.
+    pub fn send(&mut self, request: Request<Body>, timeout: OperationTimeout) -> io::Result<Response<Body>> {
+        let response = self.sender.send_request(request).map_err(io_error);
+        self.block_on(response, timeout)
+    }
+
+    // Runs `future` to completion, or fails with `TimedOut` when it takes too long
+    fn block_on<F: Future<Error = io::Error>>(&mut self, future: F, timeout: OperationTimeout) -> io::Result<F::Item> {
+        let timeout = match timeout.get() {
+            Some(timeout) => timeout,
+            None => return self.runtime.block_on(future),
+        };
+
+        self.runtime.block_on(Timeout::new(future, timeout)).map_err(|e| match e.into_inner() {
+            Some(e) => e,
+            None => io::Error::new(io::ErrorKind::TimedOut, "operation timed out"),
+        })
+    }
+}
+
+fn io_error(e: hyper::Error) -> io::Error {
+    io::Error::other(e)
+}
+
+/// Reads the body of a response, waiting for the transport whenever nothing is buffered.
+pub struct Reader<'a> {
+    transport: &'a mut Transport,
+    body: Body,
+    chunk: Chunk,
+    // How much of `chunk` was read
+    position: usize,
+    timeout: OperationTimeout,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(transport: &'a mut Transport, body: Body, timeout: OperationTimeout) -> Self {
+        Self {
+            transport,
+            body,
+            chunk: Chunk::default(),
+            position: 0,
+            timeout,
+        }
+    }
+}
+
+impl Read for Reader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            let body = &mut self.body;
+            let next = future::poll_fn(|| body.poll()).map_err(io_error);
+
+            match self.transport.block_on(next, self.timeout)? {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                },
+                None => return Ok(0),
+            }
+        }
+
+        let len = min(buf.len(), self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+/// The connection to the origin, with TLS already set up for HTTPS.
+pub enum Upstream {
+    Plain(Io),
+    Tls(SslStream<Recorder<Io>>),
+}
+
+impl Upstream {
+    fn io_mut(&mut self) -> &mut Io {
+        match self {
+            Upstream::Plain(io) => io,
+            Upstream::Tls(stream) => stream.get_mut().get_mut(),
+        }
+    }
+}
+
+impl Read for Upstream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Upstream::Plain(io) => io.read(buf),
+            Upstream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Upstream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Upstream::Plain(io) => io.write(buf),
+            Upstream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Upstream::Plain(io) => io.flush(),
+            Upstream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// A connection hyper can wait on.
+///
+/// It blocks until the transport registers it with its reactor,
+/// so the TLS handshake can run on it beforehand.
+pub struct Io {
+    stream: Duplicate,
+    registration: Option<Registration>,
+}
+
+impl Io {
+    pub fn new(stream: Duplicate) -> Self {
+        Self {
+            stream,
+            registration: None,
+        }
+    }
+
+    /// Bounds how long reads and writes block, until the connection is registered.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_timeout(timeout)
+    }
+
+    // Switches to non-blocking mode, with the reactor waking up whichever task waits on the connection
+    fn register(&mut self) -> io::Result<()> {
+        let registration = Registration::new();
+        registration.register(&EventedFd(&self.stream.as_raw_fd()))?;
+        self.stream.set_timeout(None)?;
+        self.stream.set_nonblocking(true)?;
+        self.registration = Some(registration);
+
+        Ok(())
+    }
+
+    // Whether an operation that would block can be retried right away.
+    // If not, the current task is woken up once it can.
+    fn is_ready(&self, poll_ready: fn(&Registration) -> Poll<mio::Ready, io::Error>) -> io::Result<bool> {
+        match &self.registration {
+            Some(registration) => Ok(poll_ready(registration)?.is_ready()),
+            None => Ok(false),
+        }
+    }
+}
+
+impl fmt::Debug for Io {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.stream.fmt(f)
+    }
+}
+
+impl Drop for Io {
+    fn drop(&mut self) {
+        if let Some(registration) = &mut self.registration {
+            registration.deregister(&EventedFd(&self.stream.as_raw_fd())).ok();
+        }
+    }
+}
+
+impl Read for Io {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.stream.read(buf) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock && self.is_ready(Registration::poll_read_ready)? => continue,
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Write for Io {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        loop {
+            match self.stream.write(buf) {
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock && self.is_ready(Registration::poll_write_ready)? => continue,
+                result => return result,
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+// What hyper reads from and writes to, which reports HTTP/1 messages as they go over the connection
+struct Connection {
+    upstream: Upstream,
+    sink: Sink,
+    http1: Option<Http1>,
+}
+
+struct Http1 {
+    // The request head, until all of it was sent
+    head: Option<Vec<u8>>,
+    parser: ResponseParser,
+    // What the parser passed on, until hyper reads it
+    received: Vec<u8>,
+}
+
+impl Http1 {
+    fn sent(&mut self, mut data: &[u8], sink: &Sink) {
+        if let Some(mut head) = self.head.take() {
+            // The end of the head may have been split between writes
+            let start = head.len().saturating_sub(3);
+            head.extend_from_slice(data);
+
+            let end = match head[start..].windows(4).position(|window| window == b"\r\n\r\n") {
+                Some(position) => start + position + 4,
+                None => {
+                    self.head = Some(head);
+                    return;
+                },
+            };
+
+            data = &data[data.len() - (head.len() - end)..];
+            head.truncate(end);
+            sink.send(Event::HeaderOut(head));
+        }
+
+        if !data.is_empty() {
+            sink.send(Event::DataOut(data.to_vec()));
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let http1 = match &mut self.http1 {
+            Some(http1) => http1,
+            None => return self.upstream.read(buf),
+        };
+
+        // Trailers are left out, so data may come in without anything to pass on
+        while http1.received.is_empty() {
+            let mut data = [0; 16 * 1024];
+            let len = self.upstream.read(&mut data)?;
+
+            if len == 0 {
+                return Ok(0);
+            }
+
+            http1.parser.feed(&data[..len], &self.sink, &mut http1.received);
+        }
+
+        let len = min(buf.len(), http1.received.len());
+        buf[..len].copy_from_slice(&http1.received[..len]);
+        http1.received.drain(..len);
+
+        Ok(len)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.upstream.write(buf)?;
+
+        if let Some(http1) = &mut self.http1 {
+            http1.sent(&buf[..len], &self.sink);
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.upstream.flush()
+    }
+}
+
+impl AsyncRead for Connection {}
+
+impl AsyncWrite for Connection {
+    // The connection is closed with the transfer
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_request_head_across_writes() {
+        let wire = crate::wire::Wire::new(true);
+        let mut http1 = Http1 {
+            head: Some(Vec::new()),
+            parser: ResponseParser::new(false),
+            received: Vec::new(),
+        };
+
+        http1.sent(b"POST / HTTP/1.1\r\ncontent-length: 4\r\n\r", &wire.sink());
+        http1.sent(b"\nbo", &wire.sink());
+        http1.sent(b"dy", &wire.sink());
+
+        let events = wire.drain()
+            .into_iter()
+            .map(|event| match event {
+                Event::HeaderOut(head) => ("head", head),
+                Event::DataOut(data) => ("data", data),
+                _ => ("other", Vec::new()),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(events, vec![
+            ("head", b"POST / HTTP/1.1\r\ncontent-length: 4\r\n\r\n".to_vec()),
+            ("data", b"bo".to_vec()),
+            ("data", b"dy".to_vec()),
+        ]);
+    }
+}
@@ -1,3 +1,4 @@
 
 pub mod root_rc;
 pub mod borrow_raw;
+pub mod hex;
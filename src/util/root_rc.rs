@@ -7,12 +7,6 @@ pub struct RootRc<T> {
 }
 
 impl<T> RootRc<T> {
-    pub fn new(value: T) -> Self {
-        Self {
-            inner: rc::Rc::new(value),
-        }
-    }
-
     pub fn weak(&self) -> Weak<T> {
         Weak::new(self)
     }
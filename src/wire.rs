@@ -0,0 +1,345 @@
+use std::cell::RefCell;
+use std::cmp::min;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Something that went over the connection of a transfer.
+#[derive(Clone)]
+pub enum Event {
+    SslDataIn(Vec<u8>),
+    SslDataOut(Vec<u8>),
+    HeaderOut(Vec<u8>),
+    DataOut(Vec<u8>),
+    /// A response head as received, informational or final
+    Head(Vec<u8>),
+    /// The trailer fields after a chunked body
+    Trailers(Vec<u8>),
+}
+
+/// Collects what the transport sees of the connection, until the transfer reports it.
+///
+/// Headers are always collected, the data only with `verbose`.
+pub struct Wire {
+    sink: Sink,
+    receiver: Receiver<Event>,
+    // Heads and trailers that were drained, until they are stored for the header API
+    received: RefCell<Vec<Event>>,
+}
+
+impl Wire {
+    pub fn new(verbose: bool) -> Self {
+        let (sender, receiver) = channel();
+
+        Self {
+            sink: Sink { sender, verbose },
+            receiver,
+            received: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn sink(&self) -> Sink {
+        self.sink.clone()
+    }
+
+    /// The events since the last call, in the order they happened.
+    pub fn drain(&self) -> Vec<Event> {
+        let events = self.receiver.try_iter().collect::<Vec<_>>();
+
+        self.received.borrow_mut().extend(events.iter()
+            .filter(|event| matches!(event, Event::Head(_) | Event::Trailers(_)))
+            .cloned());
+
+        events
+    }
+
+    /// The heads and trailers drained since the last call.
+    pub fn take_received(&self) -> Vec<Event> {
+        self.received.borrow_mut().split_off(0)
+    }
+}
+
+#[derive(Clone)]
+pub struct Sink {
+    sender: Sender<Event>,
+    verbose: bool,
+}
+
+impl Sink {
+    pub fn send(&self, event: Event) {
+        let is_data = !matches!(event, Event::Head(_) | Event::Trailers(_));
+
+        if is_data && !self.verbose {
+            return;
+        }
+
+        // Nobody is listening any more once the transfer is over
+        self.sender.send(event).ok();
+    }
+}
+
+/// Reports the raw bytes of a TLS connection as `SslDataIn` and `SslDataOut`.
+pub struct Recorder<S> {
+    inner: S,
+    sink: Sink,
+}
+
+impl<S> Recorder<S> {
+    pub fn new(inner: S, sink: Sink) -> Self {
+        Self { inner, sink }
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Recorder<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<S: Read> Read for Recorder<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        if len > 0 {
+            self.sink.send(Event::SslDataIn(buf[..len].to_vec()));
+        }
+        Ok(len)
+    }
+}
+
+impl<S: Write> Write for Recorder<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        if len > 0 {
+            self.sink.send(Event::SslDataOut(buf[..len].to_vec()));
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+enum State {
+    Head,
+    Length(u64),
+    UntilClose,
+    ChunkSize,
+    ChunkData(u64),
+    ChunkEnd,
+    Trailers,
+    Done,
+}
+
+/// Follows an HTTP/1 response as it comes in, to find its heads and trailers.
+///
+/// hyper fails on trailers, so they are left out of what is passed on.
+pub struct ResponseParser {
+    // Responses to HEAD have no body, whatever their headers say
+    head_request: bool,
+    state: State,
+    // The unfinished head, chunk line or trailers
+    pending: Vec<u8>,
+}
+
+impl ResponseParser {
+    pub fn new(head_request: bool) -> Self {
+        Self {
+            head_request,
+            state: State::Head,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Reports the heads and trailers in `data` to `sink`, and adds what hyper gets to see to `output`.
+    pub fn feed(&mut self, mut data: &[u8], sink: &Sink, output: &mut Vec<u8>) {
+        while !data.is_empty() {
+            let before = data;
+
+            match self.state {
+                State::Head => {
+                    if self.take_until(&mut data, b"\r\n\r\n") {
+                        let head = mem::take(&mut self.pending);
+                        self.state = self.body_of(&head);
+                        sink.send(Event::Head(head));
+                    }
+                },
+                State::Length(remaining) => {
+                    let len = min(remaining, data.len() as u64);
+                    data = &data[len as usize..];
+                    self.state = match remaining - len {
+                        0 => State::Done,
+                        remaining => State::Length(remaining),
+                    };
+                },
+                State::ChunkSize => {
+                    if self.take_until(&mut data, b"\r\n") {
+                        let line = String::from_utf8_lossy(&self.pending).into_owned();
+                        self.pending.clear();
+                        let size = line.split(';').next().unwrap_or_default().trim();
+
+                        self.state = match u64::from_str_radix(size, 16) {
+                            Ok(0) => State::Trailers,
+                            Ok(size) => State::ChunkData(size),
+                            Err(_) => State::Done,
+                        };
+                    }
+                },
+                State::ChunkData(remaining) => {
+                    let len = min(remaining, data.len() as u64);
+                    data = &data[len as usize..];
+                    self.state = match remaining - len {
+                        0 => State::ChunkEnd,
+                        remaining => State::ChunkData(remaining),
+                    };
+                },
+                State::ChunkEnd => {
+                    if self.take_until(&mut data, b"\r\n") {
+                        self.pending.clear();
+                        self.state = State::ChunkSize;
+                    }
+                },
+                State::Trailers => {
+                    let done = self.take_until(&mut data, b"\r\n");
+                    let empty = self.pending == b"\r\n";
+
+                    if empty || (done && self.pending.ends_with(b"\r\n\r\n")) {
+                        let trailers = mem::take(&mut self.pending);
+                        self.state = State::Done;
+                        output.extend_from_slice(b"\r\n");
+
+                        if !empty {
+                            sink.send(Event::Trailers(trailers));
+                        }
+                    }
+
+                    // Only the end of the body is passed on
+                    continue;
+                },
+                State::UntilClose | State::Done => data = &[],
+            }
+
+            output.extend_from_slice(&before[..before.len() - data.len()]);
+        }
+    }
+
+    // Moves bytes to `pending` until it ends with `delimiter`, returns whether it does
+    fn take_until(&mut self, data: &mut &[u8], delimiter: &[u8]) -> bool {
+        while let Some((&byte, rest)) = data.split_first() {
+            self.pending.push(byte);
+            *data = rest;
+
+            if self.pending.ends_with(delimiter) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // What follows the head, going by its status and framing headers
+    fn body_of(&self, head: &[u8]) -> State {
+        let head = String::from_utf8_lossy(head);
+        let mut lines = head.split("\r\n");
+        let status = lines.next()
+            .and_then(|line| line.split(' ').nth(1))
+            .and_then(|status| status.parse::<u16>().ok())
+            .unwrap_or(0);
+
+        match status {
+            101 => return State::UntilClose,
+            100..=199 => return State::Head,
+            204 | 304 => return State::Done,
+            _ if self.head_request => return State::Done,
+            _ => {},
+        }
+
+        let mut length = None;
+
+        for line in lines {
+            let mut field = line.splitn(2, ':');
+            let name = field.next().unwrap_or_default().trim();
+            let value = field.next().unwrap_or_default().trim();
+
+            if name.eq_ignore_ascii_case("transfer-encoding") && value.to_ascii_lowercase().contains("chunked") {
+                return State::ChunkSize;
+            }
+
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse::<u64>().ok();
+            }
+        }
+
+        match length {
+            Some(0) => State::Done,
+            Some(length) => State::Length(length),
+            None => State::UntilClose,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(head_request: bool, chunks: &[&[u8]]) -> Vec<(&'static str, Vec<u8>)> {
+        let wire = Wire::new(false);
+        let sink = wire.sink();
+        let mut parser = ResponseParser::new(head_request);
+        let mut output = Vec::new();
+
+        for chunk in chunks {
+            parser.feed(chunk, &sink, &mut output);
+        }
+
+        // Everything but the trailers is passed on
+        let input = chunks.concat();
+        let trailers = input.windows(17).position(|window| window == b"Checksum: abc\r\n\r\n");
+        match trailers {
+            Some(start) => assert_eq!(output, [&input[..start], b"\r\n"].concat()),
+            None => assert_eq!(output, input),
+        }
+
+        let events = wire.drain()
+            .into_iter()
+            .map(|event| match event {
+                Event::Head(head) => ("head", head),
+                Event::Trailers(trailers) => ("trailers", trailers),
+                _ => ("data", Vec::new()),
+            });
+
+        events.collect()
+    }
+
+    #[test]
+    fn finds_informational_heads_and_trailers() {
+        let response: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+            5;ext=1\r\nhello\r\n0\r\nChecksum: abc\r\n\r\n";
+
+        // Split at every byte, as reads can end anywhere
+        let chunks = response.chunks(1).collect::<Vec<_>>();
+
+        assert_eq!(parse(false, &chunks), vec![
+            ("head", b"HTTP/1.1 100 Continue\r\n\r\n".to_vec()),
+            ("head", b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec()),
+            ("trailers", b"Checksum: abc\r\n\r\n".to_vec()),
+        ]);
+    }
+
+    #[test]
+    fn bodies_are_not_taken_for_heads() {
+        let response: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 19\r\n\r\nHTTP/1.1 200 OK\r\n\r\n";
+        assert_eq!(parse(false, &[response]).len(), 1);
+
+        let response: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        assert_eq!(parse(false, &[response]).len(), 1);
+
+        let response: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+        assert_eq!(parse(true, &[response, b"HTTP/1.1 200 OK\r\n\r\n"]).len(), 1);
+    }
+}
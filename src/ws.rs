@@ -0,0 +1,740 @@
+use std::cmp::min;
+use std::io::{self, Read, Write};
+use std::ptr::null;
+use std::slice;
+use libc::*;
+use openssl::rand::rand_bytes;
+use openssl::sha::sha1;
+use hyper::Method;
+use url::Url;
+use hyper::header::{HeaderMap, AUTHORIZATION};
+use crate::{CURL, Options};
+use crate::{auth, sigv4};
+use crate::error::Error;
+use crate::headers::Headers;
+use crate::raw::{CURLcode::{self, *}, curl_off_t};
+use crate::rawx::*;
+use crate::session::Session;
+use crate::trace::Tracer;
+use crate::util::borrow_raw::*;
+
+// Appended to the key to compute Sec-WebSocket-Accept, see RFC 6455
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Longest response head accepted for the upgrade
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+const MAX_CONTROL_PAYLOAD: usize = 125;
+
+const OPCODE_CONT: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// The WebSocket protocol state of an upgraded connection.
+///
+/// The connection itself stays in `CURL::session`, so `curl_easy_send`
+/// and `CURLINFO_ACTIVESOCKET` keep working on it.
+pub struct WebSocket {
+    raw_mode: bool,
+    // Bytes read from the connection that haven't been handed out yet
+    received: Vec<u8>,
+    incoming: Option<Incoming>,
+    // Type of the fragmented message being received
+    message_flags: c_uint,
+    // Set while the fragments of a message are being sent
+    sending_message: bool,
+    // The frame being sent in parts with CURLWS_OFFSET
+    outgoing: Option<Outgoing>,
+    // Encoded frames the connection didn't accept yet
+    unsent: Vec<u8>,
+    close_sent: bool,
+    /// The frame `curl_ws_meta` describes.
+    pub meta: curl_ws_frame,
+    /// Set while the write function is called for a frame.
+    pub in_callback: bool,
+}
+
+struct Incoming {
+    flags: c_uint,
+    len: u64,
+    offset: u64,
+}
+
+struct Outgoing {
+    mask: [u8; 4],
+    remaining: u64,
+    offset: u64,
+}
+
+// The headers that authenticate the upgrade request up front, as it can't answer a challenge
+fn authentication(url: &Url, options: &Options) -> Result<HeaderMap, Error> {
+    let credentials = auth::Credentials::from_request(options, url)?;
+    let mut headers = HeaderMap::new();
+
+    if let Some(authorization) = auth::initial(options, credentials.as_ref()) {
+        headers.insert(AUTHORIZATION, authorization);
+    }
+
+    let signed = sigv4::Request {
+        method: &Method::GET,
+        url,
+        content_type: None,
+        payload: &[],
+    };
+    headers.extend(sigv4::sign_if_enabled(options, credentials.as_ref(), &signed)?);
+
+    Ok(headers)
+}
+
+/// Upgrades a new connection to the WebSocket protocol.
+pub fn handshake(
+    session: &mut Session,
+    url: &Url,
+    options: &Options,
+    tracer: &Tracer,
+    headers: &mut Headers,
+) -> Result<WebSocket, Error> {
+    let mut nonce = [0; 16];
+    rand_bytes(&mut nonce).map_err(|e| Error::new(CURLE_FAILED_INIT, e.to_string()))?;
+    let key = base64::encode(&nonce);
+
+    let mut request = format!("GET {}", url.path());
+    if let Some(query) = url.query() {
+        request.push_str(&format!("?{}", query));
+    }
+    request.push_str(" HTTP/1.1\r\n");
+
+    match url.port() {
+        Some(port) => request.push_str(&format!("Host: {}:{}\r\n", url.host_str().unwrap_or_default(), port)),
+        None => request.push_str(&format!("Host: {}\r\n", url.host_str().unwrap_or_default())),
+    }
+
+    // The upgrade carries the same credentials as any other request. Custom headers,
+    // a user agent and cookies aren't supported for any transfer, so there are none to add.
+    for (name, value) in &authentication(url, options)? {
+        request.push_str(&format!("{}: {}\r\n", name, String::from_utf8_lossy(value.as_bytes())));
+    }
+
+    request.push_str("Upgrade: websocket\r\n");
+    request.push_str("Connection: Upgrade\r\n");
+    request.push_str("Sec-WebSocket-Version: 13\r\n");
+    request.push_str(&format!("Sec-WebSocket-Key: {}\r\n\r\n", key));
+
+    tracer.header_out(request.as_bytes());
+
+    session.write_all(request.as_bytes())
+        .map_err(|e| Error::new(CURLE_SEND_ERROR, e.to_string()))?;
+
+    let (head, leftover) = read_head(session)?;
+    let mut lines = head.split("\r\n").filter(|line| !line.is_empty());
+    let status_line = lines.next().unwrap_or_default();
+
+    tracer.header_in(format!("{}\r\n", status_line).as_bytes());
+
+    let status = status_line.split(' ').nth(1).and_then(|status| status.parse::<u16>().ok());
+    let mut accept = None;
+
+    for line in lines {
+        tracer.header_in(format!("{}\r\n", line).as_bytes());
+
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or_default().trim();
+        let value = parts.next().unwrap_or_default().trim();
+        if name.eq_ignore_ascii_case("Sec-WebSocket-Accept") {
+            accept = Some(value);
+        }
+    }
+
+    tracer.header_in(b"\r\n");
+
+    // Like libcurl, the headers of the 101 response count as informational
+    headers.start_request();
+    headers.push_head(head.as_bytes());
+
+    if status != Some(101) {
+        return Err(Error::new(
+            CURLE_HTTP_RETURNED_ERROR,
+            format!("Refused WebSocket upgrade: {}", status.unwrap_or_default()),
+        ));
+    }
+
+    let expected = base64::encode(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()));
+
+    if accept != Some(expected.as_str()) {
+        return Err(Error::new(CURLE_HTTP_RETURNED_ERROR, "Refused WebSocket upgrade: invalid Sec-WebSocket-Accept"));
+    }
+
+    Ok(WebSocket {
+        raw_mode: options.ws_options & CURLWS_RAW_MODE != 0,
+        received: leftover,
+        incoming: None,
+        message_flags: CURLWS_BINARY,
+        sending_message: false,
+        outgoing: None,
+        unsent: Vec::new(),
+        close_sent: false,
+        meta: curl_ws_frame { age: 0, flags: 0, offset: 0, bytesleft: 0, len: 0 },
+        in_callback: false,
+    })
+}
+
+// Reads up to the end of the response head, returning the head and what came after it
+fn read_head(session: &mut Session) -> Result<(String, Vec<u8>), Error> {
+    let mut received = Vec::new();
+    let mut chunk = [0; 1024];
+
+    loop {
+        if let Some(end) = received.windows(4).position(|window| window == b"\r\n\r\n") {
+            let leftover = received.split_off(end + 4);
+            return Ok((String::from_utf8_lossy(&received).into_owned(), leftover));
+        }
+
+        if received.len() > MAX_HEAD_SIZE {
+            return Err(Error::new(CURLE_RECV_ERROR, "WebSocket upgrade response head too large"));
+        }
+
+        match session.read(&mut chunk) {
+            Ok(0) => return Err(Error::new(CURLE_GOT_NOTHING, "Empty reply from server")),
+            Ok(len) => received.extend_from_slice(&chunk[..len]),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(Error::new(CURLE_RECV_ERROR, e.to_string())),
+        }
+    }
+}
+
+impl WebSocket {
+    pub fn is_raw(&self) -> bool {
+        self.raw_mode
+    }
+
+    /// Receives the next part of the current frame, describing it in `meta`.
+    ///
+    /// Pings are answered and close frames are echoed, unless in raw mode,
+    /// where the bytes of the connection are passed on as they are.
+    pub fn recv(&mut self, session: &mut Session, buf: &mut [u8]) -> io::Result<usize> {
+        self.flush(session).or_else(would_block_ok)?;
+
+        if self.raw_mode {
+            return self.read_payload(session, buf);
+        }
+
+        if self.incoming.is_none() {
+            self.read_header(session)?;
+        }
+
+        let (flags, len, offset) = match &self.incoming {
+            Some(incoming) => (incoming.flags, incoming.len, incoming.offset),
+            None => unreachable!("a frame header was just read"),
+        };
+
+        let wanted = min(buf.len() as u64, len - offset) as usize;
+        let received = match wanted {
+            0 => 0,
+            _ => self.read_payload(session, &mut buf[..wanted])?,
+        };
+
+        self.meta = curl_ws_frame {
+            age: 0,
+            flags: flags as c_int,
+            offset: offset as curl_off_t,
+            bytesleft: (len - offset - received as u64) as curl_off_t,
+            len: received,
+        };
+
+        if let Some(incoming) = &mut self.incoming {
+            incoming.offset += received as u64;
+
+            if incoming.offset == incoming.len {
+                self.incoming = None;
+            }
+        }
+
+        Ok(received)
+    }
+
+    /// Sends `data` as a frame of the type in `flags`, or part of one with `CURLWS_OFFSET`.
+    ///
+    /// Frames the connection doesn't take right away are kept and sent first on the next call.
+    pub fn send(&mut self, session: &mut Session, data: &[u8], fragsize: curl_off_t, flags: c_uint) -> io::Result<usize> {
+        self.flush(session)?;
+
+        if self.raw_mode {
+            self.unsent.extend_from_slice(data);
+            self.flush(session).or_else(would_block_ok)?;
+            return Ok(data.len());
+        }
+
+        let sent = match self.outgoing.take() {
+            Some(outgoing) if flags & CURLWS_OFFSET != 0 => self.continue_frame(outgoing, data),
+            Some(outgoing) => {
+                self.outgoing = Some(outgoing);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "a partially sent frame is still pending"));
+            },
+            None => {
+                let len = match flags & CURLWS_OFFSET {
+                    0 => data.len() as u64,
+                    _ if fragsize > 0 => fragsize as u64,
+                    _ => data.len() as u64,
+                };
+
+                let mask = self.start_frame(flags, len)?;
+                self.continue_frame(Outgoing { mask, remaining: len, offset: 0 }, data)
+            },
+        };
+
+        self.flush(session).or_else(would_block_ok)?;
+
+        Ok(sent)
+    }
+
+    fn read_header(&mut self, session: &mut Session) -> io::Result<()> {
+        loop {
+            if let Some((fin, opcode, len, header_len)) = parse_header(&self.received)? {
+                let control = opcode & 0x8 != 0;
+
+                // Control frames are answered as a whole, so their payload is read up front
+                if !control || self.received.len() >= header_len + len as usize {
+                    self.received.drain(..header_len);
+                    self.start_incoming(fin, opcode, len)?;
+                    return Ok(());
+                }
+            }
+
+            self.fill(session)?;
+        }
+    }
+
+    fn start_incoming(&mut self, fin: bool, opcode: u8, len: u64) -> io::Result<()> {
+        let mut flags = match opcode {
+            OPCODE_CONT => self.message_flags,
+            OPCODE_TEXT => CURLWS_TEXT,
+            OPCODE_BINARY => CURLWS_BINARY,
+            OPCODE_CLOSE => CURLWS_CLOSE,
+            OPCODE_PING => CURLWS_PING,
+            OPCODE_PONG => CURLWS_PONG,
+            _ => return Err(invalid_data("unknown WebSocket opcode")),
+        };
+
+        match opcode {
+            OPCODE_CONT | OPCODE_TEXT | OPCODE_BINARY => {
+                self.message_flags = flags;
+                if !fin {
+                    flags |= CURLWS_CONT;
+                }
+            },
+            OPCODE_PING => {
+                let payload = self.received[..len as usize].to_vec();
+                self.queue_control(OPCODE_PONG, &payload)?;
+            },
+            OPCODE_CLOSE if !self.close_sent => {
+                // Echo the status code, like RFC 6455 suggests
+                let payload = self.received[..min(len as usize, 2)].to_vec();
+                self.queue_control(OPCODE_CLOSE, &payload)?;
+                self.close_sent = true;
+            },
+            _ => {},
+        }
+
+        self.incoming = Some(Incoming { flags, len, offset: 0 });
+
+        Ok(())
+    }
+
+    // Hands out buffered bytes first, then reads from the connection
+    fn read_payload(&mut self, session: &mut Session, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.received.is_empty() {
+            let len = min(buf.len(), self.received.len());
+            buf[..len].copy_from_slice(&self.received[..len]);
+            self.received.drain(..len);
+            return Ok(len);
+        }
+
+        match session.read(buf)? {
+            0 if !buf.is_empty() && !self.raw_mode => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed in the middle of a frame")),
+            len => Ok(len),
+        }
+    }
+
+    fn fill(&mut self, session: &mut Session) -> io::Result<()> {
+        let mut chunk = [0; 4096];
+
+        match session.read(&mut chunk)? {
+            0 => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed")),
+            len => {
+                self.received.extend_from_slice(&chunk[..len]);
+                Ok(())
+            },
+        }
+    }
+
+    // Encodes a frame header and returns the mask for its payload
+    fn start_frame(&mut self, flags: c_uint, len: u64) -> io::Result<[u8; 4]> {
+        let opcode = if flags & CURLWS_TEXT != 0 {
+            OPCODE_TEXT
+        } else if flags & CURLWS_BINARY != 0 {
+            OPCODE_BINARY
+        } else if flags & CURLWS_CLOSE != 0 {
+            OPCODE_CLOSE
+        } else if flags & CURLWS_PING != 0 {
+            OPCODE_PING
+        } else if flags & CURLWS_PONG != 0 {
+            OPCODE_PONG
+        } else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no frame type in flags"));
+        };
+
+        let control = opcode & 0x8 != 0;
+
+        if control && (flags & CURLWS_CONT != 0 || len > MAX_CONTROL_PAYLOAD as u64) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "control frames can't be fragmented or exceed 125 bytes"));
+        }
+
+        let opcode = match self.sending_message {
+            true if !control => OPCODE_CONT,
+            _ => opcode,
+        };
+
+        if !control {
+            self.sending_message = flags & CURLWS_CONT != 0;
+        }
+
+        if opcode == OPCODE_CLOSE {
+            self.close_sent = true;
+        }
+
+        let fin = flags & CURLWS_CONT == 0;
+        let mask = random_mask()?;
+        encode_header(&mut self.unsent, fin, opcode, len, mask);
+
+        Ok(mask)
+    }
+
+    // Masks and queues as much of `data` as the frame still takes
+    fn continue_frame(&mut self, mut outgoing: Outgoing, data: &[u8]) -> usize {
+        let len = min(data.len() as u64, outgoing.remaining) as usize;
+
+        for (i, &byte) in data[..len].iter().enumerate() {
+            self.unsent.push(byte ^ outgoing.mask[(outgoing.offset as usize + i) % 4]);
+        }
+
+        outgoing.offset += len as u64;
+        outgoing.remaining -= len as u64;
+
+        if outgoing.remaining > 0 {
+            self.outgoing = Some(outgoing);
+        }
+
+        len
+    }
+
+    fn queue_control(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mask = random_mask()?;
+        encode_header(&mut self.unsent, true, opcode, payload.len() as u64, mask);
+
+        for (i, &byte) in payload.iter().enumerate() {
+            self.unsent.push(byte ^ mask[i % 4]);
+        }
+
+        Ok(())
+    }
+
+    /// Sends the frames that are still queued.
+    pub fn flush(&mut self, session: &mut Session) -> io::Result<()> {
+        while !self.unsent.is_empty() {
+            match session.write(&self.unsent) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "connection closed")),
+                Ok(len) => { self.unsent.drain(..len); },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Returns fin, opcode, payload length and header length once the header is complete
+fn parse_header(bytes: &[u8]) -> io::Result<Option<(bool, u8, u64, usize)>> {
+    if bytes.len() < 2 {
+        return Ok(None);
+    }
+
+    let fin = bytes[0] & 0x80 != 0;
+    let opcode = bytes[0] & 0x0f;
+
+    if bytes[0] & 0x70 != 0 {
+        return Err(invalid_data("WebSocket extensions aren't supported"));
+    }
+
+    if bytes[1] & 0x80 != 0 {
+        return Err(invalid_data("server sent a masked frame"));
+    }
+
+    let (len, header_len) = match bytes[1] & 0x7f {
+        126 if bytes.len() < 4 => return Ok(None),
+        126 => (u64::from(u16::from_be_bytes([bytes[2], bytes[3]])), 4),
+        127 if bytes.len() < 10 => return Ok(None),
+        127 => {
+            let mut len = [0; 8];
+            len.copy_from_slice(&bytes[2..10]);
+            (u64::from_be_bytes(len), 10)
+        },
+        len => (u64::from(len), 2),
+    };
+
+    Ok(Some((fin, opcode, len, header_len)))
+}
+
+fn encode_header(output: &mut Vec<u8>, fin: bool, opcode: u8, len: u64, mask: [u8; 4]) {
+    output.push(if fin { 0x80 } else { 0 } | opcode);
+
+    // Client frames are always masked
+    match len {
+        0 ..= 125 => output.push(0x80 | len as u8),
+        126 ..= 0xffff => {
+            output.push(0x80 | 126);
+            output.extend_from_slice(&(len as u16).to_be_bytes());
+        },
+        _ => {
+            output.push(0x80 | 127);
+            output.extend_from_slice(&len.to_be_bytes());
+        },
+    }
+
+    output.extend_from_slice(&mask);
+}
+
+fn random_mask() -> io::Result<[u8; 4]> {
+    let mut mask = [0; 4];
+    rand_bytes(&mut mask).map_err(io::Error::other)?;
+    Ok(mask)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Queued data goes out with a later call
+fn would_block_ok(e: io::Error) -> io::Result<()> {
+    match e.kind() {
+        io::ErrorKind::WouldBlock => Ok(()),
+        _ => Err(e),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn curl_ws_recv(
+    curl: *mut CURL,
+    buffer: *mut c_void,
+    buflen: size_t,
+    recv: *mut size_t,
+    metap: *mut *const curl_ws_frame,
+) -> CURLcode::Type {
+    curl.borrow_raw_mut(|curl| {
+        if recv.is_null() || metap.is_null() || (buffer.is_null() && buflen > 0) {
+            return CURLE_BAD_FUNCTION_ARGUMENT;
+        }
+
+        *recv = 0;
+        *metap = null();
+
+        let buffer = match buflen {
+            0 => &mut [][..],
+            _ => slice::from_raw_parts_mut(buffer as *mut u8, buflen),
+        };
+
+        let result = match (&mut curl.session, &mut curl.websocket) {
+            (Some(session), Some(websocket)) => websocket.recv(session, buffer),
+            _ => return curl.error(CURLE_BAD_FUNCTION_ARGUMENT, "connection is not setup for websocket"),
+        };
+
+        match result {
+            Ok(len) => {
+                *recv = len;
+                *metap = curl.websocket.as_ref().map_or(null(), |websocket| &websocket.meta as *const curl_ws_frame);
+                CURLE_OK
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => CURLE_AGAIN,
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => curl.error(CURLE_GOT_NOTHING, e.to_string()),
+            Err(e) => curl.error(CURLE_RECV_ERROR, e.to_string()),
+        }
+    })
+    .unwrap_or(CURLE_BAD_FUNCTION_ARGUMENT)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn curl_ws_send(
+    curl: *mut CURL,
+    buffer: *const c_void,
+    buflen: size_t,
+    sent: *mut size_t,
+    fragsize: curl_off_t,
+    flags: c_uint,
+) -> CURLcode::Type {
+    curl.borrow_raw_mut(|curl| {
+        if sent.is_null() || (buffer.is_null() && buflen > 0) {
+            return CURLE_BAD_FUNCTION_ARGUMENT;
+        }
+
+        *sent = 0;
+
+        let buffer = match buflen {
+            0 => &[][..],
+            _ => slice::from_raw_parts(buffer as *const u8, buflen),
+        };
+
+        let result = match (&mut curl.session, &mut curl.websocket) {
+            (Some(session), Some(websocket)) => websocket.send(session, buffer, fragsize, flags),
+            _ => return curl.error(CURLE_BAD_FUNCTION_ARGUMENT, "connection is not setup for websocket"),
+        };
+
+        match result {
+            Ok(len) => { *sent = len; CURLE_OK },
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => CURLE_AGAIN,
+            Err(ref e) if e.kind() == io::ErrorKind::InvalidInput => curl.error(CURLE_BAD_FUNCTION_ARGUMENT, e.to_string()),
+            Err(e) => curl.error(CURLE_SEND_ERROR, e.to_string()),
+        }
+    })
+    .unwrap_or(CURLE_BAD_FUNCTION_ARGUMENT)
+}
+
+/// The frame being delivered, only valid within the write function.
+#[no_mangle]
+pub unsafe extern "C" fn curl_ws_meta(curl: *mut CURL) -> *const curl_ws_frame {
+    curl.borrow_raw(|curl| match &curl.websocket {
+        Some(websocket) if websocket.in_callback => &websocket.meta as *const curl_ws_frame,
+        _ => null(),
+    })
+    .unwrap_or(null())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc::{channel, Receiver};
+    use std::thread;
+    use crate::sigv4::Spec;
+    use crate::testing::{self, Server};
+    use super::*;
+
+    // The switching response for the upgrade request `request`
+    fn switching(request: &[u8]) -> Vec<u8> {
+        let request = String::from_utf8_lossy(request);
+        let key = request.lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+            .unwrap_or_default();
+        let accept = base64::encode(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()));
+
+        format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+            Sec-WebSocket-Accept: {}\r\n\r\n", accept).into_bytes()
+    }
+
+    // Reads a short masked frame from the client, returns its first byte and unmasked payload
+    fn read_frame(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+        let mut header = [0; 6];
+        stream.read_exact(&mut header)?;
+
+        let mut payload = vec![0; (header[1] & 0x7f) as usize];
+        stream.read_exact(&mut payload)?;
+
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= header[2 + i % 4];
+        }
+
+        Ok((header[0], payload))
+    }
+
+    /// Upgrades one connection and sends back every frame, until the client closes it.
+    fn echo_server() -> (String, Receiver<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/echo", listener.local_addr().unwrap());
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || -> io::Result<()> {
+            let (mut stream, _) = listener.accept()?;
+            let mut request = Vec::new();
+            let mut byte = [0];
+
+            while !request.ends_with(b"\r\n\r\n") {
+                stream.read_exact(&mut byte)?;
+                request.push(byte[0]);
+            }
+
+            stream.write_all(&switching(&request))?;
+            sender.send(request).ok();
+
+            loop {
+                let (first, payload) = read_frame(&mut stream)?;
+                stream.write_all(&[first, payload.len() as u8])?;
+                stream.write_all(&payload)?;
+
+                if first & 0x0f == OPCODE_CLOSE {
+                    return Ok(());
+                }
+            }
+        });
+
+        (url, receiver)
+    }
+
+    #[test]
+    fn upgrade_carries_the_credentials() {
+        let server = Server::http(|request| {
+            // A text frame, then a close frame without a payload
+            [switching(request), b"\x81\x05hello\x88\x00".to_vec()].concat()
+        });
+
+        let mut curl = CURL::init();
+        curl.options.url = Some(server.url("/").replacen("http", "ws", 1));
+        curl.options.username = Some("user".to_owned());
+        curl.options.password = Some("pass".to_owned());
+        curl.options.http_auth = CURLAUTH_BASIC;
+        assert_eq!(testing::perform(&mut curl), (CURLE_OK, b"hello".to_vec()));
+
+        let request = String::from_utf8(server.requests().remove(0)).unwrap();
+        assert!(request.starts_with("GET / HTTP/1.1\r\n"));
+        assert!(request.contains("\r\nauthorization: Basic dXNlcjpwYXNz\r\n"));
+        assert!(request.contains("\r\nUpgrade: websocket\r\n"));
+    }
+
+    #[test]
+    fn echoes_frames_after_a_signed_upgrade() {
+        let (url, requests) = echo_server();
+
+        let mut curl = CURL::init();
+        curl.options.url = Some(url);
+        curl.options.connect_only = 2;
+        curl.options.username = Some("AKID".to_owned());
+        curl.options.password = Some("secret".to_owned());
+        curl.options.http_auth = CURLAUTH_AWS_SIGV4;
+        curl.options.aws_sigv4 = Some(Spec::parse("aws:amz:us-east-1:execute-api").unwrap());
+        assert_eq!(curl.perform(), CURLE_OK);
+
+        let request = String::from_utf8(requests.recv().unwrap()).unwrap();
+        assert!(request.contains("\r\nauthorization: AWS4-HMAC-SHA256 Credential=AKID/"));
+        assert!(request.contains("/us-east-1/execute-api/aws4_request"));
+        assert!(request.contains("\r\nx-amz-date: "));
+
+        let (session, websocket) = match (&mut curl.session, &mut curl.websocket) {
+            (Some(session), Some(websocket)) => (session, websocket),
+            _ => panic!("the connection was not kept"),
+        };
+        session.set_nonblocking(false).unwrap();
+
+        for (message, flags) in &[(&b"hello"[..], CURLWS_TEXT), (b"\x00\x01", CURLWS_BINARY)] {
+            assert_eq!(websocket.send(session, message, 0, *flags).unwrap(), message.len());
+
+            let mut buffer = [0; 16];
+            let len = websocket.recv(session, &mut buffer).unwrap();
+            assert_eq!(&buffer[..len], *message);
+            assert_eq!(websocket.meta.flags as c_uint, *flags);
+            assert_eq!(websocket.meta.bytesleft, 0);
+        }
+
+        websocket.send(session, b"", 0, CURLWS_CLOSE).unwrap();
+        let mut buffer = [0; 16];
+        assert_eq!(websocket.recv(session, &mut buffer).unwrap(), 0);
+        assert_eq!(websocket.meta.flags as c_uint, CURLWS_CLOSE);
+    }
+}